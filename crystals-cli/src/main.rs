@@ -0,0 +1,366 @@
+//! `crystals` command-line tool for Dilithium keys and signatures.
+//!
+//! The parameter set is chosen when the binary is built, as for the
+//! library: dilithium3 by default, `--features mode2` or `mode5`
+//! otherwise. Exit status is 0 on success, 1 when a signature does not
+//! verify and 2 for any other error.
+
+mod keys;
+
+use std::{
+  fs,
+  io::{self, Read, Write},
+  path::{Path, PathBuf},
+  process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use crystals::{diagnostics, pkcs8::ALGORITHM, Keypair, PUBLICKEYBYTES};
+use keys::{Decoded, Format, Object};
+
+#[derive(Parser)]
+#[command(name = "crystals", version, about)]
+struct Cli
+{
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command
+{
+  /// Generates a keypair, writing the secret key to OUT and the public
+  /// key to OUT.pub
+  Keygen
+  {
+    /// Parameter set; must be the one this binary was built for
+    #[arg(short, long)]
+    mode: Option<Mode>,
+    #[arg(short, long, value_enum, default_value_t = Format::Pem)]
+    format: Format,
+    #[arg(short, long)]
+    out: PathBuf,
+    /// Overwrite existing files
+    #[arg(long)]
+    force: bool,
+  },
+  /// Signs FILE, or stdin, with a secret key
+  Sign
+  {
+    #[arg(short, long)]
+    key: PathBuf,
+    /// Write the signature followed by the message instead of the
+    /// signature alone
+    #[arg(long)]
+    attached: bool,
+    /// Output file, stdout by default
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    file: Option<PathBuf>,
+  },
+  /// Verifies a detached signature over FILE, or an attached signature
+  /// in FILE; stdin when FILE is missing
+  Verify
+  {
+    /// Public or secret key
+    #[arg(short = 'p', long)]
+    key: PathBuf,
+    #[arg(
+      short,
+      long,
+      required_unless_present = "attached",
+      conflicts_with = "attached"
+    )]
+    signature: Option<PathBuf>,
+    #[arg(long)]
+    attached: bool,
+    /// Write the message of a verified attached signature here
+    #[arg(short, long, requires = "attached")]
+    out: Option<PathBuf>,
+    file: Option<PathBuf>,
+  },
+  /// Derives the public key of a secret key
+  Pubkey
+  {
+    #[arg(short, long)]
+    key: PathBuf,
+    #[arg(short, long, value_enum, default_value_t = Format::Pem)]
+    format: Format,
+    /// Output file, stdout by default
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+  },
+  /// Describes a key or signature file, or stdin
+  Inspect
+  {
+    file: Option<PathBuf>
+  },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode
+{
+  #[value(name = "dilithium2", alias = "2")]
+  Dilithium2,
+  #[value(name = "dilithium3", alias = "3")]
+  Dilithium3,
+  #[value(name = "dilithium5", alias = "5")]
+  Dilithium5,
+}
+
+enum Error
+{
+  /// A signature that does not verify
+  Verify,
+  /// Anything else, with a message for stderr
+  Failed(String),
+}
+
+impl From<String> for Error
+{
+  fn from(msg: String) -> Self
+  {
+    Error::Failed(msg)
+  }
+}
+
+fn main() -> ExitCode
+{
+  match run(Cli::parse().command) {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(Error::Verify) => {
+      eprintln!("crystals: bad signature");
+      ExitCode::from(1)
+    }
+    Err(Error::Failed(msg)) => {
+      eprintln!("crystals: {}", msg);
+      ExitCode::from(2)
+    }
+  }
+}
+
+fn run(command: Command) -> Result<(), Error>
+{
+  match command {
+    Command::Keygen {
+      mode,
+      format,
+      out,
+      force,
+    } => keygen(mode, format, &out, force),
+    Command::Sign {
+      key,
+      attached,
+      out,
+      file,
+    } => {
+      let keypair = secret_key(&key)?;
+      let msg = read_input(file.as_deref())?;
+      let sig = keypair
+        .sign_checked(&msg)
+        .map_err(|err| format!("signing failed: {:?}", err))?;
+      let mut output = sig.to_vec();
+      if attached {
+        output.extend_from_slice(&msg);
+      }
+      write_output(out.as_deref(), &output)
+    }
+    Command::Verify {
+      key,
+      signature,
+      attached: _,
+      out,
+      file,
+    } => {
+      let pk = public_key(&key)?;
+      let input = read_input(file.as_deref())?;
+      let msg = match signature {
+        Some(path) => match decode_file(&path)?.object {
+          Object::Signature(sig) => {
+            crystals::verify(&sig, &input, &pk).map_err(|_| Error::Verify)?;
+            &input[..]
+          }
+          _ => return Err(not_a(&path, "signature")),
+        },
+        None => crystals::open(&input, &pk).map_err(|_| Error::Verify)?,
+      };
+      match out {
+        Some(out) => write_output(Some(&out), msg),
+        None => {
+          eprintln!("crystals: good signature");
+          Ok(())
+        }
+      }
+    }
+    Command::Pubkey { key, format, out } => {
+      let keypair = secret_key(&key)?;
+      let pk = keypair.public().try_into().unwrap();
+      write_output(out.as_deref(), &keys::encode_public(&pk, format))
+    }
+    Command::Inspect { file } => {
+      let data = read_input(file.as_deref())?;
+      let Decoded { object, encoding } = keys::decode(&data)?;
+      let mode = ALGORITHM.name();
+      let description = match object {
+        Object::Secret(keypair) => {
+          let report = diagnostics::inspect_public_key(keypair.public())
+            .expect("length checked");
+          format!("{} secret key, {}\n{}", mode, encoding, report)
+        }
+        Object::Public(pk) => {
+          let report =
+            diagnostics::inspect_public_key(&pk[..]).expect("length checked");
+          format!("{} public key, {}\n{}", mode, encoding, report)
+        }
+        Object::Signature(sig) => {
+          let report =
+            diagnostics::inspect_signature(&sig[..]).expect("length checked");
+          format!("{} signature, {}\n{}", mode, encoding, report)
+        }
+      };
+      println!("{}", description);
+      Ok(())
+    }
+  }
+}
+
+fn keygen(
+  mode: Option<Mode>,
+  format: Format,
+  out: &Path,
+  force: bool,
+) -> Result<(), Error>
+{
+  let build = ALGORITHM.name();
+  if let Some(mode) = mode {
+    let name = mode.to_possible_value().unwrap().get_name().to_string();
+    if name != build {
+      return Err(Error::Failed(format!(
+        "{} is not available in this build ({}); rebuild with \
+         --features mode{}",
+        name,
+        build,
+        &name[name.len() - 1..]
+      )));
+    }
+  }
+  let keypair = Keypair::try_generate()
+    .map_err(|err| format!("self test failed: {:?}", err))?;
+  let pk = keypair.public().try_into().unwrap();
+  let mut pub_path = out.as_os_str().to_owned();
+  pub_path.push(".pub");
+
+  write_new(out, &keys::encode_secret(&keypair, format), force, true)?;
+  write_new(
+    pub_path.as_ref(),
+    &keys::encode_public(&pk, format),
+    force,
+    false,
+  )?;
+  eprintln!("{} key, fingerprint: {}", build, keys::fingerprint(&pk));
+  Ok(())
+}
+
+/// Loads a keypair from a secret key file
+fn secret_key(path: &Path) -> Result<Keypair, Error>
+{
+  match decode_file(path)?.object {
+    Object::Secret(keypair) => Ok(keypair),
+    _ => Err(not_a(path, "secret key")),
+  }
+}
+
+/// Loads a public key, or the public half of a secret key
+fn public_key(path: &Path) -> Result<[u8; PUBLICKEYBYTES], Error>
+{
+  match decode_file(path)?.object {
+    Object::Public(pk) => Ok(*pk),
+    Object::Secret(keypair) => Ok(keypair.public().try_into().unwrap()),
+    Object::Signature(_) => Err(not_a(path, "key")),
+  }
+}
+
+fn decode_file(path: &Path) -> Result<Decoded, Error>
+{
+  let data = fs::read(path).map_err(|err| io_error(path, err))?;
+  keys::decode(&data)
+    .map_err(|msg| Error::Failed(format!("{}: {}", path.display(), msg)))
+}
+
+/// Reads FILE, or stdin for none or `-`
+fn read_input(path: Option<&Path>) -> Result<Vec<u8>, Error>
+{
+  match path {
+    Some(path) if path != Path::new("-") => {
+      fs::read(path).map_err(|err| io_error(path, err))
+    }
+    _ => {
+      let mut data = vec![];
+      io::stdin()
+        .read_to_end(&mut data)
+        .map_err(|err| io_error(Path::new("stdin"), err))?;
+      Ok(data)
+    }
+  }
+}
+
+/// Writes to the file, or stdout for none or `-`
+fn write_output(path: Option<&Path>, data: &[u8]) -> Result<(), Error>
+{
+  match path {
+    Some(path) if path != Path::new("-") => {
+      fs::write(path, data).map_err(|err| io_error(path, err))
+    }
+    _ => io::stdout()
+      .write_all(data)
+      .map_err(|err| io_error(Path::new("stdout"), err)),
+  }
+}
+
+/// Creates a key file, readable by the owner only if it is secret. With
+/// `force`, an existing file is replaced by a new one rather than
+/// truncated, so that it does not keep its old permissions.
+fn write_new(
+  path: &Path,
+  data: &[u8],
+  force: bool,
+  secret: bool,
+) -> Result<(), Error>
+{
+  if !force {
+    return create(path, data, secret).map_err(|err| io_error(path, err));
+  }
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(format!(".{}.tmp", std::process::id()));
+  let tmp = PathBuf::from(tmp);
+  create(&tmp, data, secret)
+    .and_then(|()| fs::rename(&tmp, path))
+    .map_err(|err| {
+      let _ = fs::remove_file(&tmp);
+      io_error(path, err)
+    })
+}
+
+fn create(path: &Path, data: &[u8], secret: bool) -> io::Result<()>
+{
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  if secret {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  #[cfg(not(unix))]
+  let _ = secret;
+  options.open(path).and_then(|mut file| file.write_all(data))
+}
+
+fn not_a(path: &Path, what: &str) -> Error
+{
+  Error::Failed(format!("{}: not a {}", path.display(), what))
+}
+
+fn io_error(path: &Path, err: io::Error) -> Error
+{
+  Error::Failed(format!("{}: {}", path.display(), err))
+}
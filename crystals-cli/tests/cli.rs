@@ -0,0 +1,177 @@
+use std::{
+  fs,
+  io::Write,
+  path::{Path, PathBuf},
+  process::{Command, Output, Stdio},
+};
+
+/// Runs the binary with `stdin` as input
+fn crystals(args: &[&str], stdin: &[u8]) -> Output
+{
+  let mut child = Command::new(env!("CARGO_BIN_EXE_crystals"))
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  // the binary may exit before reading, e.g. on usage errors
+  let _ = child.stdin.take().unwrap().write_all(stdin);
+  child.wait_with_output().unwrap()
+}
+
+fn status(output: &Output) -> i32
+{
+  output.status.code().unwrap()
+}
+
+/// Empty scratch directory for one test
+fn scratch(name: &str) -> PathBuf
+{
+  let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+fn path(dir: &Path, name: &str) -> String
+{
+  dir.join(name).to_str().unwrap().to_string()
+}
+
+#[test]
+fn keygen_formats()
+{
+  let dir = scratch("keygen_formats");
+  for format in ["raw", "pem", "json"] {
+    let key = path(&dir, format);
+    let pub_key = format!("{}.pub", key);
+    assert_eq!(
+      status(&crystals(&["keygen", "-f", format, "-o", &key], b"")),
+      0
+    );
+
+    let secret = crystals(&["inspect", &key], b"");
+    let public = crystals(&["inspect", &pub_key], b"");
+    let secret = String::from_utf8(secret.stdout).unwrap();
+    let public = String::from_utf8(public.stdout).unwrap();
+    assert!(secret.contains(" secret key, "), "{}", secret);
+    assert!(public.contains(" public key, "), "{}", public);
+    // same rho and fingerprint lines
+    assert_eq!(
+      secret.lines().skip(1).collect::<Vec<_>>(),
+      public.lines().skip(1).collect::<Vec<_>>()
+    );
+    let fingerprint = secret.lines().nth(2).unwrap();
+    assert!(fingerprint.starts_with("fingerprint: "), "{}", secret);
+
+    let derived = crystals(&["pubkey", "-k", &key, "-f", format], b"");
+    assert_eq!(derived.stdout, fs::read(&pub_key).unwrap());
+  }
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(dir.join("pem")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+  }
+}
+
+#[test]
+fn sign_verify()
+{
+  let dir = scratch("sign_verify");
+  let key = path(&dir, "key");
+  let pub_key = format!("{}.pub", key);
+  let sig = path(&dir, "sig");
+  let msg = path(&dir, "msg");
+  assert_eq!(status(&crystals(&["keygen", "-o", &key], b"")), 0);
+  fs::write(&msg, b"crystals").unwrap();
+
+  // detached, from a file and from stdin
+  let out = crystals(&["sign", "-k", &key, "-o", &sig, &msg], b"");
+  assert_eq!(status(&out), 0);
+  let out = crystals(&["verify", "-p", &pub_key, "-s", &sig, &msg], b"");
+  assert_eq!(status(&out), 0);
+  let out = crystals(&["verify", "-p", &pub_key, "-s", &sig], b"crystals");
+  assert_eq!(status(&out), 0);
+  let out = crystals(&["verify", "-p", &key, "-s", &sig, "-"], b"crystals");
+  assert_eq!(status(&out), 0);
+  let out = crystals(&["verify", "-p", &pub_key, "-s", &sig], b"crystalz");
+  assert_eq!(status(&out), 1);
+  let out = crystals(&["inspect", &sig], b"");
+  let report = String::from_utf8(out.stdout).unwrap();
+  assert!(report.ends_with("violations: none\n"), "{}", report);
+
+  // attached
+  let signed = crystals(&["sign", "-k", &key, "--attached"], b"crystals");
+  assert_eq!(status(&signed), 0);
+  assert!(signed.stdout.ends_with(b"crystals"));
+  let args = ["verify", "-p", &pub_key, "--attached", "-o", "-"];
+  let out = crystals(&args, &signed.stdout);
+  assert_eq!((status(&out), &out.stdout[..]), (0, &b"crystals"[..]));
+  let mut tampered = signed.stdout.clone();
+  *tampered.last_mut().unwrap() ^= 1;
+  let out = crystals(&args, &tampered);
+  assert_eq!((status(&out), out.stdout.len()), (1, 0));
+  assert_eq!(status(&crystals(&args, b"short")), 1);
+}
+
+#[test]
+fn errors()
+{
+  let dir = scratch("errors");
+  let key = path(&dir, "key");
+  let pub_key = format!("{}.pub", key);
+  assert_eq!(status(&crystals(&["keygen", "-o", &key], b"")), 0);
+  let before = fs::read(&key).unwrap();
+
+  // existing files are kept unless forced
+  assert_eq!(status(&crystals(&["keygen", "-o", &key], b"")), 2);
+  assert_eq!(fs::read(&key).unwrap(), before);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(&key, fs::Permissions::from_mode(0o644)).unwrap();
+  }
+  let forced = crystals(&["keygen", "-f", "raw", "-o", &key, "--force"], b"");
+  assert_eq!(status(&forced), 0);
+  assert_ne!(fs::read(&key).unwrap(), before);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(&key).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+  }
+
+  // only the build's mode
+  let inspect = crystals(&["inspect", &key], b"");
+  let build = String::from_utf8(inspect.stdout).unwrap();
+  let build = build.split(' ').next().unwrap().to_string();
+  for mode in ["dilithium2", "3", "dilithium5"] {
+    let name = match mode {
+      "3" => "dilithium3",
+      _ => mode,
+    };
+    let other = path(&dir, name);
+    let out = crystals(&["keygen", "-m", mode, "-o", &other], b"");
+    assert_eq!(status(&out) == 0, name == build, "{}", mode);
+  }
+
+  // wrong kinds of file
+  let out = crystals(&["sign", "-k", &pub_key], b"msg");
+  assert_eq!(status(&out), 2);
+  let err = String::from_utf8(out.stderr).unwrap();
+  assert!(err.contains("not a secret key"), "{}", err);
+  let out = crystals(&["inspect"], &[0u8; 1000]);
+  assert_eq!(status(&out), 2);
+  // a signature of another mode is named
+  let (other, len) = match build.as_str() {
+    "dilithium5" => ("dilithium2 signature", 2420),
+    _ => ("dilithium5 signature", 4595),
+  };
+  let out = crystals(&["inspect"], &vec![0u8; len]);
+  let err = String::from_utf8(out.stderr).unwrap();
+  assert!(err.contains(other), "{}", err);
+  let out = crystals(&["verify", "-p", &pub_key], b"msg");
+  assert_eq!(status(&out), 2);
+}
@@ -0,0 +1,316 @@
+//! Python bindings, imported as `crystals`.
+//!
+//! Keys, messages and signatures go in and out as `bytes`. The parameter
+//! set is fixed when the extension is built, like the library's:
+//! dilithium3 by default, `--features mode2` or `mode5` otherwise.
+//! `crystals.MODE` names it, and asking for another mode raises
+//! `ValueError` rather than silently using the build's.
+//!
+//! Malformed input raises `ValueError`; a failed self test or a fault
+//! caught while signing raises `RuntimeError`. A signature of the right
+//! length that does not verify is `False`, not an exception.
+
+use crystals::{
+  pkcs8::{self, PrivateKey, ALGORITHM},
+  EncodingError, KeypairError, SignError, PUBLICKEYBYTES, SECRETKEYBYTES,
+  SEEDBYTES, SIGNBYTES,
+};
+use pyo3::{
+  exceptions::{PyRuntimeError, PyValueError},
+  prelude::*,
+  types::PyBytes,
+};
+
+/// Accepts the build's mode as "dilithium3" or "3"
+fn check_mode(mode: Option<&str>) -> PyResult<()>
+{
+  let build = ALGORITHM.name();
+  match mode {
+    None => Ok(()),
+    Some(mode) if mode == build || mode == &build[build.len() - 1..] => Ok(()),
+    Some(mode) => Err(PyValueError::new_err(format!(
+      "mode {:?} is not available in this build ({}); rebuild with \
+       --features mode2, mode3 or mode5",
+      mode, build
+    ))),
+  }
+}
+
+fn exact<'a, const LEN: usize>(
+  bytes: &'a [u8],
+  what: &str,
+) -> PyResult<&'a [u8; LEN]>
+{
+  bytes.try_into().map_err(|_| {
+    PyValueError::new_err(format!(
+      "{} must be {} bytes for {}, got {}",
+      what,
+      LEN,
+      ALGORITHM.name(),
+      bytes.len()
+    ))
+  })
+}
+
+fn encoding_error(err: EncodingError) -> PyErr
+{
+  PyValueError::new_err(match err {
+    EncodingError::Der => "malformed DER".to_string(),
+    EncodingError::Pem => "malformed PEM or unexpected label".to_string(),
+    EncodingError::UnsupportedAlgorithm => {
+      format!("key of another algorithm than {}", ALGORITHM.name())
+    }
+    EncodingError::InvalidKey => {
+      format!("wrong key length for {}", ALGORITHM.name())
+    }
+  })
+}
+
+fn keypair_error(err: KeypairError) -> PyErr
+{
+  match err {
+    KeypairError::InvalidPublicKey => {
+      PyValueError::new_err("invalid public key")
+    }
+    KeypairError::InvalidSecretKey => {
+      PyValueError::new_err("inconsistent secret key")
+    }
+    KeypairError::SelfTest(err) => self_test(err),
+  }
+}
+
+fn sign_error(err: SignError) -> PyErr
+{
+  match err {
+    SignError::SelfTest(err) => self_test(err),
+    SignError::Fault => {
+      PyRuntimeError::new_err("signature failed its own verification")
+    }
+    err => PyValueError::new_err(format!("{:?}", err)),
+  }
+}
+
+fn self_test(err: crystals::SelfTestError) -> PyErr
+{
+  PyRuntimeError::new_err(format!("self test failed: {:?}", err))
+}
+
+/// A Dilithium secret key and its public key
+#[pyclass(module = "crystals", frozen, eq)]
+#[derive(PartialEq)]
+struct Keypair
+{
+  inner: crystals::Keypair,
+}
+
+#[pymethods]
+impl Keypair
+{
+  /// Generates a keypair from the operating system's randomness.
+  /// `mode`, if given, must name the build's parameter set.
+  #[staticmethod]
+  #[pyo3(signature = (mode = None))]
+  fn generate(mode: Option<&str>) -> PyResult<Self>
+  {
+    check_mode(mode)?;
+    let inner = crystals::Keypair::try_generate().map_err(self_test)?;
+    Ok(Keypair { inner })
+  }
+
+  /// Derives the keypair of a 32-byte seed
+  #[staticmethod]
+  #[pyo3(signature = (seed, mode = None))]
+  fn from_seed(seed: &[u8], mode: Option<&str>) -> PyResult<Self>
+  {
+    check_mode(mode)?;
+    let inner = crystals::Keypair::try_from_seed(exact(seed, "seed")?)
+      .map_err(self_test)?;
+    Ok(Keypair { inner })
+  }
+
+  /// Restores a keypair from a packed secret key; the public key is
+  /// recomputed from it
+  #[staticmethod]
+  fn from_secret_key(secret_key: &[u8]) -> PyResult<Self>
+  {
+    let sk: &[u8; SECRETKEYBYTES] = exact(secret_key, "secret key")?;
+    restore(&PrivateKey::Expanded(sk.to_vec()))
+  }
+
+  /// Restores a keypair from PKCS#8 DER
+  #[staticmethod]
+  fn from_pkcs8_der(der: &[u8]) -> PyResult<Self>
+  {
+    restore(&pkcs8::private_key_from_der(der).map_err(encoding_error)?)
+  }
+
+  /// Restores a keypair from PKCS#8 PEM
+  #[staticmethod]
+  fn from_pkcs8_pem(pem: &str) -> PyResult<Self>
+  {
+    restore(&pkcs8::private_key_from_pem(pem).map_err(encoding_error)?)
+  }
+
+  /// Restores a keypair from the library's serde JSON. The stored
+  /// public key must match the secret key.
+  #[staticmethod]
+  fn from_json(json: &str) -> PyResult<Self>
+  {
+    // deserializing checks the public half against the secret key
+    let inner: crystals::Keypair = serde_json::from_str(json)
+      .map_err(|err| PyValueError::new_err(format!("bad JSON: {}", err)))?;
+    Ok(Keypair { inner })
+  }
+
+  #[getter]
+  fn public_key<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes>
+  {
+    PyBytes::new(py, self.inner.public())
+  }
+
+  #[getter]
+  fn secret_key<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes>
+  {
+    PyBytes::new(py, self.inner.expose_secret())
+  }
+
+  /// Detached signature of `message`, checked against the public key
+  /// before it is returned
+  fn sign<'py>(
+    &self,
+    py: Python<'py>,
+    message: &[u8],
+  ) -> PyResult<Bound<'py, PyBytes>>
+  {
+    let sig = py
+      .allow_threads(|| self.inner.sign_checked(message))
+      .map_err(sign_error)?;
+    Ok(PyBytes::new(py, &sig))
+  }
+
+  /// Whether `signature` is this key's signature of `message`
+  fn verify(
+    &self,
+    py: Python<'_>,
+    signature: &[u8],
+    message: &[u8],
+  ) -> PyResult<bool>
+  {
+    verify(py, signature, message, self.inner.public())
+  }
+
+  /// PKCS#8 DER of the expanded secret key
+  fn to_pkcs8_der<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes>
+  {
+    let der = pkcs8::private_key_to_der(&self.inner.to_private_key())
+      .expect("keypair has the build's key length");
+    PyBytes::new(py, &der)
+  }
+
+  /// PKCS#8 PEM of the expanded secret key
+  fn to_pkcs8_pem(&self) -> String
+  {
+    pkcs8::private_key_to_pem(&self.inner.to_private_key())
+      .expect("keypair has the build's key length")
+  }
+
+  /// The library's serde JSON, holding both keys in hex
+  fn to_json(&self) -> String
+  {
+    serde_json::to_string(&self.inner).expect("keypair serializes")
+  }
+
+  /// Secret key elided
+  fn __repr__(&self) -> String
+  {
+    let fingerprint = crystals::diagnostics::fingerprint(self.inner.public());
+    let hex: String =
+      fingerprint.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("Keypair({}, {})", ALGORITHM.name(), hex)
+  }
+}
+
+fn restore(key: &PrivateKey) -> PyResult<Keypair>
+{
+  let inner =
+    crystals::Keypair::from_private_key(key).map_err(keypair_error)?;
+  Ok(Keypair { inner })
+}
+
+/// Whether `signature` is the signature of `message` by `public_key`.
+/// Raises ValueError if the signature or key has the wrong length.
+#[pyfunction]
+fn verify(
+  py: Python<'_>,
+  signature: &[u8],
+  message: &[u8],
+  public_key: &[u8],
+) -> PyResult<bool>
+{
+  let sig: &[u8; SIGNBYTES] = exact(signature, "signature")?;
+  let pk: &[u8; PUBLICKEYBYTES] = exact(public_key, "public key")?;
+  match py.allow_threads(|| crystals::verify(sig, message, pk)) {
+    Ok(()) => Ok(true),
+    Err(SignError::SelfTest(err)) => Err(self_test(err)),
+    // a malformed signature does not verify either
+    Err(_) => Ok(false),
+  }
+}
+
+/// SubjectPublicKeyInfo DER of a packed public key
+#[pyfunction]
+fn public_key_to_der<'py>(
+  py: Python<'py>,
+  public_key: &[u8],
+) -> PyResult<Bound<'py, PyBytes>>
+{
+  let der = pkcs8::public_key_to_der(public_key).map_err(encoding_error)?;
+  Ok(PyBytes::new(py, &der))
+}
+
+/// Packed public key of SubjectPublicKeyInfo DER
+#[pyfunction]
+fn public_key_from_der<'py>(
+  py: Python<'py>,
+  der: &[u8],
+) -> PyResult<Bound<'py, PyBytes>>
+{
+  let pk = pkcs8::public_key_from_der(der).map_err(encoding_error)?;
+  Ok(PyBytes::new(py, &pk))
+}
+
+/// SubjectPublicKeyInfo PEM of a packed public key
+#[pyfunction]
+fn public_key_to_pem(public_key: &[u8]) -> PyResult<String>
+{
+  pkcs8::public_key_to_pem(public_key).map_err(encoding_error)
+}
+
+/// Packed public key of SubjectPublicKeyInfo PEM
+#[pyfunction]
+fn public_key_from_pem<'py>(
+  py: Python<'py>,
+  pem: &str,
+) -> PyResult<Bound<'py, PyBytes>>
+{
+  let pk = pkcs8::public_key_from_pem(pem).map_err(encoding_error)?;
+  Ok(PyBytes::new(py, &pk))
+}
+
+#[pymodule]
+#[pyo3(name = "crystals")]
+fn crystals_py(m: &Bound<'_, PyModule>) -> PyResult<()>
+{
+  m.add("MODE", ALGORITHM.name())?;
+  m.add("PUBLIC_KEY_BYTES", PUBLICKEYBYTES)?;
+  m.add("SECRET_KEY_BYTES", SECRETKEYBYTES)?;
+  m.add("SIGNATURE_BYTES", SIGNBYTES)?;
+  m.add("SEED_BYTES", SEEDBYTES)?;
+  m.add_class::<Keypair>()?;
+  m.add_function(wrap_pyfunction!(verify, m)?)?;
+  m.add_function(wrap_pyfunction!(public_key_to_der, m)?)?;
+  m.add_function(wrap_pyfunction!(public_key_from_der, m)?)?;
+  m.add_function(wrap_pyfunction!(public_key_to_pem, m)?)?;
+  m.add_function(wrap_pyfunction!(public_key_from_pem, m)?)?;
+  Ok(())
+}
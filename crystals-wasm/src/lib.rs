@@ -0,0 +1,280 @@
+//! WebAssembly bindings for browser and Node wallets.
+//!
+//! Keys, messages and signatures are `Uint8Array`s, and names follow
+//! JavaScript conventions (`Keypair.fromSeed`, `publicKeyToPem`). The
+//! parameter set is fixed when the module is built, like the library's:
+//! dilithium3 by default, `--features mode2` or `mode5` otherwise; `mode()`
+//! names it. Randomness for `Keypair.generate` comes from
+//! `crypto.getRandomValues` through getrandom's JS backend.
+//!
+//! Malformed input throws an `Error`. A signature of the right length
+//! that does not verify is `false`, not an exception.
+
+use std::fmt;
+
+use crystals::{
+  pkcs8::{self, PrivateKey, ALGORITHM},
+  EncodingError, KeypairError, SignError, PUBLICKEYBYTES, SECRETKEYBYTES,
+  SEEDBYTES, SIGNBYTES,
+};
+use wasm_bindgen::prelude::*;
+
+/// Thrown to JavaScript as an `Error` with this message
+#[derive(Debug, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    f.write_str(&self.0)
+  }
+}
+
+impl From<Error> for JsValue
+{
+  fn from(err: Error) -> JsValue
+  {
+    JsError::new(&err.0).into()
+  }
+}
+
+impl From<EncodingError> for Error
+{
+  fn from(err: EncodingError) -> Error
+  {
+    Error(match err {
+      EncodingError::Der => "malformed DER".to_string(),
+      EncodingError::Pem => "malformed PEM or unexpected label".to_string(),
+      EncodingError::UnsupportedAlgorithm => {
+        format!("key of another algorithm than {}", mode())
+      }
+      EncodingError::InvalidKey => format!("wrong key length for {}", mode()),
+    })
+  }
+}
+
+impl From<KeypairError> for Error
+{
+  fn from(err: KeypairError) -> Error
+  {
+    match err {
+      KeypairError::InvalidPublicKey => Error("invalid public key".into()),
+      KeypairError::InvalidSecretKey => Error("inconsistent secret key".into()),
+      KeypairError::SelfTest(err) => err.into(),
+    }
+  }
+}
+
+impl From<SignError> for Error
+{
+  fn from(err: SignError) -> Error
+  {
+    Error(match err {
+      SignError::SelfTest(err) => format!("self test failed: {:?}", err),
+      SignError::Fault => "signature failed its own verification".to_string(),
+      err => format!("{:?}", err),
+    })
+  }
+}
+
+impl From<crystals::SelfTestError> for Error
+{
+  fn from(err: crystals::SelfTestError) -> Error
+  {
+    SignError::SelfTest(err).into()
+  }
+}
+
+fn exact<'a, const LEN: usize>(
+  bytes: &'a [u8],
+  what: &str,
+) -> Result<&'a [u8; LEN], Error>
+{
+  bytes.try_into().map_err(|_| {
+    Error(format!(
+      "{} must be {} bytes for {}, got {}",
+      what,
+      LEN,
+      mode(),
+      bytes.len()
+    ))
+  })
+}
+
+/// Parameter set of the module: "dilithium2", "dilithium3" or
+/// "dilithium5"
+#[wasm_bindgen]
+pub fn mode() -> String
+{
+  ALGORITHM.name().to_string()
+}
+
+#[wasm_bindgen(js_name = publicKeyBytes)]
+pub fn public_key_bytes() -> usize
+{
+  PUBLICKEYBYTES
+}
+
+#[wasm_bindgen(js_name = secretKeyBytes)]
+pub fn secret_key_bytes() -> usize
+{
+  SECRETKEYBYTES
+}
+
+#[wasm_bindgen(js_name = signatureBytes)]
+pub fn signature_bytes() -> usize
+{
+  SIGNBYTES
+}
+
+#[wasm_bindgen(js_name = seedBytes)]
+pub fn seed_bytes() -> usize
+{
+  SEEDBYTES
+}
+
+/// A Dilithium secret key and its public key
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Keypair
+{
+  inner: crystals::Keypair,
+}
+
+#[wasm_bindgen]
+impl Keypair
+{
+  /// Generates a keypair from `crypto.getRandomValues`
+  pub fn generate() -> Result<Keypair, Error>
+  {
+    let inner = crystals::Keypair::try_generate()?;
+    Ok(Keypair { inner })
+  }
+
+  /// Derives the keypair of a 32-byte seed
+  #[wasm_bindgen(js_name = fromSeed)]
+  pub fn from_seed(seed: &[u8]) -> Result<Keypair, Error>
+  {
+    let inner = crystals::Keypair::try_from_seed(exact(seed, "seed")?)?;
+    Ok(Keypair { inner })
+  }
+
+  /// Restores a keypair from a packed secret key; the public key is
+  /// recomputed from it
+  #[wasm_bindgen(js_name = fromSecretKey)]
+  pub fn from_secret_key(secret_key: &[u8]) -> Result<Keypair, Error>
+  {
+    let sk: &[u8; SECRETKEYBYTES] = exact(secret_key, "secret key")?;
+    restore(&PrivateKey::Expanded(sk.to_vec()))
+  }
+
+  /// Restores a keypair from PKCS#8 DER
+  #[wasm_bindgen(js_name = fromPkcs8Der)]
+  pub fn from_pkcs8_der(der: &[u8]) -> Result<Keypair, Error>
+  {
+    restore(&pkcs8::private_key_from_der(der)?)
+  }
+
+  /// Restores a keypair from PKCS#8 PEM
+  #[wasm_bindgen(js_name = fromPkcs8Pem)]
+  pub fn from_pkcs8_pem(pem: &str) -> Result<Keypair, Error>
+  {
+    restore(&pkcs8::private_key_from_pem(pem)?)
+  }
+
+  #[wasm_bindgen(getter, js_name = publicKey)]
+  pub fn public_key(&self) -> Vec<u8>
+  {
+    self.inner.public().to_vec()
+  }
+
+  #[wasm_bindgen(getter, js_name = secretKey)]
+  pub fn secret_key(&self) -> Vec<u8>
+  {
+    self.inner.expose_secret().to_vec()
+  }
+
+  /// Detached signature of `message`, checked against the public key
+  /// before it is returned
+  pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error>
+  {
+    Ok(self.inner.sign_checked(message)?.to_vec())
+  }
+
+  /// Whether `signature` is this key's signature of `message`
+  pub fn verify(&self, signature: &[u8], message: &[u8])
+    -> Result<bool, Error>
+  {
+    verify(signature, message, self.inner.public())
+  }
+
+  /// PKCS#8 DER of the expanded secret key
+  #[wasm_bindgen(js_name = toPkcs8Der)]
+  pub fn to_pkcs8_der(&self) -> Vec<u8>
+  {
+    pkcs8::private_key_to_der(&self.inner.to_private_key())
+      .expect("keypair has the build's key length")
+  }
+
+  /// PKCS#8 PEM of the expanded secret key
+  #[wasm_bindgen(js_name = toPkcs8Pem)]
+  pub fn to_pkcs8_pem(&self) -> String
+  {
+    pkcs8::private_key_to_pem(&self.inner.to_private_key())
+      .expect("keypair has the build's key length")
+  }
+}
+
+fn restore(key: &PrivateKey) -> Result<Keypair, Error>
+{
+  let inner = crystals::Keypair::from_private_key(key)?;
+  Ok(Keypair { inner })
+}
+
+/// Whether `signature` is the signature of `message` by `publicKey`.
+/// Throws if the signature or key has the wrong length.
+#[wasm_bindgen]
+pub fn verify(
+  signature: &[u8],
+  message: &[u8],
+  #[wasm_bindgen(js_name = publicKey)] public_key: &[u8],
+) -> Result<bool, Error>
+{
+  let sig: &[u8; SIGNBYTES] = exact(signature, "signature")?;
+  let pk: &[u8; PUBLICKEYBYTES] = exact(public_key, "public key")?;
+  match crystals::verify(sig, message, pk) {
+    Ok(()) => Ok(true),
+    Err(SignError::SelfTest(err)) => Err(err.into()),
+    // a malformed signature does not verify either
+    Err(_) => Ok(false),
+  }
+}
+
+/// SubjectPublicKeyInfo DER of a packed public key
+#[wasm_bindgen(js_name = publicKeyToDer)]
+pub fn public_key_to_der(public_key: &[u8]) -> Result<Vec<u8>, Error>
+{
+  Ok(pkcs8::public_key_to_der(public_key)?)
+}
+
+/// Packed public key of SubjectPublicKeyInfo DER
+#[wasm_bindgen(js_name = publicKeyFromDer)]
+pub fn public_key_from_der(der: &[u8]) -> Result<Vec<u8>, Error>
+{
+  Ok(pkcs8::public_key_from_der(der)?)
+}
+
+/// SubjectPublicKeyInfo PEM of a packed public key
+#[wasm_bindgen(js_name = publicKeyToPem)]
+pub fn public_key_to_pem(public_key: &[u8]) -> Result<String, Error>
+{
+  Ok(pkcs8::public_key_to_pem(public_key)?)
+}
+
+/// Packed public key of SubjectPublicKeyInfo PEM
+#[wasm_bindgen(js_name = publicKeyFromPem)]
+pub fn public_key_from_pem(pem: &str) -> Result<Vec<u8>, Error>
+{
+  Ok(pkcs8::public_key_from_pem(pem)?)
+}
@@ -0,0 +1,379 @@
+//! Benchmarks for the mode selected at compile time. Run once per mode:
+//!
+//! ```text
+//! cargo bench --features bench,offchain,mode2
+//! cargo bench --features bench,offchain
+//! cargo bench --features bench,offchain,mode5
+//! ```
+//!
+//! Before timing, every operation runs once under a counting allocator
+//! and the number of heap allocations and bytes is printed.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{
+  criterion_group, criterion_main, BatchSize, Criterion, Throughput,
+};
+use crystals::bench::*;
+use crystals::sha3::{Shake128, Shake256};
+use crystals::*;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc
+{
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8
+  {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+    System.alloc(layout)
+  }
+
+  unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8
+  {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+    System.alloc_zeroed(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+  {
+    System.dealloc(ptr, layout)
+  }
+
+  unsafe fn realloc(
+    &self,
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+  ) -> *mut u8
+  {
+    // counted as a fresh allocation of the new size
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+    System.realloc(ptr, layout, new_size)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Runs `setup`, then `op` on its output, and returns the number of
+/// allocations and bytes requested by `op` alone.
+fn count_allocations<I, R>(
+  setup: impl FnOnce() -> I,
+  op: impl FnOnce(I) -> R,
+) -> (usize, usize)
+{
+  let input = setup();
+  let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+  let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+  let result = op(input);
+  let counts = (
+    ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+  );
+  drop(black_box(result));
+  counts
+}
+
+fn mode() -> &'static str
+{
+  match K {
+    4 => "mode2",
+    6 => "mode3",
+    _ => "mode5",
+  }
+}
+
+const MSG: &[u8] = b"crystals benchmark message";
+
+/// Everything the verify stages consume, produced by running the
+/// earlier stages on a valid signature.
+struct Staged
+{
+  sig: [u8; SIGNBYTES],
+  pk: [u8; PUBLICKEYBYTES],
+  rho: Box<[u8; SEEDBYTES]>,
+  t1: Box<Polyveck>,
+  c: Box<[u8; SEEDBYTES]>,
+  z: Box<Polyvecl>,
+  h: Box<Polyveck>,
+  mu: Box<[u8; CRHBYTES]>,
+  cp: Box<Poly>,
+  mat: Box<[Polyvecl; K]>,
+  w1: Box<Polyveck>,
+  w1_reduced: Box<Polyveck>,
+}
+
+impl Staged
+{
+  fn stage6_inputs(&self) -> (Poly, [Polyvecl; K], Polyvecl, Polyveck)
+  {
+    let (cp, mat) = ((*self.cp).clone(), (*self.mat).clone());
+    (cp, mat, (*self.z).clone(), (*self.t1).clone())
+  }
+
+  fn new() -> Self
+  {
+    let keypair = Keypair::generate();
+    let sig = keypair.sign(MSG);
+    let pk: [u8; PUBLICKEYBYTES] = keypair.public().try_into().unwrap();
+    let (mut rho, t1) = crypto_sign_verify_stage1(&sig, &pk).unwrap();
+    let (mut c, z, h) = crypto_sign_verify_stage2(&sig).unwrap();
+    let mu = crypto_sign_verify_stage3(&pk, MSG);
+    let cp = crypto_sign_verify_stage4(&mut c);
+    let mat = crypto_sign_verify_stage5(&mut rho);
+    let w1 = crypto_sign_verify_stage6(
+      (*cp).clone(),
+      (*mat).clone(),
+      (*z).clone(),
+      (*t1).clone(),
+    )
+    .unwrap();
+    let w1_reduced = crypto_sign_verify_stage7(w1.clone()).unwrap();
+    Staged {
+      sig,
+      pk,
+      rho,
+      t1,
+      c,
+      z,
+      h,
+      mu,
+      cp,
+      mat,
+      w1,
+      w1_reduced,
+    }
+  }
+}
+
+fn report_allocations(_: &mut Criterion)
+{
+  let keypair = Keypair::generate();
+  let s = Staged::new();
+  let none = || ();
+  let rows = [
+    ("keygen", count_allocations(none, |_| Keypair::generate())),
+    ("sign", count_allocations(none, |_| keypair.sign(MSG))),
+    (
+      "verify",
+      count_allocations(none, |_| verify(&s.sig, MSG, &s.pk)),
+    ),
+    (
+      "verify_stage1",
+      count_allocations(none, |_| crypto_sign_verify_stage1(&s.sig, &s.pk)),
+    ),
+    (
+      "verify_stage2",
+      count_allocations(none, |_| crypto_sign_verify_stage2(&s.sig)),
+    ),
+    (
+      "verify_stage3",
+      count_allocations(none, |_| crypto_sign_verify_stage3(&s.pk, MSG)),
+    ),
+    (
+      "verify_stage4",
+      count_allocations(|| *s.c, |mut c| crypto_sign_verify_stage4(&mut c)),
+    ),
+    (
+      "verify_stage5",
+      count_allocations(
+        || *s.rho,
+        |mut rho| crypto_sign_verify_stage5(&mut rho),
+      ),
+    ),
+    (
+      "verify_stage6",
+      count_allocations(
+        || s.stage6_inputs(),
+        |(cp, mat, z, t1)| crypto_sign_verify_stage6(cp, mat, z, t1),
+      ),
+    ),
+    (
+      "verify_stage7",
+      count_allocations(|| s.w1.clone(), crypto_sign_verify_stage7),
+    ),
+    (
+      "verify_stage8",
+      count_allocations(
+        || {
+          let buf = Box::new([0u8; K * POLYW1_PACKEDBYTES]);
+          (buf, s.w1_reduced.clone(), s.mu.clone())
+        },
+        |(buf, w1, mu)| {
+          let mut c2 = [0u8; SEEDBYTES];
+          crypto_sign_verify_stage8(buf, w1, &s.h, mu, &s.c, &mut c2)
+        },
+      ),
+    ),
+    (
+      "matrix_expand",
+      count_allocations(
+        || vec![Polyvecl::default(); K],
+        |mut mat| polyvec_matrix_expand(&mut mat, &s.rho[..]),
+      ),
+    ),
+    (
+      "ntt",
+      count_allocations(|| vec![1i32; N], |mut a| ntt(&mut a)),
+    ),
+  ];
+
+  println!("{:<24} {:>12} {:>12}", "operation", "allocations", "bytes");
+  for (name, (allocations, bytes)) in rows {
+    let name = format!("{}/{}", mode(), name);
+    println!("{:<24} {:>12} {:>12}", name, allocations, bytes);
+  }
+}
+
+fn bench_api(c: &mut Criterion)
+{
+  let mut group = c.benchmark_group(mode());
+  let keypair = Keypair::generate();
+  let sig = keypair.sign(MSG);
+  let pk: [u8; PUBLICKEYBYTES] = keypair.public().try_into().unwrap();
+
+  group.bench_function("keygen", |b| b.iter(Keypair::generate));
+  group.bench_function("sign", |b| b.iter(|| keypair.sign(black_box(MSG))));
+  group.bench_function("verify", |b| {
+    b.iter(|| verify(black_box(&sig), MSG, &pk).unwrap())
+  });
+  group.finish();
+}
+
+fn bench_stages(c: &mut Criterion)
+{
+  let mut group = c.benchmark_group(format!("{}/verify_stage", mode()));
+  let s = Staged::new();
+
+  group.bench_function("1", |b| {
+    b.iter(|| crypto_sign_verify_stage1(black_box(&s.sig), &s.pk).unwrap())
+  });
+  group.bench_function("2", |b| {
+    b.iter(|| crypto_sign_verify_stage2(black_box(&s.sig)).unwrap())
+  });
+  group.bench_function("3", |b| {
+    b.iter(|| crypto_sign_verify_stage3(&s.pk, black_box(MSG)))
+  });
+  group.bench_function("4", |b| {
+    let mut c = *s.c;
+    b.iter(|| crypto_sign_verify_stage4(black_box(&mut c)))
+  });
+  group.bench_function("5", |b| {
+    let mut rho = *s.rho;
+    b.iter(|| crypto_sign_verify_stage5(black_box(&mut rho)))
+  });
+  group.bench_function("6", |b| {
+    b.iter_batched(
+      || s.stage6_inputs(),
+      |(cp, mat, z, t1)| crypto_sign_verify_stage6(cp, mat, z, t1).unwrap(),
+      BatchSize::SmallInput,
+    )
+  });
+  group.bench_function("7", |b| {
+    b.iter_batched(
+      || s.w1.clone(),
+      |w1| crypto_sign_verify_stage7(w1).unwrap(),
+      BatchSize::SmallInput,
+    )
+  });
+  group.bench_function("8", |b| {
+    b.iter_batched(
+      || {
+        let buf = Box::new([0u8; K * POLYW1_PACKEDBYTES]);
+        (buf, s.w1_reduced.clone(), s.mu.clone())
+      },
+      |(buf, w1, mu)| {
+        let mut c2 = [0u8; SEEDBYTES];
+        crypto_sign_verify_stage8(buf, w1, &s.h, mu, &s.c, &mut c2).unwrap()
+      },
+      BatchSize::SmallInput,
+    )
+  });
+  group.finish();
+}
+
+fn bench_arithmetic(c: &mut Criterion)
+{
+  let mut group = c.benchmark_group(mode());
+  let rho = [7u8; SEEDBYTES];
+  let mut mat = vec![Polyvecl::default(); K];
+  group.bench_function("matrix_expand", |b| {
+    b.iter(|| polyvec_matrix_expand(&mut mat, black_box(&rho)))
+  });
+
+  let input: Vec<i32> = (0..N as i32).map(|i| i * 7919 % Q as i32).collect();
+  let mut a = input.clone();
+  group.bench_function("ntt", |b| {
+    b.iter(|| {
+      a.copy_from_slice(&input);
+      ntt(black_box(&mut a))
+    })
+  });
+  group.bench_function("ntt_portable", |b| {
+    b.iter(|| {
+      a.copy_from_slice(&input);
+      ntt_portable(black_box(&mut a))
+    })
+  });
+  group.bench_function("invntt_tomont", |b| {
+    b.iter(|| {
+      a.copy_from_slice(&input);
+      invntt_tomont(black_box(&mut a))
+    })
+  });
+  group.bench_function("invntt_tomont_portable", |b| {
+    b.iter(|| {
+      a.copy_from_slice(&input);
+      invntt_tomont_portable(black_box(&mut a))
+    })
+  });
+  group.finish();
+}
+
+fn bench_shake(c: &mut Criterion)
+{
+  let mut group = c.benchmark_group("shake");
+  let input = vec![0x5au8; 16 * 1024];
+  let mut out = vec![0u8; 16 * 1024];
+  group.throughput(Throughput::Bytes(input.len() as u64));
+
+  group.bench_function("shake128_absorb", |b| {
+    b.iter(|| {
+      let mut h = Shake128::new();
+      h.update(black_box(&input));
+      h.finalize_xof().read(&mut out[..32]);
+    })
+  });
+  group.bench_function("shake256_absorb", |b| {
+    b.iter(|| {
+      let mut h = Shake256::new();
+      h.update(black_box(&input));
+      h.finalize_xof().read(&mut out[..32]);
+    })
+  });
+  group.bench_function("shake128_squeeze", |b| {
+    b.iter(|| Shake128::new().finalize_xof().read(black_box(&mut out)))
+  });
+  group.bench_function("shake256_squeeze", |b| {
+    b.iter(|| Shake256::new().finalize_xof().read(black_box(&mut out)))
+  });
+  group.finish();
+}
+
+criterion_group!(
+  benches,
+  report_allocations,
+  bench_api,
+  bench_stages,
+  bench_arithmetic,
+  bench_shake
+);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+//! dudect-style leakage test of signing. Timing is noisy, so it runs as
+//! a benchmark binary rather than a test:
+//!
+//! ```text
+//! cargo bench --features bench,offchain --bench dudect
+//! ```
+//!
+//! Signs random messages under one fixed secret key (class 0) or a key
+//! drawn from a pool (class 1), with the class picked at random per
+//! measurement. Welch's t-test is run on all measurements and on
+//! measurements below several percentiles, which removes the long tail
+//! caused by the rejection loop and by interrupts. |t| > 10 is treated as
+//! a leak, as in dudect, and makes the process exit with an error.
+
+use std::hint::black_box;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use crystals::bench::crypto_sign_signature;
+use crystals::sha3::Shake256;
+use crystals::*;
+
+const MEASUREMENTS: usize = 20_000;
+const POOL: u8 = 64;
+const THRESHOLD: f64 = 10.0;
+
+/// Running mean and variance (Welford).
+#[derive(Default)]
+struct Moments
+{
+  n: f64,
+  mean: f64,
+  m2: f64,
+}
+
+impl Moments
+{
+  fn push(&mut self, x: f64)
+  {
+    self.n += 1.0;
+    let delta = x - self.mean;
+    self.mean += delta / self.n;
+    self.m2 += delta * (x - self.mean);
+  }
+
+  /// Welch's t statistic of self against other.
+  fn t(&self, other: &Moments) -> f64
+  {
+    let var = |m: &Moments| m.m2 / (m.n - 1.0) / m.n;
+    (self.mean - other.mean) / (var(self) + var(other)).sqrt()
+  }
+}
+
+/// Deterministic bytes: SHAKE256(label || i).
+fn bytes(label: &[u8], i: u8, len: usize) -> Vec<u8>
+{
+  let mut xof = Shake256::new();
+  xof.update(label);
+  xof.update(&[i]);
+  let mut out = vec![0u8; len];
+  xof.finalize_xof().read(&mut out);
+  out
+}
+
+fn secret_key(label: &[u8], i: u8) -> Vec<u8>
+{
+  let seed: [u8; SEEDBYTES] = bytes(label, i, SEEDBYTES).try_into().unwrap();
+  Keypair::from_seed(&seed).expose_secret().to_vec()
+}
+
+fn main() -> ExitCode
+{
+  let fixed = secret_key(b"fixed", 0);
+  let pool: Vec<Vec<u8>> = (0..POOL).map(|i| secret_key(b"pool", i)).collect();
+  let coins = bytes(b"coins", 0, 2 * MEASUREMENTS);
+  let mut sk = vec![0u8; SECRETKEYBYTES];
+  let mut sig = [0u8; SIGNBYTES];
+
+  let mut samples = Vec::with_capacity(MEASUREMENTS);
+  for i in 0..MEASUREMENTS {
+    let class = coins[2 * i] & 1;
+    // the same buffer is signed from in both classes, so only its
+    // contents differ
+    match class {
+      0 => sk.copy_from_slice(&fixed),
+      _ => sk.copy_from_slice(&pool[(coins[2 * i + 1] % POOL) as usize]),
+    }
+    let msg = (i as u64).to_le_bytes();
+
+    let start = Instant::now();
+    crypto_sign_signature(&mut sig, &msg, &sk);
+    let elapsed = start.elapsed().as_nanos() as f64;
+    black_box(&sig);
+    samples.push((class, elapsed));
+  }
+
+  let mut sorted: Vec<f64> = samples.iter().map(|s| s.1).collect();
+  sorted.sort_by(f64::total_cmp);
+  let cutoffs = [1.0, 0.9, 0.75, 0.5]
+    .map(|p| sorted[((sorted.len() - 1) as f64 * p) as usize]);
+
+  let mut leak = false;
+  for cutoff in cutoffs {
+    let mut classes = [Moments::default(), Moments::default()];
+    for &(class, t) in samples.iter().filter(|s| s.1 <= cutoff) {
+      classes[class as usize].push(t);
+    }
+    let t = classes[0].t(&classes[1]);
+    println!("cutoff {:>12.0} ns: t = {:.2}", cutoff, t);
+    leak |= t.abs() >= THRESHOLD;
+  }
+  if leak {
+    eprintln!("timing leak: |t| >= {}", THRESHOLD);
+    return ExitCode::FAILURE;
+  }
+  ExitCode::SUCCESS
+}
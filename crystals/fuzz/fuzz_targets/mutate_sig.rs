@@ -0,0 +1,89 @@
+//! Mutates a valid signature and checks that verification rejects
+//! every signature that differs from the original, and the original
+//! under a different message.
+
+#![no_main]
+
+use std::sync::OnceLock;
+
+use arbitrary::Arbitrary;
+use crystals::fuzzing::*;
+use crystals::*;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Mutation
+{
+  Xor
+  {
+    offset: u16,
+    mask: u8,
+  },
+  Set
+  {
+    offset: u16,
+    value: u8,
+  },
+  Truncate(u16),
+  Append(u8),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input
+{
+  msg: Vec<u8>,
+  other_msg: Vec<u8>,
+  mutations: Vec<Mutation>,
+}
+
+/// A fixed keypair, so that failures reproduce from the input alone
+fn keypair() -> &'static (Vec<u8>, Vec<u8>)
+{
+  static KEYPAIR: OnceLock<(Vec<u8>, Vec<u8>)> = OnceLock::new();
+  KEYPAIR.get_or_init(|| {
+    let mut pk = vec![0u8; PUBLICKEYBYTES];
+    let mut sk = vec![0u8; SECRETKEYBYTES];
+    crypto_sign_keypair(&mut pk, &mut sk, Some(&[0x42; SEEDBYTES]));
+    (pk, sk)
+  })
+}
+
+fn mutate(sig: &mut Vec<u8>, mutation: &Mutation)
+{
+  let at = |offset: u16, len: usize| offset as usize % len.max(1);
+  match *mutation {
+    Mutation::Xor { offset, mask } if !sig.is_empty() => {
+      let i = at(offset, sig.len());
+      sig[i] ^= mask;
+    }
+    Mutation::Set { offset, value } if !sig.is_empty() => {
+      let i = at(offset, sig.len());
+      sig[i] = value;
+    }
+    Mutation::Truncate(len) => sig.truncate(len as usize),
+    Mutation::Append(byte) => sig.push(byte),
+    _ => {}
+  }
+}
+
+fuzz_target!(|input: Input| {
+  let (pk, sk) = keypair();
+  let mut sig = vec![0u8; SIGNBYTES];
+  crypto_sign_signature(&mut sig, &input.msg, sk);
+  assert!(crypto_sign_verify(&sig, &input.msg, pk).is_ok());
+
+  if input.other_msg != input.msg {
+    assert!(crypto_sign_verify(&sig, &input.other_msg, pk).is_err());
+  }
+
+  let mut mutated = sig.clone();
+  for mutation in &input.mutations {
+    mutate(&mut mutated, mutation);
+  }
+  if mutated != sig {
+    assert!(
+      crypto_sign_verify(&mutated, &input.msg, pk).is_err(),
+      "mutated signature accepted"
+    );
+  }
+});
@@ -0,0 +1,15 @@
+//! Arbitrary-length inputs to the first two verify stages must be
+//! rejected with an error, never a panic.
+
+#![no_main]
+
+use crystals::fuzzing::*;
+use crystals::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let _ = crypto_sign_verify_stage2(data);
+  let (sig, pk) = data.split_at(data.len().min(SIGNBYTES));
+  let _ = crypto_sign_verify_stage1(sig, pk);
+  let _ = crypto_sign_verify_stage1(data, data);
+});
@@ -0,0 +1,36 @@
+//! Feeds arbitrary bytes to the coefficient and key/signature decoders
+//! and checks the decoded ranges.
+
+#![no_main]
+
+use crystals::fuzzing::*;
+use crystals::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let mut a = Poly::default();
+  if data.len() >= POLYZ_PACKEDBYTES {
+    polyz_unpack(&mut a, &data[..POLYZ_PACKEDBYTES]);
+    let gamma1 = GAMMA1 as i32;
+    assert!(a.coeffs.iter().all(|&c| c > -gamma1 && c <= gamma1));
+  }
+  if data.len() >= POLYT1_PACKEDBYTES {
+    polyt1_unpack(&mut a, &data[..POLYT1_PACKEDBYTES]);
+    assert!(a.coeffs.iter().all(|&c| (0..1 << 10).contains(&c)));
+  }
+  if data.len() >= PUBLICKEYBYTES {
+    let mut rho = [0u8; SEEDBYTES];
+    let mut t1 = Polyveck::default();
+    unpack_pk(&mut rho, &mut t1, &data[..PUBLICKEYBYTES]);
+  }
+  if data.len() >= SIGNBYTES {
+    let mut c = [0u8; SEEDBYTES];
+    let mut z = Polyvecl::default();
+    let mut h = Polyveck::default();
+    if unpack_sig(&mut c, &mut z, &mut h, &data[..SIGNBYTES]).is_ok() {
+      let coeffs = h.vec.iter().flat_map(|p| p.coeffs.iter());
+      assert!(coeffs.clone().all(|&x| x == 0 || x == 1));
+      assert!(coeffs.filter(|&&x| x == 1).count() <= OMEGA);
+    }
+  }
+});
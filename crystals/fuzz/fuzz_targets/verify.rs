@@ -0,0 +1,33 @@
+//! Runs full verification on arbitrary signatures, keys and messages,
+//! both at the raw lengths and resized to the expected lengths so the
+//! fuzzer reaches the arithmetic.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use crystals::fuzzing::*;
+use crystals::*;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input
+{
+  sig: Vec<u8>,
+  pk: Vec<u8>,
+  msg: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+  let Input {
+    mut sig,
+    mut pk,
+    msg,
+  } = input;
+  let _ = crypto_sign_verify(&sig, &msg, &pk);
+
+  sig.resize(SIGNBYTES, 0);
+  pk.resize(PUBLICKEYBYTES, 0);
+  let sig: [u8; SIGNBYTES] = sig.try_into().unwrap();
+  let pk: [u8; PUBLICKEYBYTES] = pk.try_into().unwrap();
+  let _ = verify(&sig, &msg, &pk);
+});
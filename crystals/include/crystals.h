@@ -0,0 +1,148 @@
+/* Generated from src/capi.rs by cbindgen; do not edit. */
+
+#ifndef CRYSTALS_H
+#define CRYSTALS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/*
+ * Sizes of the parameter set the library was built for: define
+ * CRYSTALS_MODE2 or CRYSTALS_MODE5 to match a mode2 or mode5 build.
+ * crystals_mode() reports the build at run time.
+ */
+#if defined(CRYSTALS_MODE2)
+#define CRYSTALS_MODE 2
+#define CRYSTALS_PUBLICKEYBYTES 1312
+#define CRYSTALS_SECRETKEYBYTES 2528
+#define CRYSTALS_SIGNBYTES 2420
+#elif defined(CRYSTALS_MODE5)
+#define CRYSTALS_MODE 5
+#define CRYSTALS_PUBLICKEYBYTES 2592
+#define CRYSTALS_SECRETKEYBYTES 4864
+#define CRYSTALS_SIGNBYTES 4595
+#else
+#define CRYSTALS_MODE 3
+#define CRYSTALS_PUBLICKEYBYTES 1952
+#define CRYSTALS_SECRETKEYBYTES 4000
+#define CRYSTALS_SIGNBYTES 3293
+#endif
+#define CRYSTALS_SEEDBYTES 32
+
+/**
+ * Success
+ */
+#define CRYSTALS_OK 0
+
+/**
+ * A required pointer is null
+ */
+#define CRYSTALS_ERROR_NULL -1
+
+/**
+ * An input has the wrong length or an output buffer is too small
+ */
+#define CRYSTALS_ERROR_LENGTH -2
+
+/**
+ * The signature does not verify
+ */
+#define CRYSTALS_ERROR_VERIFY -3
+
+/**
+ * A self test failed; the library refuses to operate
+ */
+#define CRYSTALS_ERROR_SELF_TEST -4
+
+/**
+ * A Rust panic was caught at the boundary
+ */
+#define CRYSTALS_ERROR_INTERNAL -5
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parameter set of the library: 2, 3 or 5
+ */
+int crystals_mode(void);
+
+size_t crystals_public_key_bytes(void);
+
+size_t crystals_secret_key_bytes(void);
+
+size_t crystals_signature_bytes(void);
+
+size_t crystals_seed_bytes(void);
+
+/**
+ * Static description of an error code
+ */
+const char *crystals_strerror(int code);
+
+/**
+ * Generates a keypair from the operating system's randomness
+ */
+int crystals_keypair(uint8_t *pk, size_t pk_len, uint8_t *sk, size_t sk_len);
+
+/**
+ * Derives the keypair of a 32-byte seed
+ */
+int crystals_keypair_from_seed(const uint8_t *seed,
+                               size_t seed_len,
+                               uint8_t *pk,
+                               size_t pk_len,
+                               uint8_t *sk,
+                               size_t sk_len);
+
+/**
+ * Writes the detached signature of a message to `sig`
+ */
+int crystals_sign(uint8_t *sig,
+                  size_t sig_len,
+                  const uint8_t *msg,
+                  size_t msg_len,
+                  const uint8_t *sk,
+                  size_t sk_len);
+
+/**
+ * Writes the signature followed by the message to `sm`, and its
+ * length, `crystals_signature_bytes() + msg_len`, to `sm_written`
+ */
+int crystals_sign_attached(uint8_t *sm,
+                           size_t sm_len,
+                           size_t *sm_written,
+                           const uint8_t *msg,
+                           size_t msg_len,
+                           const uint8_t *sk,
+                           size_t sk_len);
+
+/**
+ * Verifies a detached signature
+ */
+int crystals_verify(const uint8_t *sig,
+                    size_t sig_len,
+                    const uint8_t *msg,
+                    size_t msg_len,
+                    const uint8_t *pk,
+                    size_t pk_len);
+
+/**
+ * Verifies a signature followed by its message, as written by
+ * `crystals_sign_attached`, and copies the message to `msg` with its
+ * length in `msg_written`. Nothing is copied if it does not verify.
+ */
+int crystals_open(uint8_t *msg,
+                  size_t msg_len,
+                  size_t *msg_written,
+                  const uint8_t *sm,
+                  size_t sm_len,
+                  const uint8_t *pk,
+                  size_t pk_len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CRYSTALS_H */
@@ -1,8 +1,12 @@
 use crate::params::{PUBLICKEYBYTES, SIGNBYTES};
+use crate::pkcs8::PrivateKey;
 use crate::sign::*;
 
 #[cfg(feature = "offchain")]
-use SECRETKEYBYTES;
+use crate::{
+  params::{SECRETKEYBYTES, SEEDBYTES},
+  selftest,
+};
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Keypair
 {
@@ -19,16 +23,135 @@ impl std::fmt::Debug for Keypair
   }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum SignError
 {
   Input,
   Verify,
+  /// A freshly produced signature did not verify, e.g. after a fault
+  Fault,
+  /// A self test failed or the module is in the error state
+  SelfTest(SelfTestError),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelfTestError
+{
+  Shake,
+  Ntt,
+  SignVerify,
+  PairwiseConsistency,
+  /// A test failed after power-on; the module refuses to operate
+  ErrorState,
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum KeypairError
 {
   InvalidPublicKey,
   InvalidSecretKey,
+  /// A self test failed or the module is in the error state
+  SelfTest(SelfTestError),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EncodingError
+{
+  /// Malformed or non-canonical DER
+  Der,
+  /// Malformed base64 or unexpected PEM label
+  Pem,
+  /// The key belongs to another algorithm or parameter set
+  UnsupportedAlgorithm,
+  /// Wrong key length
+  InvalidKey,
+}
+
+#[cfg(feature = "jose")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum JoseError
+{
+  /// Malformed JSON, CBOR, base64url or token structure
+  Malformed,
+  /// Missing, misplaced or unsupported header parameter
+  Header,
+  /// The token or key names another algorithm
+  UnsupportedAlgorithm,
+  /// Wrong key type or length, or a seed that does not match `pub`
+  InvalidKey,
+  Signature(SignError),
+}
+
+#[cfg(feature = "ssh")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum SshError
+{
+  /// Malformed key file, armor or wire encoding
+  Malformed,
+  /// Another key type or hash algorithm
+  UnsupportedAlgorithm,
+  /// Passphrase-protected private keys are not supported
+  Encrypted,
+  /// Wrong key length, inconsistent key file, or a signature by another
+  /// key
+  InvalidKey,
+  /// Empty namespace, or a signature made for another namespace
+  Namespace,
+  Signature(SignError),
+}
+
+#[cfg(feature = "keystore")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeystoreError
+{
+  Io(std::io::ErrorKind),
+  /// Malformed JSON, hex or envelope field
+  Malformed,
+  /// Unknown envelope version, KDF or cipher
+  Unsupported,
+  /// The keystore holds a key of another parameter set
+  WrongMode,
+  /// KDF parameters out of range
+  Kdf,
+  /// Wrong password, or the envelope was modified
+  Decrypt,
+  /// Wrong length, or a secret key that is inconsistent or does not
+  /// match its public key
+  InvalidKey,
+}
+
+#[cfg(feature = "keystore")]
+impl From<std::io::Error> for KeystoreError
+{
+  fn from(err: std::io::Error) -> Self
+  {
+    KeystoreError::Io(err.kind())
+  }
+}
+
+#[cfg(feature = "hd")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum HdError
+{
+  /// Not a valid English BIP-39 mnemonic, or an unsupported word count
+  Mnemonic,
+  /// Malformed derivation path
+  Path,
+  /// A path index without the hardened marker
+  NonHardened,
+  /// Master seed shorter than 16 or longer than 64 bytes
+  Seed,
+}
+
+#[cfg(feature = "hybrid")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum KemError
+{
+  InvalidPublicKey,
+  InvalidSecretKey,
+  InvalidCiphertext,
+  /// Wrong length of caller-provided randomness or of an output buffer
+  InvalidLength,
 }
 
 impl Keypair
@@ -39,23 +162,41 @@ impl Keypair
     &self.secret
   }
 
-  /// Generates a keypair for signing and verification
+  /// Packed public key (rho, t1)
+  pub fn public(&self) -> &[u8]
+  {
+    &self.public
+  }
+
+  /// Generates a keypair for signing and verification, checking that it
+  /// signs and verifies before returning it
+  ///
+  /// Panics if the self tests fail or the module is in the error state;
+  /// see [`Keypair::try_generate`].
   #[cfg(feature = "offchain")]
   pub fn generate() -> Keypair
   {
-    let mut public = vec![0u8; PUBLICKEYBYTES].into_boxed_slice();
-    let mut secret = vec![0u8; SECRETKEYBYTES].into_boxed_slice();
-    crypto_sign_keypair(&mut public, &mut secret, None);
-    Keypair { public, secret }
+    Self::try_generate().expect("crystals self test failed")
   }
 
-  /// Restore a keypair
- #[cfg(feature = "offchain")]
+  /// Generates a keypair once the power-on self tests have passed, and
+  /// checks that it signs and verifies before returning it
+  #[cfg(feature = "offchain")]
+  pub fn try_generate() -> Result<Keypair, SelfTestError>
+  {
+    selftest::status()?;
+    Self::checked(None)
+  }
+
+  /// Restore a keypair. Fails with `KeypairError::SelfTest` once the self
+  /// tests have failed.
+  #[cfg(feature = "offchain")]
   pub fn restore(
     pub_bytes: Vec<u8>,
     sec_bytes: Vec<u8>,
   ) -> Result<Self, KeypairError>
   {
+    selftest::status().map_err(KeypairError::SelfTest)?;
     let public = match pub_bytes.into_boxed_slice().try_into() {
       Ok(bytes) => bytes,
       Err(_) => return Err(KeypairError::InvalidPublicKey),
@@ -67,13 +208,97 @@ impl Keypair
     Ok(Self { public, secret })
   }
 
+  /// Derives the keypair of a 32-byte key generation seed, checking that
+  /// it signs and verifies
+  ///
+  /// Panics if the self tests fail or the module is in the error state;
+  /// see [`Keypair::try_from_seed`].
+  #[cfg(feature = "offchain")]
+  pub fn from_seed(seed: &[u8; SEEDBYTES]) -> Keypair
+  {
+    Self::try_from_seed(seed).expect("crystals self test failed")
+  }
+
+  /// Derives the keypair of a 32-byte seed once the power-on self tests
+  /// have passed, and checks that it signs and verifies
+  #[cfg(feature = "offchain")]
+  pub fn try_from_seed(seed: &[u8; SEEDBYTES])
+    -> Result<Keypair, SelfTestError>
+  {
+    selftest::status()?;
+    Self::checked(Some(seed))
+  }
+
+  /// Key generation followed by the pairwise consistency test
+  #[cfg(feature = "offchain")]
+  fn checked(seed: Option<&[u8; SEEDBYTES]>) -> Result<Keypair, SelfTestError>
+  {
+    let mut public = vec![0u8; PUBLICKEYBYTES].into_boxed_slice();
+    let mut secret = vec![0u8; SECRETKEYBYTES].into_boxed_slice();
+    crypto_sign_keypair(&mut public, &mut secret, seed.map(|s| &s[..]));
+    selftest::pairwise_consistency(&public, &secret)?;
+    Ok(Keypair { public, secret })
+  }
+
+  /// Restores a keypair from a decoded PKCS#8 private key. The public
+  /// key is recomputed from the secret key, and a seed must agree with
+  /// an expanded key stored next to it. Fails with
+  /// `KeypairError::SelfTest` once the self tests have failed.
+  #[cfg(feature = "offchain")]
+  pub fn from_private_key(key: &PrivateKey) -> Result<Keypair, KeypairError>
+  {
+    selftest::status().map_err(KeypairError::SelfTest)?;
+    let from_seed =
+      |seed| Self::try_from_seed(seed).map_err(KeypairError::SelfTest);
+    match key {
+      PrivateKey::Seed(seed) => from_seed(seed),
+      PrivateKey::Expanded(sk) => {
+        let mut public = vec![0u8; PUBLICKEYBYTES].into_boxed_slice();
+        crypto_sign_public_from_secret(&mut public, sk)
+          .map_err(|_| KeypairError::InvalidSecretKey)?;
+        let secret = sk.clone().into_boxed_slice();
+        Ok(Keypair { public, secret })
+      }
+      PrivateKey::Both(seed, sk) => {
+        let keypair = from_seed(seed)?;
+        match *keypair.secret == **sk {
+          true => Ok(keypair),
+          false => Err(KeypairError::InvalidSecretKey),
+        }
+      }
+    }
+  }
+
+  /// Expanded PKCS#8 private key, see [`crate::pkcs8`]
+  pub fn to_private_key(&self) -> PrivateKey
+  {
+    PrivateKey::Expanded(self.secret.to_vec())
+  }
+
   /// Generates a signature for the given message using a keypair
+  ///
+  /// Panics if the module is in the self test error state; see
+  /// [`Keypair::sign_checked`].
   pub fn sign(&self, msg: &[u8]) -> [u8; SIGNBYTES]
   {
+    #[cfg(feature = "offchain")]
+    selftest::status().expect("crystals self test failed");
     let mut sig = [0u8; SIGNBYTES];
     crypto_sign_signature(&mut sig, msg, &self.secret);
     sig
   }
+
+  /// Like sign, but verifies the signature against the stored public
+  /// key before returning it. Guards against fault attacks at roughly
+  /// the cost of one verification.
+  pub fn sign_checked(&self, msg: &[u8]) -> Result<[u8; SIGNBYTES], SignError>
+  {
+    #[cfg(feature = "offchain")]
+    selftest::status().map_err(SignError::SelfTest)?;
+    let mut sig = [0u8; SIGNBYTES];
+    crypto_sign_signature_checked(&mut sig, msg, &self.secret, &self.public)?;
+    Ok(sig)
+  }
 }
 
 /// Verify signature using keypair
@@ -86,6 +311,8 @@ pub fn verify(
   if sig.len() != SIGNBYTES {
     return Err(SignError::Input);
   }
+  #[cfg(feature = "offchain")]
+  selftest::status().map_err(SignError::SelfTest)?;
 
   // Wrapping inputs in Box for heap allocation
   let sig_box = Box::new(sig.to_vec());
@@ -95,21 +322,126 @@ pub fn verify(
   crypto_sign_verify(&sig_box, &msg_box, &pk_box)
 }
 
-/// Open message
-pub fn open(
-  sig: &[u8; SIGNBYTES],
-  msg: &[u8],
+/// Open a signed message, the signature followed by the message, and
+/// return the message once the signature verifies
+pub fn open<'a>(
+  signed_message: &'a [u8],
   public_key: &[u8; PUBLICKEYBYTES],
-) -> Result<(), SignError>
+) -> Result<&'a [u8], SignError>
 {
-  if sig.len() != SIGNBYTES {
+  if signed_message.len() < SIGNBYTES {
     return Err(SignError::Input);
   }
+  #[cfg(feature = "offchain")]
+  selftest::status().map_err(SignError::SelfTest)?;
 
-  // Wrapping inputs in Box for heap allocation
-  let sig_box = Box::new(sig.to_vec());
-  let msg_box = Box::new(msg.to_vec());
-  let pk_box = Box::new(public_key.to_vec());
+  let msg = &signed_message[SIGNBYTES..];
+  let mut mlen = msg.len();
+  crypto_sign_open(msg, &mut mlen, signed_message, public_key)?;
+  Ok(msg)
+}
+
+#[cfg(all(test, feature = "offchain"))]
+mod tests
+{
+  use super::*;
+  use crate::pkcs8;
+
+  #[test]
+  fn sign_verify()
+  {
+    let keypair = Keypair::generate();
+    let msg = b"crystals";
+    let mut sig = keypair.sign(msg);
+    let pk: [u8; PUBLICKEYBYTES] = keypair.public().try_into().unwrap();
+    assert!(verify(&sig, msg, &pk).is_ok());
+    assert!(verify(&sig, b"crystal", &pk).is_err());
+    sig[0] ^= 1;
+    assert!(verify(&sig, msg, &pk).is_err());
+  }
+
+  #[test]
+  fn open_signed_message()
+  {
+    let keypair = Keypair::generate();
+    let pk: [u8; PUBLICKEYBYTES] = keypair.public().try_into().unwrap();
+    let msg = b"crystals";
+    let mut signed = [&keypair.sign(msg)[..], msg].concat();
+    assert_eq!(open(&signed, &pk), Ok(&msg[..]));
+
+    // the signature covers the message, not the empty string
+    let empty = keypair.sign(b"");
+    assert_eq!(open(&empty, &pk), Ok(&b""[..]));
+    assert_eq!(
+      open(&[&empty[..], msg].concat(), &pk),
+      Err(SignError::Verify)
+    );
 
-  crypto_sign_open(&msg_box, &mut msg_box.len(), &sig_box, &pk_box)
+    *signed.last_mut().unwrap() ^= 1;
+    assert_eq!(open(&signed, &pk), Err(SignError::Verify));
+    assert_eq!(open(&signed[..SIGNBYTES - 1], &pk), Err(SignError::Input));
+  }
+
+  #[test]
+  fn sign_checked()
+  {
+    let keypair = Keypair::generate();
+    let msg = b"crystals";
+    assert_eq!(keypair.sign_checked(msg), Ok(keypair.sign(msg)));
+
+    // a flipped bit in s1 stands in for a fault during signing
+    let mut secret = keypair.expose_secret().to_vec();
+    secret[3 * SEEDBYTES] ^= 1;
+    let faulty = Keypair::restore(keypair.public().to_vec(), secret).unwrap();
+    assert_eq!(faulty.sign_checked(msg), Err(SignError::Fault));
+
+    let other = Keypair::generate();
+    let mut sig = [0xAAu8; SIGNBYTES];
+    assert_eq!(
+      crypto_sign_signature_checked(
+        &mut sig,
+        msg,
+        keypair.expose_secret(),
+        other.public()
+      ),
+      Err(SignError::Fault)
+    );
+    assert_eq!(sig, [0u8; SIGNBYTES]);
+  }
+
+  #[test]
+  fn private_key()
+  {
+    let seed = [7u8; SEEDBYTES];
+    let keypair = Keypair::from_seed(&seed);
+    let expanded = keypair.to_private_key();
+    let pem = pkcs8::private_key_to_pem(&expanded).unwrap();
+    let decoded = pkcs8::private_key_from_pem(&pem).unwrap();
+    assert_eq!(Keypair::from_private_key(&decoded), Ok(keypair.clone()));
+    assert_eq!(
+      Keypair::from_private_key(&PrivateKey::Seed(seed)),
+      Ok(keypair.clone())
+    );
+    assert_eq!(Keypair::try_from_seed(&seed), Ok(keypair.clone()));
+    let both = PrivateKey::Both(seed, keypair.expose_secret().to_vec());
+    assert_eq!(Keypair::from_private_key(&both), Ok(keypair.clone()));
+
+    let other = Keypair::from_seed(&[8u8; SEEDBYTES]);
+    let mismatched = PrivateKey::Both(seed, other.expose_secret().to_vec());
+    assert_eq!(
+      Keypair::from_private_key(&mismatched),
+      Err(KeypairError::InvalidSecretKey)
+    );
+
+    // t0 no longer matches A*s1 + s2
+    let mut secret = keypair.expose_secret().to_vec();
+    secret[3 * SEEDBYTES] ^= 1;
+    assert_eq!(
+      Keypair::from_private_key(&PrivateKey::Expanded(secret)),
+      Err(KeypairError::InvalidSecretKey)
+    );
+
+    let der = pkcs8::public_key_to_der(keypair.public()).unwrap();
+    assert_eq!(pkcs8::public_key_from_der(&der).unwrap(), keypair.public());
+  }
 }
@@ -0,0 +1,199 @@
+//! AVX2 versions of the NTT, pointwise Montgomery multiplication,
+//! uniform rejection sampling and the four-way Keccak permutation.
+//!
+//! Every routine computes exactly the same integers as its portable
+//! counterpart, including the unreduced Montgomery representatives, so
+//! keys and signatures do not depend on which backend ran. Callers go
+//! through the portable entry points, which check [`enabled`] and
+//! dispatch here.
+
+// Intrinsics need an unsafe block up to Rust 1.86 and are safe inside
+// a matching #[target_feature] function from 1.87 on, so the blocks
+// the MSRV requires read as unused on newer compilers.
+#![deny(unsafe_op_in_unsafe_fn)]
+#![allow(unused_unsafe)]
+
+use core::arch::x86_64::*;
+
+use crate::{params::Q, reduce::QINV};
+
+mod keccak;
+mod ntt;
+mod rejsample;
+
+pub use keccak::*;
+pub use ntt::*;
+pub use rejsample::*;
+
+/// Whether the running CPU supports AVX2. The result of the CPUID
+/// probe is cached by the standard library.
+pub fn enabled() -> bool {
+  is_x86_feature_detected!("avx2")
+}
+
+/// Lane-wise montgomery_reduce(a * b) for eight signed 32-bit pairs.
+///
+/// a*b - t*Q is divisible by 2^32, so its high half is exactly
+/// hi(a*b) - hi(t*Q) and matches the scalar reduction bit for bit.
+#[target_feature(enable = "avx2")]
+unsafe fn montgomery_mul(a: __m256i, b: __m256i) -> __m256i {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    let q = _mm256_set1_epi32(Q as i32);
+    let qinv = _mm256_set1_epi32(QINV);
+
+    let prod_even = _mm256_mul_epi32(a, b);
+    let prod_odd =
+      _mm256_mul_epi32(_mm256_srli_epi64::<32>(a), _mm256_srli_epi64::<32>(b));
+    let t_even = _mm256_mul_epi32(_mm256_mullo_epi32(prod_even, qinv), q);
+    let t_odd = _mm256_mul_epi32(_mm256_mullo_epi32(prod_odd, qinv), q);
+    let r_even = _mm256_srli_epi64::<32>(_mm256_sub_epi64(prod_even, t_even));
+    let r_odd = _mm256_sub_epi64(prod_odd, t_odd);
+    _mm256_blend_epi32::<0xAA>(r_even, r_odd)
+  }
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn load(a: &[i32]) -> __m256i {
+  assert!(a.len() >= 8);
+  // SAFETY: the caller guarantees AVX2, at least eight i32 are readable
+  // and unaligned loads are allowed
+  unsafe { _mm256_loadu_si256(a.as_ptr() as *const __m256i) }
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn store(a: &mut [i32], v: __m256i) {
+  assert!(a.len() >= 8);
+  // SAFETY: the caller guarantees AVX2, at least eight i32 are writable
+  // and unaligned stores are allowed
+  unsafe { _mm256_storeu_si256(a.as_mut_ptr() as *mut __m256i, v) }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fips202x4::keccakf1600x4_statepermute_portable,
+    ntt::{invntt_tomont_portable, ntt_portable},
+    params::N,
+    poly::{poly_pointwise_montgomery_portable, rej_uniform_portable, Poly},
+    reduce::montgomery_reduce,
+  };
+
+  struct Lcg(u64);
+
+  impl Lcg {
+    fn next(&mut self) -> u32 {
+      self.0 = self.0.wrapping_mul(6364136223846793005);
+      self.0 = self.0.wrapping_add(1442695040888963407);
+      (self.0 >> 32) as u32
+    }
+
+    fn coeff(&mut self, bound: i32) -> i32 {
+      (self.next() % (2 * bound as u32 + 1)) as i32 - bound
+    }
+  }
+
+  #[test]
+  fn montgomery_mul_matches_scalar() {
+    if !enabled() {
+      return;
+    }
+    let mut rng = Lcg(1);
+    for _ in 0..1000 {
+      let a: [i32; 8] = core::array::from_fn(|_| rng.next() as i32);
+      let b: [i32; 8] = core::array::from_fn(|_| rng.coeff(Q as i32));
+      let mut out = [0i32; 8];
+      unsafe { store(&mut out, montgomery_mul(load(&a), load(&b))) };
+      for i in 0..8 {
+        assert_eq!(out[i], montgomery_reduce(a[i] as i64 * b[i] as i64));
+      }
+    }
+  }
+
+  #[test]
+  fn ntt_matches_portable() {
+    if !enabled() {
+      return;
+    }
+    let mut rng = Lcg(2);
+    for _ in 0..100 {
+      let a: Vec<i32> = (0..N).map(|_| rng.coeff(Q as i32 - 1)).collect();
+      let mut fast = a.clone();
+      let mut slow = a.clone();
+      unsafe { ntt(&mut fast) };
+      ntt_portable(&mut slow);
+      assert_eq!(fast, slow);
+
+      let mut fast = a.clone();
+      let mut slow = a;
+      unsafe { invntt_tomont(&mut fast) };
+      invntt_tomont_portable(&mut slow);
+      assert_eq!(fast, slow);
+    }
+  }
+
+  #[test]
+  fn pointwise_matches_portable() {
+    if !enabled() {
+      return;
+    }
+    let mut rng = Lcg(3);
+    let mut a = Poly::default();
+    let mut b = Poly::default();
+    let mut fast = Poly::default();
+    let mut slow = Poly::default();
+    for _ in 0..100 {
+      for i in 0..N {
+        a.coeffs[i] = rng.coeff(22 * Q as i32);
+        b.coeffs[i] = rng.coeff(Q as i32);
+      }
+      unsafe { pointwise_montgomery(&mut fast.coeffs, &a.coeffs, &b.coeffs) };
+      poly_pointwise_montgomery_portable(&mut slow, &a, &b);
+      assert_eq!(fast.coeffs, slow.coeffs);
+    }
+  }
+
+  #[test]
+  fn rej_uniform_matches_portable() {
+    if !enabled() {
+      return;
+    }
+    let mut rng = Lcg(4);
+    for buflen in [0, 3, 24, 31, 32, 168, 840] {
+      for len in [0, 1, 7, 8, 9, 100, 256] {
+        // bias towards 0xff so a fair share of candidates is >= Q
+        let buf: Vec<u8> = (0..buflen)
+          .map(|_| match rng.next() % 4 {
+            0 => 0xff,
+            _ => rng.next() as u8,
+          })
+          .collect();
+        let mut fast = [0i32; N];
+        let mut slow = [0i32; N];
+        let n = unsafe { rej_uniform(&mut fast, len, &buf, buflen) };
+        let m = rej_uniform_portable(&mut slow, len, &buf, buflen);
+        assert_eq!(n, m);
+        assert_eq!(fast, slow);
+      }
+    }
+  }
+
+  #[test]
+  fn keccak_matches_portable() {
+    if !enabled() {
+      return;
+    }
+    let mut rng = Lcg(5);
+    let mut fast = [[0u64; 4]; 25];
+    for word in fast.iter_mut().flatten() {
+      *word = (rng.next() as u64) << 32 | rng.next() as u64;
+    }
+    let mut slow = fast;
+    for _ in 0..3 {
+      unsafe { keccakf1600x4_statepermute(&mut fast) };
+      keccakf1600x4_statepermute_portable(&mut slow);
+      assert_eq!(fast, slow);
+    }
+  }
+}
@@ -0,0 +1,85 @@
+//! Keccak-f[1600] on four states, one 256-bit register per state word.
+
+use core::arch::x86_64::*;
+
+use crate::{
+  fips202::KECCAKF_ROUNDCONSTANTS,
+  fips202x4::KECCAKF_ROTC,
+};
+
+#[target_feature(enable = "avx2")]
+unsafe fn rol(x: __m256i, n: u32) -> __m256i {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    let n = n as i64;
+    // shifts by 64 produce zero, so n == 0 returns x unchanged
+    _mm256_or_si256(
+      _mm256_sllv_epi64(x, _mm256_set1_epi64x(n)),
+      _mm256_srlv_epi64(x, _mm256_set1_epi64x(64 - n)),
+    )
+  }
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn andnot(a: __m256i, b: __m256i) -> __m256i {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    // (!a) & b
+    _mm256_andnot_si256(a, b)
+  }
+}
+
+/// AVX2 version of crate::fips202x4::keccakf1600x4_statepermute.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, see [`super::enabled`].
+#[target_feature(enable = "avx2")]
+pub unsafe fn keccakf1600x4_statepermute(state: &mut [[u64; 4]; 25]) {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    let mut a = [_mm256_setzero_si256(); 25];
+    for (v, lanes) in a.iter_mut().zip(state.iter()) {
+      // [u64; 4] is 32 readable bytes
+      *v = _mm256_loadu_si256(lanes.as_ptr() as *const __m256i);
+    }
+
+    let mut b = [_mm256_setzero_si256(); 25];
+    for rc in KECCAKF_ROUNDCONSTANTS {
+      let mut c = [_mm256_setzero_si256(); 5];
+      for (x, c) in c.iter_mut().enumerate() {
+        *c = _mm256_xor_si256(
+          _mm256_xor_si256(a[x], a[x + 5]),
+          _mm256_xor_si256(_mm256_xor_si256(a[x + 10], a[x + 15]), a[x + 20]),
+        );
+      }
+      for x in 0..5 {
+        let d = _mm256_xor_si256(c[(x + 4) % 5], rol(c[(x + 1) % 5], 1));
+        for y in 0..5 {
+          a[x + 5 * y] = _mm256_xor_si256(a[x + 5 * y], d);
+        }
+      }
+
+      for x in 0..5 {
+        for y in 0..5 {
+          let to = y + 5 * ((2 * x + 3 * y) % 5);
+          b[to] = rol(a[x + 5 * y], KECCAKF_ROTC[x + 5 * y]);
+        }
+      }
+
+      for y in 0..5 {
+        for x in 0..5 {
+          let chi = andnot(b[(x + 1) % 5 + 5 * y], b[(x + 2) % 5 + 5 * y]);
+          a[x + 5 * y] = _mm256_xor_si256(b[x + 5 * y], chi);
+        }
+      }
+
+      a[0] = _mm256_xor_si256(a[0], _mm256_set1_epi64x(rc as i64));
+    }
+
+    for (v, lanes) in a.iter().zip(state.iter_mut()) {
+      // [u64; 4] is 32 writable bytes
+      _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, *v);
+    }
+  }
+}
@@ -0,0 +1,189 @@
+//! Forward and inverse NTT on eight coefficients per vector. The layers
+//! with len >= 8 pair whole vectors; the last three layers regroup two
+//! vectors so that butterfly partners share a lane.
+
+use core::arch::x86_64::*;
+
+use super::{load, montgomery_mul, store};
+use crate::{ntt::ZETAS, params::N};
+
+/// Splits A, B into (lo, hi) so that lane i of lo and lane i of hi are
+/// butterfly partners at distance `len`, for len in {4, 2, 1}.
+#[target_feature(enable = "avx2")]
+unsafe fn split(a: __m256i, b: __m256i, len: usize) -> (__m256i, __m256i) {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    match len {
+      4 => (
+        _mm256_permute2x128_si256::<0x20>(a, b),
+        _mm256_permute2x128_si256::<0x31>(a, b),
+      ),
+      2 => (_mm256_unpacklo_epi64(a, b), _mm256_unpackhi_epi64(a, b)),
+      _ => {
+        // [x0, x2, x1, x3] in each half, then evens and odds
+        let a = _mm256_shuffle_epi32::<0xD8>(a);
+        let b = _mm256_shuffle_epi32::<0xD8>(b);
+        (_mm256_unpacklo_epi64(a, b), _mm256_unpackhi_epi64(a, b))
+      }
+    }
+  }
+}
+
+/// Inverse of split.
+#[target_feature(enable = "avx2")]
+unsafe fn join(lo: __m256i, hi: __m256i, len: usize) -> (__m256i, __m256i) {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    match len {
+      4 => (
+        _mm256_permute2x128_si256::<0x20>(lo, hi),
+        _mm256_permute2x128_si256::<0x31>(lo, hi),
+      ),
+      2 => (_mm256_unpacklo_epi64(lo, hi), _mm256_unpackhi_epi64(lo, hi)),
+      _ => {
+        let a = _mm256_unpacklo_epi64(lo, hi);
+        let b = _mm256_unpackhi_epi64(lo, hi);
+        (
+          _mm256_shuffle_epi32::<0xD8>(a),
+          _mm256_shuffle_epi32::<0xD8>(b),
+        )
+      }
+    }
+  }
+}
+
+/// Block offsets (relative to the first block of the 16 coefficients
+/// being processed) of the lanes produced by split.
+fn block_order(len: usize) -> [usize; 8] {
+  match len {
+    4 => [0, 0, 0, 0, 1, 1, 1, 1],
+    2 => [0, 0, 2, 2, 1, 1, 3, 3],
+    _ => [0, 1, 4, 5, 2, 3, 6, 7],
+  }
+}
+
+/// Zetas for the 16 coefficients at `base`, arranged like split.
+/// `zeta(block)` returns the zeta of the block with that index.
+#[target_feature(enable = "avx2")]
+unsafe fn zetas(len: usize, base: usize, zeta: impl Fn(usize) -> i32) -> __m256i {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    let first = base / (2 * len);
+    let z = block_order(len).map(|off| zeta(first + off));
+    _mm256_setr_epi32(z[0], z[1], z[2], z[3], z[4], z[5], z[6], z[7])
+  }
+}
+
+/// AVX2 version of crate::ntt::ntt.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, see [`super::enabled`].
+#[target_feature(enable = "avx2")]
+pub unsafe fn ntt(a: &mut [i32]) {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    let mut k = 0usize;
+    let mut len = 128;
+    while len >= 8 {
+      let mut start = 0;
+      while start < N {
+        k += 1;
+        let zeta = _mm256_set1_epi32(ZETAS[k]);
+        for j in (start..start + len).step_by(8) {
+          let lo = load(&a[j..]);
+          let hi = load(&a[j + len..]);
+          let t = montgomery_mul(zeta, hi);
+          store(&mut a[j + len..], _mm256_sub_epi32(lo, t));
+          store(&mut a[j..], _mm256_add_epi32(lo, t));
+        }
+        start += 2 * len;
+      }
+      len >>= 1;
+    }
+
+    while len > 0 {
+      // layer with blocks of 2*len coefficients starts at zeta 128/len
+      let offset = 128 / len;
+      for base in (0..N).step_by(16) {
+        let (lo, hi) = split(load(&a[base..]), load(&a[base + 8..]), len);
+        let zeta = zetas(len, base, |b| ZETAS[offset + b]);
+        let t = montgomery_mul(zeta, hi);
+        let (x, y) =
+          join(_mm256_add_epi32(lo, t), _mm256_sub_epi32(lo, t), len);
+        store(&mut a[base..], x);
+        store(&mut a[base + 8..], y);
+      }
+      len >>= 1;
+    }
+  }
+}
+
+/// AVX2 version of crate::ntt::invntt_tomont.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, see [`super::enabled`].
+#[target_feature(enable = "avx2")]
+pub unsafe fn invntt_tomont(a: &mut [i32]) {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    const F: i32 = 41978; // mont^2/256
+
+    let mut len = 1;
+    while len < 8 {
+      // blocks of this layer use zetas 256/len - 1 downwards
+      let offset = 256 / len - 1;
+      for base in (0..N).step_by(16) {
+        let (lo, hi) = split(load(&a[base..]), load(&a[base + 8..]), len);
+        let zeta = zetas(len, base, |b| -ZETAS[offset - b]);
+        let sum = _mm256_add_epi32(lo, hi);
+        let diff = montgomery_mul(zeta, _mm256_sub_epi32(lo, hi));
+        let (x, y) = join(sum, diff, len);
+        store(&mut a[base..], x);
+        store(&mut a[base + 8..], y);
+      }
+      len <<= 1;
+    }
+
+    let mut k = 256 / len;
+    while len < N {
+      let mut start = 0;
+      while start < N {
+        k -= 1;
+        let zeta = _mm256_set1_epi32(-ZETAS[k]);
+        for j in (start..start + len).step_by(8) {
+          let lo = load(&a[j..]);
+          let hi = load(&a[j + len..]);
+          store(&mut a[j..], _mm256_add_epi32(lo, hi));
+          let diff = _mm256_sub_epi32(lo, hi);
+          store(&mut a[j + len..], montgomery_mul(zeta, diff));
+        }
+        start += 2 * len;
+      }
+      len <<= 1;
+    }
+
+    let f = _mm256_set1_epi32(F);
+    for j in (0..N).step_by(8) {
+      let v = montgomery_mul(f, load(&a[j..]));
+      store(&mut a[j..], v);
+    }
+  }
+}
+
+/// AVX2 version of crate::poly::poly_pointwise_montgomery on raw
+/// coefficient slices.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, see [`super::enabled`].
+#[target_feature(enable = "avx2")]
+pub unsafe fn pointwise_montgomery(c: &mut [i32], a: &[i32], b: &[i32]) {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    for j in (0..N).step_by(8) {
+      store(&mut c[j..], montgomery_mul(load(&a[j..]), load(&b[j..])));
+    }
+  }
+}
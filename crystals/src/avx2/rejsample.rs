@@ -0,0 +1,79 @@
+//! Rejection sampling of uniform coefficients, eight candidates at a
+//! time.
+
+use core::arch::x86_64::*;
+
+use crate::{params::Q, poly::rej_uniform_portable};
+
+/// For each 8-bit acceptance mask, the indices of the set bits in
+/// increasing order, padded with zeros.
+const COMPRESS: [[u8; 8]; 256] = {
+  let mut table = [[0u8; 8]; 256];
+  let mut mask = 0;
+  while mask < 256 {
+    let (mut bit, mut n) = (0, 0);
+    while bit < 8 {
+      if mask & (1 << bit) != 0 {
+        table[mask][n] = bit as u8;
+        n += 1;
+      }
+      bit += 1;
+    }
+    mask += 1;
+  }
+  table
+};
+
+/// AVX2 version of crate::poly::rej_uniform. Full groups of eight
+/// candidates are filtered in vectors while at least eight more
+/// coefficients are wanted; the tail goes through the portable code, so
+/// accepted coefficients appear in the same order.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, see [`super::enabled`].
+#[target_feature(enable = "avx2")]
+pub unsafe fn rej_uniform(a: &mut [i32], len: u32, buf: &[u8], buflen: usize) -> u32 {
+  // SAFETY: the caller guarantees AVX2
+  unsafe {
+    let buf = &buf[..buflen];
+    let len = len as usize;
+    let bound = _mm256_set1_epi32(Q as i32);
+    let mask = _mm256_set1_epi32(0x7FFFFF);
+    // bytes 3j..3j+2 into lane j; the high half reads from byte 8 on
+    let idx = _mm256_setr_epi8(
+      0, 1, 2, -1, 3, 4, 5, -1, 6, 7, 8, -1, 9, 10, 11, -1, 4, 5, 6, -1, 7, 8,
+      9, -1, 10, 11, 12, -1, 13, 14, 15, -1,
+    );
+
+    let (mut ctr, mut pos) = (0usize, 0usize);
+    // 24 bytes are consumed per step, but the load reads 32
+    while ctr + 8 <= len && pos + 32 <= buf.len() {
+      // pos + 32 <= buf.len()
+      let v = _mm256_loadu_si256(buf[pos..].as_ptr() as *const __m256i);
+      let v = _mm256_permute4x64_epi64::<0x94>(v);
+      let v = _mm256_and_si256(_mm256_shuffle_epi8(v, idx), mask);
+      let good = _mm256_cmpgt_epi32(bound, v);
+      let good = _mm256_movemask_ps(_mm256_castsi256_ps(good)) as usize;
+
+      // COMPRESS rows are 8 readable bytes
+      let perm = _mm256_cvtepu8_epi32(_mm_loadl_epi64(
+        COMPRESS[good].as_ptr() as *const __m128i
+      ));
+      let mut out = [0i32; 8];
+      super::store(&mut out, _mm256_permutevar8x32_epi32(v, perm));
+      let n = good.count_ones() as usize;
+      a[ctr..ctr + n].copy_from_slice(&out[..n]);
+      ctr += n;
+      pos += 24;
+    }
+
+    let rest = rej_uniform_portable(
+      &mut a[ctr..],
+      (len - ctr) as u32,
+      &buf[pos..],
+      buf.len() - pos,
+    );
+    (ctr + rest as usize) as u32
+  }
+}
@@ -0,0 +1,14 @@
+//! Internals re-exported for the benchmarks in `benches/`. Enabled by
+//! the `bench` feature; not part of the stable API.
+
+pub use crate::ntt::{
+  invntt_tomont, invntt_tomont_portable, ntt, ntt_portable,
+};
+pub use crate::poly::Poly;
+pub use crate::polyvec::{polyvec_matrix_expand, Polyveck, Polyvecl};
+pub use crate::sign::{
+  crypto_sign_signature, crypto_sign_verify_stage1, crypto_sign_verify_stage2,
+  crypto_sign_verify_stage3, crypto_sign_verify_stage4,
+  crypto_sign_verify_stage5, crypto_sign_verify_stage6,
+  crypto_sign_verify_stage7, crypto_sign_verify_stage8,
+};
@@ -0,0 +1,294 @@
+//! C ABI of the cdylib; `include/crystals.h` is generated from this file
+//! by cbindgen with `cbindgen.toml`, run from `tests/capi.rs`.
+//!
+//! Every buffer is owned by the caller and passed as a pointer and a
+//! length. Keys, seeds and signatures read by a function must have
+//! exactly their size; output buffers must be at least as large as what
+//! is written, and no two buffers may overlap. A message pointer may be
+//! null when its length is 0. All functions return `CRYSTALS_OK` or a
+//! negative error code, and never unwind into C.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+  os::raw::{c_char, c_int},
+  panic::{catch_unwind, AssertUnwindSafe},
+  slice,
+};
+
+use crate::{
+  params::*, pkcs8::Algorithm, pkcs8::ALGORITHM, selftest,
+  sign::crypto_sign_signature, Keypair, SelfTestError, SignError,
+};
+
+/// Success
+pub const CRYSTALS_OK: c_int = 0;
+/// A required pointer is null
+pub const CRYSTALS_ERROR_NULL: c_int = -1;
+/// An input has the wrong length or an output buffer is too small
+pub const CRYSTALS_ERROR_LENGTH: c_int = -2;
+/// The signature does not verify
+pub const CRYSTALS_ERROR_VERIFY: c_int = -3;
+/// A self test failed; the library refuses to operate
+pub const CRYSTALS_ERROR_SELF_TEST: c_int = -4;
+/// A Rust panic was caught at the boundary
+pub const CRYSTALS_ERROR_INTERNAL: c_int = -5;
+
+/// Parameter set of the library: 2, 3 or 5
+#[no_mangle]
+pub extern "C" fn crystals_mode() -> c_int
+{
+  match ALGORITHM {
+    Algorithm::Dilithium2 => 2,
+    Algorithm::Dilithium5 => 5,
+    _ => 3,
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn crystals_public_key_bytes() -> usize
+{
+  PUBLICKEYBYTES
+}
+
+#[no_mangle]
+pub extern "C" fn crystals_secret_key_bytes() -> usize
+{
+  SECRETKEYBYTES
+}
+
+#[no_mangle]
+pub extern "C" fn crystals_signature_bytes() -> usize
+{
+  SIGNBYTES
+}
+
+#[no_mangle]
+pub extern "C" fn crystals_seed_bytes() -> usize
+{
+  SEEDBYTES
+}
+
+/// Static description of an error code
+#[no_mangle]
+pub extern "C" fn crystals_strerror(code: c_int) -> *const c_char
+{
+  let text: &'static [u8] = match code {
+    CRYSTALS_OK => b"success\0",
+    CRYSTALS_ERROR_NULL => b"null pointer\0",
+    CRYSTALS_ERROR_LENGTH => b"wrong length or buffer too small\0",
+    CRYSTALS_ERROR_VERIFY => b"signature does not verify\0",
+    CRYSTALS_ERROR_SELF_TEST => b"self test failed\0",
+    CRYSTALS_ERROR_INTERNAL => b"internal error\0",
+    _ => b"unknown error\0",
+  };
+  text.as_ptr().cast()
+}
+
+/// Generates a keypair from the operating system's randomness
+#[no_mangle]
+pub unsafe extern "C" fn crystals_keypair(
+  pk: *mut u8,
+  pk_len: usize,
+  sk: *mut u8,
+  sk_len: usize,
+) -> c_int
+{
+  guard(|| {
+    let pk = output(pk, pk_len, PUBLICKEYBYTES)?;
+    let sk = output(sk, sk_len, SECRETKEYBYTES)?;
+    let keypair = Keypair::try_generate().map_err(self_test)?;
+    pk.copy_from_slice(keypair.public());
+    sk.copy_from_slice(keypair.expose_secret());
+    Ok(())
+  })
+}
+
+/// Derives the keypair of a 32-byte seed
+#[no_mangle]
+pub unsafe extern "C" fn crystals_keypair_from_seed(
+  seed: *const u8,
+  seed_len: usize,
+  pk: *mut u8,
+  pk_len: usize,
+  sk: *mut u8,
+  sk_len: usize,
+) -> c_int
+{
+  guard(|| {
+    let seed = exact(seed, seed_len, SEEDBYTES)?;
+    let pk = output(pk, pk_len, PUBLICKEYBYTES)?;
+    let sk = output(sk, sk_len, SECRETKEYBYTES)?;
+    let keypair =
+      Keypair::try_from_seed(seed.try_into().unwrap()).map_err(self_test)?;
+    pk.copy_from_slice(keypair.public());
+    sk.copy_from_slice(keypair.expose_secret());
+    Ok(())
+  })
+}
+
+/// Writes the detached signature of a message to `sig`
+#[no_mangle]
+pub unsafe extern "C" fn crystals_sign(
+  sig: *mut u8,
+  sig_len: usize,
+  msg: *const u8,
+  msg_len: usize,
+  sk: *const u8,
+  sk_len: usize,
+) -> c_int
+{
+  guard(|| {
+    let sig = output(sig, sig_len, SIGNBYTES)?;
+    let msg = input(msg, msg_len)?;
+    let sk = exact(sk, sk_len, SECRETKEYBYTES)?;
+    selftest::status().map_err(self_test)?;
+    crypto_sign_signature(sig, msg, sk);
+    Ok(())
+  })
+}
+
+/// Writes the signature followed by the message to `sm`, and its
+/// length, `crystals_signature_bytes() + msg_len`, to `sm_written`
+#[no_mangle]
+pub unsafe extern "C" fn crystals_sign_attached(
+  sm: *mut u8,
+  sm_len: usize,
+  sm_written: *mut usize,
+  msg: *const u8,
+  msg_len: usize,
+  sk: *const u8,
+  sk_len: usize,
+) -> c_int
+{
+  guard(|| {
+    let msg = input(msg, msg_len)?;
+    let sm = output(sm, sm_len, SIGNBYTES.saturating_add(msg_len))?;
+    let sk = exact(sk, sk_len, SECRETKEYBYTES)?;
+    if sm_written.is_null() {
+      return Err(CRYSTALS_ERROR_NULL);
+    }
+    selftest::status().map_err(self_test)?;
+    let (sig, rest) = sm.split_at_mut(SIGNBYTES);
+    rest.copy_from_slice(msg);
+    crypto_sign_signature(sig, msg, sk);
+    *sm_written = sm.len();
+    Ok(())
+  })
+}
+
+/// Verifies a detached signature
+#[no_mangle]
+pub unsafe extern "C" fn crystals_verify(
+  sig: *const u8,
+  sig_len: usize,
+  msg: *const u8,
+  msg_len: usize,
+  pk: *const u8,
+  pk_len: usize,
+) -> c_int
+{
+  guard(|| {
+    let sig = exact(sig, sig_len, SIGNBYTES)?;
+    let msg = input(msg, msg_len)?;
+    let pk = exact(pk, pk_len, PUBLICKEYBYTES)?;
+    verify(sig, msg, pk)
+  })
+}
+
+/// Verifies a signature followed by its message, as written by
+/// `crystals_sign_attached`, and copies the message to `msg` with its
+/// length in `msg_written`. Nothing is copied if it does not verify.
+#[no_mangle]
+pub unsafe extern "C" fn crystals_open(
+  msg: *mut u8,
+  msg_len: usize,
+  msg_written: *mut usize,
+  sm: *const u8,
+  sm_len: usize,
+  pk: *const u8,
+  pk_len: usize,
+) -> c_int
+{
+  guard(|| {
+    let sm = input(sm, sm_len)?;
+    let pk = exact(pk, pk_len, PUBLICKEYBYTES)?;
+    if msg_written.is_null() {
+      return Err(CRYSTALS_ERROR_NULL);
+    }
+    if sm.len() < SIGNBYTES {
+      return Err(CRYSTALS_ERROR_VERIFY);
+    }
+    let (sig, message) = sm.split_at(SIGNBYTES);
+    let out = match message.is_empty() {
+      true => &mut [][..],
+      false => output(msg, msg_len, message.len())?,
+    };
+    verify(sig, message, pk)?;
+    out.copy_from_slice(message);
+    *msg_written = message.len();
+    Ok(())
+  })
+}
+
+fn verify(sig: &[u8], msg: &[u8], pk: &[u8]) -> Result<(), c_int>
+{
+  let sig = sig.try_into().unwrap();
+  let pk = pk.try_into().unwrap();
+  crate::verify(sig, msg, pk).map_err(|err| match err {
+    SignError::SelfTest(_) => CRYSTALS_ERROR_SELF_TEST,
+    _ => CRYSTALS_ERROR_VERIFY,
+  })
+}
+
+/// Maps errors and panics to codes
+fn guard(f: impl FnOnce() -> Result<(), c_int>) -> c_int
+{
+  match catch_unwind(AssertUnwindSafe(f)) {
+    Ok(Ok(())) => CRYSTALS_OK,
+    Ok(Err(code)) => code,
+    Err(_) => CRYSTALS_ERROR_INTERNAL,
+  }
+}
+
+fn self_test(_: SelfTestError) -> c_int
+{
+  CRYSTALS_ERROR_SELF_TEST
+}
+
+/// Message input; null is the empty message
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], c_int>
+{
+  match (ptr.is_null(), len) {
+    (true, 0) => Ok(&[]),
+    (true, _) => Err(CRYSTALS_ERROR_NULL),
+    (false, _) => Ok(slice::from_raw_parts(ptr, len)),
+  }
+}
+
+/// Key, seed or signature input of a fixed size
+unsafe fn exact<'a>(
+  ptr: *const u8,
+  len: usize,
+  size: usize,
+) -> Result<&'a [u8], c_int>
+{
+  match (ptr.is_null(), len == size) {
+    (true, _) => Err(CRYSTALS_ERROR_NULL),
+    (false, false) => Err(CRYSTALS_ERROR_LENGTH),
+    (false, true) => Ok(slice::from_raw_parts(ptr, len)),
+  }
+}
+
+/// The first `size` bytes of an output buffer of `len` bytes
+unsafe fn output<'a>(
+  ptr: *mut u8,
+  len: usize,
+  size: usize,
+) -> Result<&'a mut [u8], c_int>
+{
+  match (ptr.is_null(), len >= size) {
+    (true, _) => Err(CRYSTALS_ERROR_NULL),
+    (false, false) => Err(CRYSTALS_ERROR_LENGTH),
+    (false, true) => Ok(slice::from_raw_parts_mut(ptr, size)),
+  }
+}
@@ -0,0 +1,223 @@
+//! Composite Ed25519 + Dilithium signatures.
+//!
+//! Layout follows draft-ietf-lamps-pq-composite-sigs: both components
+//! sign the message representative
+//!
+//!   M' = Prefix || Label || len(ctx) || ctx || SHA-512(M)
+//!
+//! and keys and signatures are the plain concatenation of the lattice
+//! component followed by the Ed25519 component. The keys here are
+//! round-3 Dilithium rather than ML-DSA, so the label names Dilithium
+//! instead of reusing an ML-DSA composite label.
+
+use crate::{
+  sign::crypto_sign_verify, Keypair, SignError, PUBLICKEYBYTES, SIGNBYTES,
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+#[cfg(feature = "offchain")]
+use crate::randombytes::*;
+
+pub const ED25519_PUBLICKEYBYTES: usize = 32;
+pub const ED25519_SECRETKEYBYTES: usize = 32;
+pub const ED25519_SIGNBYTES: usize = 64;
+
+pub const COMPOSITE_PUBLICKEYBYTES: usize =
+  PUBLICKEYBYTES + ED25519_PUBLICKEYBYTES;
+pub const COMPOSITE_SIGNBYTES: usize = SIGNBYTES + ED25519_SIGNBYTES;
+
+/// Maximum length of the application context string
+pub const MAX_CONTEXT_BYTES: usize = 255;
+
+const PREFIX: &[u8] = b"CompositeAlgorithmSignatures2025";
+
+#[cfg(feature = "mode2")]
+const LABEL: &[u8] = b"COMPSIG-DILITHIUM2-Ed25519-SHA512";
+#[cfg(not(any(feature = "mode2", feature = "mode5")))]
+const LABEL: &[u8] = b"COMPSIG-DILITHIUM3-Ed25519-SHA512";
+#[cfg(feature = "mode5")]
+const LABEL: &[u8] = b"COMPSIG-DILITHIUM5-Ed25519-SHA512";
+
+/// Builds the domain-separated message representative M'.
+fn message_representative(
+  msg: &[u8],
+  ctx: &[u8],
+) -> Result<Vec<u8>, SignError>
+{
+  if ctx.len() > MAX_CONTEXT_BYTES {
+    return Err(SignError::Input);
+  }
+  let mut m =
+    Vec::with_capacity(PREFIX.len() + LABEL.len() + 1 + ctx.len() + 64);
+  m.extend_from_slice(PREFIX);
+  m.extend_from_slice(LABEL);
+  m.push(ctx.len() as u8);
+  m.extend_from_slice(ctx);
+  m.extend_from_slice(&Sha512::digest(msg));
+  Ok(m)
+}
+
+pub struct CompositeKeypair
+{
+  dilithium: Keypair,
+  ed25519: SigningKey,
+}
+
+/// Secret keys elided
+impl std::fmt::Debug for CompositeKeypair
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(f, "public: {:?}\nsecret: <elided>", self.public())
+  }
+}
+
+impl CompositeKeypair
+{
+  /// Generates fresh Dilithium and Ed25519 keypairs
+  #[cfg(feature = "offchain")]
+  pub fn generate() -> CompositeKeypair
+  {
+    let mut ed25519_secret = [0u8; ED25519_SECRETKEYBYTES];
+    randombytes(&mut ed25519_secret, ED25519_SECRETKEYBYTES);
+    Self::from_parts(Keypair::generate(), &ed25519_secret)
+  }
+
+  /// Pairs an existing Dilithium keypair with an Ed25519 secret key,
+  /// e.g. the key of an existing Solana account
+  pub fn from_parts(
+    dilithium: Keypair,
+    ed25519_secret: &[u8; ED25519_SECRETKEYBYTES],
+  ) -> CompositeKeypair
+  {
+    CompositeKeypair {
+      dilithium,
+      ed25519: SigningKey::from_bytes(ed25519_secret),
+    }
+  }
+
+  /// The Dilithium component
+  pub fn dilithium(&self) -> &Keypair
+  {
+    &self.dilithium
+  }
+
+  /// Explicitly expose the Ed25519 secret key
+  pub fn expose_ed25519_secret(&self) -> &[u8; ED25519_SECRETKEYBYTES]
+  {
+    self.ed25519.as_bytes()
+  }
+
+  /// Composite public key, Dilithium public key || Ed25519 public key
+  pub fn public(&self) -> [u8; COMPOSITE_PUBLICKEYBYTES]
+  {
+    let mut pk = [0u8; COMPOSITE_PUBLICKEYBYTES];
+    pk[..PUBLICKEYBYTES].copy_from_slice(self.dilithium.public());
+    pk[PUBLICKEYBYTES..]
+      .copy_from_slice(self.ed25519.verifying_key().as_bytes());
+    pk
+  }
+
+  /// Signs `msg` under the application context `ctx` with both keys.
+  ///
+  /// Returns Dilithium signature || Ed25519 signature.
+  pub fn sign(
+    &self,
+    msg: &[u8],
+    ctx: &[u8],
+  ) -> Result<[u8; COMPOSITE_SIGNBYTES], SignError>
+  {
+    let m = message_representative(msg, ctx)?;
+    let mut sig = [0u8; COMPOSITE_SIGNBYTES];
+    sig[..SIGNBYTES].copy_from_slice(&self.dilithium.sign(&m));
+    sig[SIGNBYTES..].copy_from_slice(&self.ed25519.sign(&m).to_bytes());
+    Ok(sig)
+  }
+}
+
+/// Verify a composite signature. Both the Dilithium and the Ed25519
+/// component have to verify for the signature to be accepted.
+pub fn composite_verify(
+  sig: &[u8; COMPOSITE_SIGNBYTES],
+  msg: &[u8],
+  ctx: &[u8],
+  public_key: &[u8; COMPOSITE_PUBLICKEYBYTES],
+) -> Result<(), SignError>
+{
+  let m = message_representative(msg, ctx)?;
+
+  let mut ed_pk = [0u8; ED25519_PUBLICKEYBYTES];
+  ed_pk.copy_from_slice(&public_key[PUBLICKEYBYTES..]);
+  let mut ed_sig = [0u8; ED25519_SIGNBYTES];
+  ed_sig.copy_from_slice(&sig[SIGNBYTES..]);
+  let ed_key =
+    VerifyingKey::from_bytes(&ed_pk).map_err(|_| SignError::Input)?;
+
+  // Evaluate both components before deciding
+  let dilithium_ok =
+    crypto_sign_verify(&sig[..SIGNBYTES], &m, &public_key[..PUBLICKEYBYTES])
+      .is_ok();
+  let ed25519_ok = ed_key
+    .verify_strict(&m, &Signature::from_bytes(&ed_sig))
+    .is_ok();
+
+  if dilithium_ok && ed25519_ok {
+    Ok(())
+  } else {
+    Err(SignError::Verify)
+  }
+}
+
+#[cfg(all(test, feature = "offchain"))]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn sign_verify()
+  {
+    let keypair = CompositeKeypair::generate();
+    let pk = keypair.public();
+    let sig = keypair.sign(b"migrate", b"vault").unwrap();
+
+    assert!(composite_verify(&sig, b"migrate", b"vault", &pk).is_ok());
+    assert!(composite_verify(&sig, b"migrate", b"", &pk).is_err());
+    assert!(composite_verify(&sig, b"migrated", b"vault", &pk).is_err());
+    assert!(keypair.sign(b"migrate", &[0u8; 256]).is_err());
+  }
+
+  #[test]
+  fn both_components_required()
+  {
+    let keypair = CompositeKeypair::generate();
+    let pk = keypair.public();
+    let sig = keypair.sign(b"migrate", b"").unwrap();
+
+    // Break only the Dilithium component
+    let mut bad = sig;
+    bad[0] ^= 1;
+    assert!(composite_verify(&bad, b"migrate", b"", &pk).is_err());
+
+    // Break only the Ed25519 component
+    let mut bad = sig;
+    bad[COMPOSITE_SIGNBYTES - 1] ^= 1;
+    assert!(composite_verify(&bad, b"migrate", b"", &pk).is_err());
+
+    // The Ed25519 component must come from the paired Ed25519 key
+    let other = CompositeKeypair::from_parts(
+      keypair.dilithium().clone(),
+      &[1u8; ED25519_SECRETKEYBYTES],
+    );
+    let mut mixed = sig;
+    mixed[SIGNBYTES..]
+      .copy_from_slice(&other.sign(b"migrate", b"").unwrap()[SIGNBYTES..]);
+    assert!(composite_verify(&mixed, b"migrate", b"", &pk).is_err());
+
+    // and must sign the message representative, not the plain message
+    let ed25519 = SigningKey::from_bytes(keypair.expose_ed25519_secret());
+    let mut plain = sig;
+    plain[SIGNBYTES..].copy_from_slice(&ed25519.sign(b"migrate").to_bytes());
+    assert!(composite_verify(&plain, b"migrate", b"", &pk).is_err());
+  }
+}
@@ -0,0 +1,349 @@
+//! Message-free diagnostics for signatures and public keys.
+//!
+//! When a verification fails, [`inspect_signature`] tells a malformed
+//! signature apart from one that is well formed but does not match the
+//! message or key. It decodes (c, z, h) with the verifier's own
+//! `unpack_sig`, reports how far ‖z‖∞ is from the γ1 − β bound, and
+//! lists every violation of the hint encoding rather than stopping at
+//! the first. [`inspect_public_key`] decodes (rho, t1) and gives a short
+//! fingerprint.
+
+use core::fmt;
+
+use crate::{
+  fips202::shake256,
+  packing::{unpack_pk, unpack_sig},
+  params::*,
+  polyvec::{Polyveck, Polyvecl},
+  SignError,
+};
+
+/// An encoding rule of the hint vector h that a signature breaks. Any
+/// of these makes verification fail before the message is read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation
+{
+  /// The running hint count after `row` decreases or exceeds OMEGA
+  HintCount
+  {
+    row: usize, cumulative: u8
+  },
+  /// Hint positions of `row` are not strictly increasing at `slot`
+  HintOrder
+  {
+    row: usize, slot: usize
+  },
+  /// Unused hint `slot` is nonzero
+  HintPadding
+  {
+    slot: usize
+  },
+}
+
+/// Decoded signature and the checks verification makes on it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureReport
+{
+  /// Challenge seed c̃
+  pub c: [u8; SEEDBYTES],
+  /// The L polynomials of z, centred
+  pub z: Vec<[i32; N]>,
+  /// Hint positions of each of the K rows of h, as encoded
+  pub h: Vec<Vec<u8>>,
+  /// ‖z‖∞
+  pub z_norm: i32,
+  /// γ1 − β − ‖z‖∞; verification needs it to be positive
+  pub z_margin: i32,
+  pub violations: Vec<Violation>,
+}
+
+impl SignatureReport
+{
+  /// Hints set in each row of h
+  pub fn hint_counts(&self) -> Vec<usize>
+  {
+    self.h.iter().map(Vec::len).collect()
+  }
+
+  /// Hints set in h, at most OMEGA for a well-formed signature
+  pub fn hints(&self) -> usize
+  {
+    self.h.iter().map(Vec::len).sum()
+  }
+
+  /// Whether the signature passes every check that needs neither the
+  /// message nor the key. If it does and still fails to verify, it
+  /// belongs to another message or key.
+  pub fn well_formed(&self) -> bool
+  {
+    self.violations.is_empty() && self.z_margin > 0
+  }
+}
+
+impl fmt::Display for SignatureReport
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    writeln!(f, "c: {}", Hex(&self.c))?;
+    writeln!(
+      f,
+      "z: norm {}, margin {} against gamma1 - beta = {}",
+      self.z_norm,
+      self.z_margin,
+      GAMMA1 - BETA
+    )?;
+    writeln!(
+      f,
+      "h: {} of {} hints, per row {:?}",
+      self.hints(),
+      OMEGA,
+      self.hint_counts()
+    )?;
+    match self.violations.is_empty() {
+      true => write!(f, "violations: none"),
+      false => write!(f, "violations: {:?}", self.violations),
+    }
+  }
+}
+
+/// Decodes a packed signature. Fails only on a wrong length; encoding
+/// problems are listed in the report.
+pub fn inspect_signature(sig: &[u8]) -> Result<SignatureReport, SignError>
+{
+  if sig.len() != SIGNBYTES {
+    return Err(SignError::Input);
+  }
+  let mut c = [0u8; SEEDBYTES];
+  let mut z = Polyvecl::default();
+  let mut h = Polyveck::default();
+  // z is decoded before any hint check can fail, and every bit string
+  // is a valid z, so only h needs the detailed scan below
+  let unpacked = unpack_sig(&mut c, &mut z, &mut h, sig);
+
+  let z: Vec<[i32; N]> = z
+    .vec
+    .iter()
+    .map(|p| p.coeffs[..N].try_into().unwrap())
+    .collect();
+  let z_norm = z.iter().flatten().map(|x| x.abs()).max().unwrap_or(0);
+  let (h, violations) = scan_hints(&sig[SEEDBYTES + L * POLYZ_PACKEDBYTES..]);
+  debug_assert_eq!(unpacked.is_ok(), violations.is_empty());
+
+  Ok(SignatureReport {
+    c,
+    z,
+    h,
+    z_norm,
+    z_margin: (GAMMA1 - BETA) as i32 - z_norm,
+    violations,
+  })
+}
+
+/// Applies the rules of `unpack_sig` to the packed h, carrying on past
+/// violations. Positions of a row with a bad count are left empty.
+fn scan_hints(hint: &[u8]) -> (Vec<Vec<u8>>, Vec<Violation>)
+{
+  let mut rows = vec![vec![]; K];
+  let mut violations = vec![];
+  let mut k = 0usize;
+  for (row, positions) in rows.iter_mut().enumerate() {
+    let cumulative = hint[OMEGA + row];
+    let end = cumulative as usize;
+    if end < k || end > OMEGA {
+      violations.push(Violation::HintCount { row, cumulative });
+      continue;
+    }
+    for slot in k..end {
+      if slot > k && hint[slot] <= hint[slot - 1] {
+        violations.push(Violation::HintOrder { row, slot });
+      }
+      positions.push(hint[slot]);
+    }
+    k = end;
+  }
+  for (slot, &b) in hint.iter().enumerate().take(OMEGA).skip(k) {
+    if b != 0 {
+      violations.push(Violation::HintPadding { slot });
+    }
+  }
+  (rows, violations)
+}
+
+/// Decoded public key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKeyReport
+{
+  pub rho: [u8; SEEDBYTES],
+  /// The K polynomials of t1, coefficients in [0, 1024)
+  pub t1: Vec<[i32; N]>,
+  pub fingerprint: [u8; 8],
+}
+
+impl fmt::Display for PublicKeyReport
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    writeln!(f, "rho: {}", Hex(&self.rho))?;
+    write!(f, "fingerprint: {}", Hex(&self.fingerprint))
+  }
+}
+
+/// Decodes a packed public key. Every bit string of the right length is
+/// a valid key, so this fails only on a wrong length.
+pub fn inspect_public_key(pk: &[u8]) -> Result<PublicKeyReport, SignError>
+{
+  if pk.len() != PUBLICKEYBYTES {
+    return Err(SignError::Input);
+  }
+  let mut rho = [0u8; SEEDBYTES];
+  let mut t1 = Polyveck::default();
+  unpack_pk(&mut rho, &mut t1, pk);
+  Ok(PublicKeyReport {
+    rho,
+    t1: t1
+      .vec
+      .iter()
+      .map(|p| p.coeffs[..N].try_into().unwrap())
+      .collect(),
+    fingerprint: fingerprint(pk),
+  })
+}
+
+/// First 8 bytes of tr = SHAKE256(pk), the key hash every signature
+/// binds to
+pub fn fingerprint(pk: &[u8]) -> [u8; 8]
+{
+  let mut tr = [0u8; SEEDBYTES];
+  shake256(&mut tr, SEEDBYTES, pk, pk.len());
+  tr[..8].try_into().unwrap()
+}
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_>
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+  }
+}
+
+#[cfg(all(test, feature = "offchain"))]
+mod tests
+{
+  use super::*;
+  use crate::Keypair;
+
+  const HINTS: usize = SEEDBYTES + L * POLYZ_PACKEDBYTES;
+
+  fn signed() -> (Keypair, [u8; SIGNBYTES])
+  {
+    let keypair = Keypair::from_seed(&[5; SEEDBYTES]);
+    let sig = keypair.sign(b"diagnostics");
+    (keypair, sig)
+  }
+
+  fn verify(keypair: &Keypair, sig: &[u8; SIGNBYTES]) -> Result<(), SignError>
+  {
+    let pk = keypair.public().try_into().unwrap();
+    crate::verify(sig, b"diagnostics", pk)
+  }
+
+  #[test]
+  fn valid_signature()
+  {
+    let (keypair, sig) = signed();
+    let report = inspect_signature(&sig).unwrap();
+    assert!(report.well_formed(), "{}", report);
+    assert_eq!(report.c, sig[..SEEDBYTES]);
+    assert_eq!((report.z.len(), report.h.len()), (L, K));
+    assert!(report.z_margin > 0 && report.z_norm > 0);
+    assert!(report.hints() <= OMEGA);
+    assert_eq!(report.hint_counts().len(), K);
+
+    // a wrong message leaves the signature well formed
+    let pk = keypair.public().try_into().unwrap();
+    assert_eq!(crate::verify(&sig, b"other", pk), Err(SignError::Verify));
+    assert_eq!(inspect_signature(&sig[1..]), Err(SignError::Input));
+  }
+
+  #[test]
+  fn z_out_of_range()
+  {
+    let (keypair, mut sig) = signed();
+    // packed 0 decodes to gamma1
+    sig[SEEDBYTES..SEEDBYTES + 5].fill(0);
+    let report = inspect_signature(&sig).unwrap();
+    assert_eq!(report.z_norm, GAMMA1 as i32);
+    assert_eq!(report.z[0][0], GAMMA1 as i32);
+    assert_eq!(report.z_margin, -(BETA as i32));
+    assert!(report.violations.is_empty() && !report.well_formed());
+    assert_eq!(verify(&keypair, &sig), Err(SignError::Input));
+  }
+
+  #[test]
+  fn hint_violations()
+  {
+    let (keypair, sig) = signed();
+    let report = inspect_signature(&sig).unwrap();
+    let total = report.hints();
+
+    // a count beyond OMEGA; the hints it drops show up as padding
+    let mut bad = sig;
+    bad[HINTS + OMEGA + K - 1] = OMEGA as u8 + 1;
+    let report = inspect_signature(&bad).unwrap();
+    assert_eq!(
+      report.violations[0],
+      Violation::HintCount {
+        row: K - 1,
+        cumulative: OMEGA as u8 + 1
+      }
+    );
+    assert_eq!(verify(&keypair, &bad), Err(SignError::Input));
+
+    // nonzero padding after the last hint
+    let mut bad = sig;
+    bad[HINTS + OMEGA - 1] = 1;
+    if total < OMEGA {
+      let report = inspect_signature(&bad).unwrap();
+      assert_eq!(
+        report.violations,
+        [Violation::HintPadding { slot: OMEGA - 1 }]
+      );
+      assert!(!report.well_formed());
+      assert_eq!(verify(&keypair, &bad), Err(SignError::Input));
+    }
+
+    // repeated position in the first row with two hints
+    let counts = report.hint_counts();
+    if let Some(row) = (0..K).find(|&row| counts[row] >= 2) {
+      let first: usize = counts[..row].iter().sum();
+      let mut bad = sig;
+      bad[HINTS + first + 1] = bad[HINTS + first];
+      let report = inspect_signature(&bad).unwrap();
+      assert_eq!(
+        report.violations,
+        [Violation::HintOrder {
+          row,
+          slot: first + 1
+        }]
+      );
+      assert_eq!(verify(&keypair, &bad), Err(SignError::Input));
+    }
+  }
+
+  #[test]
+  fn public_key()
+  {
+    let (keypair, sig) = signed();
+    let report = inspect_public_key(keypair.public()).unwrap();
+    assert_eq!(report.rho, keypair.public()[..SEEDBYTES]);
+    assert_eq!(report.t1.len(), K);
+    assert!(report.t1.iter().flatten().all(|&x| (0..1024).contains(&x)));
+    assert_eq!(report.fingerprint, fingerprint(keypair.public()));
+    assert_ne!(report.fingerprint, fingerprint(&[0; PUBLICKEYBYTES]));
+    assert_eq!(inspect_public_key(&sig), Err(SignError::Input));
+    let text = report.to_string();
+    assert!(text.ends_with(&format!("{:02x}", report.fingerprint[7])));
+  }
+}
@@ -3,8 +3,10 @@
 
 pub const SHAKE128_RATE: usize = 168;
 pub const SHAKE256_RATE: usize = 136;
+pub const SHA3_256_RATE: usize = 136;
+pub const SHA3_512_RATE: usize = 72;
 
-const NROUNDS: usize = 24;
+pub const NROUNDS: usize = 24;
 
 #[derive(Copy, Clone)]
 pub struct KeccakState {
@@ -50,7 +52,7 @@ pub fn store64(x: &mut [u8], u: u64) {
 }
 
 /// Keccak round constants
-const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
+pub const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
   0x0000000000000001u64,
   0x0000000000008082u64,
   0x800000000000808au64,
@@ -326,7 +328,7 @@ pub fn keccakf1600_statepermute(state: &mut [u64]) {
 }
 
 /// Absorb step of Keccak; incremental.
-fn keccak_absorb(
+pub fn keccak_absorb(
   state: &mut KeccakState,
   r: usize,
   input: &[u8],
@@ -353,7 +355,7 @@ fn keccak_absorb(
 }
 
 /// Finalize absorb step.
-fn keccak_finalize(s: &mut [u64; 25], pos: usize, r: usize, p: u8) {
+pub fn keccak_finalize(s: &mut [u64; 25], pos: usize, r: usize, p: u8) {
   s[pos / 8] ^= (p as u64) << 8 * (pos % 8);
   s[r / 8 - 1] ^= 1u64 << 63;
 }
@@ -363,20 +365,20 @@ fn keccak_finalize(s: &mut [u64; 25], pos: usize, r: usize, p: u8) {
 ///  squeezing, i.e., is incremental.
 ///
 // Returns new position pos in current block
-fn keccak_squeeze(
+pub fn keccak_squeeze(
   out: &mut [u8],
   mut outlen: usize,
   s: &mut [u64; 25],
   mut pos: usize,
   r: usize,
 ) -> usize {
+  let mut idx = 0;
   while outlen != 0 {
     if pos == r {
       keccakf1600_statepermute(s);
       pos = 0;
     }
     let mut i = pos;
-    let mut idx = 0;
     while i < r && i < pos + outlen {
       out[idx] = (s[i / 8] >> 8 * (i % 8)) as u8;
       idx += 1;
@@ -517,3 +519,27 @@ pub fn shake256(
   let idx = nblocks * SHAKE256_RATE;
   shake256_squeeze(&mut output[idx..], outlen, &mut state);
 }
+
+/// SHA3-256 with non-incremental API
+#[cfg(any(feature = "hybrid", feature = "offchain", test))]
+pub fn sha3_256(h: &mut [u8], input: &[u8], inlen: usize) {
+  let mut s = [0u64; 25];
+
+  keccak_absorb_once(&mut s, SHA3_256_RATE, input, inlen, 0x06);
+  keccakf1600_statepermute(&mut s);
+  for i in 0..4 {
+    store64(&mut h[8 * i..], s[i]);
+  }
+}
+
+/// SHA3-512 with non-incremental API
+#[cfg(feature = "hybrid")]
+pub fn sha3_512(h: &mut [u8], input: &[u8], inlen: usize) {
+  let mut s = [0u64; 25];
+
+  keccak_absorb_once(&mut s, SHA3_512_RATE, input, inlen, 0x06);
+  keccakf1600_statepermute(&mut s);
+  for i in 0..8 {
+    store64(&mut h[8 * i..], s[i]);
+  }
+}
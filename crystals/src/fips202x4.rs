@@ -0,0 +1,236 @@
+#![allow(clippy::needless_range_loop)]
+
+use crate::fips202::*;
+
+/// Four independent Keccak states, stored lane by lane so that word `i`
+/// of all four states sits in `s[i]`. Each permutation step then works
+/// on `[u64; 4]` and the compiler can keep the four states in one
+/// vector register where the target has them.
+#[derive(Copy, Clone, Default)]
+pub struct KeccakStatex4 {
+  pub s: [[u64; 4]; 25],
+}
+
+/// Rotation offsets of rho, indexed by x + 5*y
+pub const KECCAKF_ROTC: [u32; 25] = [
+  0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8,
+  18, 2, 61, 56, 14,
+];
+
+/// Applies the Keccak F1600 permutation to four states at once.
+/// Lane l of the result equals keccakf1600_statepermute applied to
+/// lane l of the input.
+pub fn keccakf1600x4_statepermute(a: &mut [[u64; 4]; 25]) {
+  #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+  if crate::avx2::enabled() {
+    // SAFETY: the CPU supports AVX2
+    return unsafe { crate::avx2::keccakf1600x4_statepermute(a) };
+  }
+  keccakf1600x4_statepermute_portable(a)
+}
+
+/// Portable keccakf1600x4_statepermute.
+pub fn keccakf1600x4_statepermute_portable(a: &mut [[u64; 4]; 25]) {
+  let mut b = [[0u64; 4]; 25];
+  let mut c = [[0u64; 4]; 5];
+  for round in 0..NROUNDS {
+    // theta
+    for x in 0..5 {
+      for l in 0..4 {
+        c[x][l] =
+          a[x][l] ^ a[x + 5][l] ^ a[x + 10][l] ^ a[x + 15][l] ^ a[x + 20][l];
+      }
+    }
+    for x in 0..5 {
+      for l in 0..4 {
+        let d = c[(x + 4) % 5][l] ^ c[(x + 1) % 5][l].rotate_left(1);
+        for y in 0..5 {
+          a[x + 5 * y][l] ^= d;
+        }
+      }
+    }
+
+    // rho and pi
+    for x in 0..5 {
+      for y in 0..5 {
+        let to = y + 5 * ((2 * x + 3 * y) % 5);
+        for l in 0..4 {
+          b[to][l] = a[x + 5 * y][l].rotate_left(KECCAKF_ROTC[x + 5 * y]);
+        }
+      }
+    }
+
+    // chi
+    for y in 0..5 {
+      for x in 0..5 {
+        for l in 0..4 {
+          a[x + 5 * y][l] = b[x + 5 * y][l]
+            ^ ((!b[(x + 1) % 5 + 5 * y][l]) & b[(x + 2) % 5 + 5 * y][l]);
+        }
+      }
+    }
+
+    // iota
+    for l in 0..4 {
+      a[0][l] ^= KECCAKF_ROUNDCONSTANTS[round];
+    }
+  }
+}
+
+/// Initializes the four states and absorbs one input per lane.
+/// All inputs must have the same length.
+fn keccakx4_absorb_once(
+  s: &mut [[u64; 4]; 25],
+  r: usize,
+  input: [&[u8]; 4],
+  mut inlen: usize,
+  p: u8,
+) {
+  *s = [[0u64; 4]; 25];
+  let mut idx = 0;
+  while inlen >= r {
+    for i in 0..r / 8 {
+      for l in 0..4 {
+        s[i][l] ^= load64(&input[l][idx + 8 * i..]);
+      }
+    }
+    idx += r;
+    inlen -= r;
+    keccakf1600x4_statepermute(s);
+  }
+
+  for i in 0..inlen {
+    for l in 0..4 {
+      s[i / 8][l] ^= (input[l][idx + i] as u64) << (8 * (i % 8));
+    }
+  }
+  for l in 0..4 {
+    s[inlen / 8][l] ^= (p as u64) << (8 * (inlen % 8));
+    s[(r - 1) / 8][l] ^= 1u64 << 63;
+  }
+}
+
+/// Squeezes nblocks full blocks of r bytes from each lane into the
+/// matching output buffer.
+fn keccakx4_squeezeblocks(
+  out: [&mut [u8]; 4],
+  nblocks: usize,
+  s: &mut [[u64; 4]; 25],
+  r: usize,
+) {
+  let [out0, out1, out2, out3] = out;
+  let mut idx = 0usize;
+  for _ in 0..nblocks {
+    keccakf1600x4_statepermute(s);
+    for i in 0..(r >> 3) {
+      let off = idx + 8 * i;
+      store64(&mut out0[off..], s[i][0]);
+      store64(&mut out1[off..], s[i][1]);
+      store64(&mut out2[off..], s[i][2]);
+      store64(&mut out3[off..], s[i][3]);
+    }
+    idx += r;
+  }
+}
+
+/// Initialize and absorb four SHAKE128 inputs of equal length.
+pub fn shake128x4_absorb_once(
+  state: &mut KeccakStatex4,
+  input: [&[u8]; 4],
+  inlen: usize,
+) {
+  keccakx4_absorb_once(&mut state.s, SHAKE128_RATE, input, inlen, 0x1F);
+}
+
+/// Squeeze full SHAKE128 blocks from all four states.
+pub fn shake128x4_squeezeblocks(
+  out: [&mut [u8]; 4],
+  nblocks: usize,
+  state: &mut KeccakStatex4,
+) {
+  keccakx4_squeezeblocks(out, nblocks, &mut state.s, SHAKE128_RATE);
+}
+
+/// Initialize and absorb four SHAKE256 inputs of equal length.
+pub fn shake256x4_absorb_once(
+  state: &mut KeccakStatex4,
+  input: [&[u8]; 4],
+  inlen: usize,
+) {
+  keccakx4_absorb_once(&mut state.s, SHAKE256_RATE, input, inlen, 0x1F);
+}
+
+/// Squeeze full SHAKE256 blocks from all four states.
+pub fn shake256x4_squeezeblocks(
+  out: [&mut [u8]; 4],
+  nblocks: usize,
+  state: &mut KeccakStatex4,
+) {
+  keccakx4_squeezeblocks(out, nblocks, &mut state.s, SHAKE256_RATE);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn permute_matches_scalar() {
+    let mut x4 = KeccakStatex4::default();
+    let mut single = [[0u64; 25]; 4];
+    let mut v = 0x0123_4567_89ab_cdefu64;
+    for i in 0..25 {
+      for l in 0..4 {
+        v = v.wrapping_mul(6364136223846793005);
+        v = v.wrapping_add(1442695040888963407);
+        x4.s[i][l] = v;
+        single[l][i] = v;
+      }
+    }
+    for _ in 0..3 {
+      keccakf1600x4_statepermute(&mut x4.s);
+      for l in 0..4 {
+        keccakf1600_statepermute(&mut single[l]);
+      }
+    }
+    for i in 0..25 {
+      for l in 0..4 {
+        assert_eq!(x4.s[i][l], single[l][i]);
+      }
+    }
+  }
+
+  #[test]
+  fn shake_matches_scalar() {
+    let inputs: [Vec<u8>; 4] = core::array::from_fn(|l| {
+      (0..300).map(|i| (i * 7 + l * 31) as u8).collect()
+    });
+    let [in0, in1, in2, in3] = &inputs;
+    let input = [&in0[..], &in1[..], &in2[..], &in3[..]];
+    for inlen in [0, 34, 136, 168, 300] {
+      let mut out = [[0u8; 3 * SHAKE128_RATE]; 4];
+      let mut state = KeccakStatex4::default();
+      shake128x4_absorb_once(&mut state, input, inlen);
+      let [o0, o1, o2, o3] = &mut out;
+      shake128x4_squeezeblocks([o0, o1, o2, o3], 3, &mut state);
+      for l in 0..4 {
+        let mut expect = [0u8; 3 * SHAKE128_RATE];
+        let mut st = KeccakState::default();
+        shake128_absorb(&mut st, input[l], inlen);
+        shake128_finalize(&mut st);
+        shake128_squeezeblocks(&mut expect, 3, &mut st);
+        assert_eq!(out[l], expect);
+      }
+
+      let mut out = [[0u8; 2 * SHAKE256_RATE]; 4];
+      let mut state = KeccakStatex4::default();
+      shake256x4_absorb_once(&mut state, input, inlen);
+      let [o0, o1, o2, o3] = &mut out;
+      shake256x4_squeezeblocks([o0, o1, o2, o3], 2, &mut state);
+      for l in 0..4 {
+        let mut expect = [0u8; 2 * SHAKE256_RATE];
+        shake256(&mut expect, 2 * SHAKE256_RATE, input[l], inlen);
+        assert_eq!(out[l], expect);
+      }
+    }
+  }
+}
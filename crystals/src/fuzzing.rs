@@ -0,0 +1,13 @@
+//! Parsers and verification stages re-exported for the cargo-fuzz
+//! targets in `fuzz/`. Enabled by the `fuzzing` feature; not part of the
+//! stable API.
+
+pub use crate::packing::{unpack_pk, unpack_sig};
+pub use crate::poly::{polyt1_unpack, polyz_unpack, Poly};
+pub use crate::polyvec::{Polyveck, Polyvecl};
+#[cfg(feature = "offchain")]
+pub use crate::sign::crypto_sign_keypair;
+pub use crate::sign::{
+  crypto_sign_signature, crypto_sign_verify, crypto_sign_verify_stage1,
+  crypto_sign_verify_stage2,
+};
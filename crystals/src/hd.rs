@@ -0,0 +1,329 @@
+//! BIP-39 mnemonics and hierarchical deterministic key derivation.
+//!
+//! A mnemonic becomes a 64-byte master seed as in BIP-39. Lattice keys
+//! have no public child derivation, so the tree is hardened only, with
+//! SHAKE256 in place of BIP-32's HMAC-SHA512:
+//!
+//! ```text
+//! master      = SHAKE256("crystals-hd master" || seed, 64)
+//! child(i)    = SHAKE256("crystals-hd child" || chain || key || be32(i), 64)
+//! keygen seed = SHAKE256("crystals-hd keygen " || mode || key, 32)
+//! ```
+//!
+//! where each 64-byte output splits into `key || chain`, `i` has the
+//! hardened bit set, and `mode` is "dilithium2", "dilithium3" or
+//! "dilithium5". The keygen seed is passed to `crypto_sign_keypair`, so
+//! one path yields unrelated keys in each mode. Paths are written
+//! `m/44'/501'/0'/0'`, with `'` or `h` marking hardened indices.
+
+use bip39::Mnemonic;
+
+use crate::{
+  fips202::shake256, pkcs8::ALGORITHM, randombytes::*, HdError, Keypair,
+  SEEDBYTES,
+};
+
+/// Offset of hardened child indices
+pub const HARDENED: u32 = 1 << 31;
+
+const MASTER_LABEL: &[u8] = b"crystals-hd master";
+const CHILD_LABEL: &[u8] = b"crystals-hd child";
+const KEYGEN_LABEL: &[u8] = b"crystals-hd keygen ";
+
+/// Generates an English mnemonic of 12, 15, 18, 21 or 24 words.
+pub fn generate_mnemonic(words: usize) -> Result<String, HdError>
+{
+  if !(12..=24).contains(&words) || words % 3 != 0 {
+    return Err(HdError::Mnemonic);
+  }
+  let mut entropy = [0u8; 32];
+  let len = words / 3 * 4;
+  randombytes(&mut entropy, len);
+  let mnemonic =
+    Mnemonic::from_entropy(&entropy[..len]).map_err(|_| HdError::Mnemonic)?;
+  entropy.fill(0);
+  Ok(mnemonic.to_string())
+}
+
+/// BIP-39 seed of an English mnemonic, whose checksum must be valid.
+pub fn mnemonic_to_seed(
+  mnemonic: &str,
+  passphrase: &str,
+) -> Result<[u8; 64], HdError>
+{
+  let mnemonic = Mnemonic::parse(mnemonic).map_err(|_| HdError::Mnemonic)?;
+  Ok(mnemonic.to_seed(passphrase))
+}
+
+/// Derives the keypair at `path` from a mnemonic and passphrase.
+pub fn derive_keypair(
+  mnemonic: &str,
+  passphrase: &str,
+  path: &str,
+) -> Result<Keypair, HdError>
+{
+  let seed = mnemonic_to_seed(mnemonic, passphrase)?;
+  Ok(ExtendedKey::master(&seed)?.derive_path(path)?.keypair())
+}
+
+/// A node of the derivation tree
+#[derive(Clone, PartialEq, Eq)]
+pub struct ExtendedKey
+{
+  key: [u8; 32],
+  chain_code: [u8; 32],
+}
+
+/// Key material elided
+impl std::fmt::Debug for ExtendedKey
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(f, "ExtendedKey(<elided>)")
+  }
+}
+
+impl ExtendedKey
+{
+  /// Root of the tree for a 16 to 64 byte seed
+  pub fn master(seed: &[u8]) -> Result<ExtendedKey, HdError>
+  {
+    if !(16..=64).contains(&seed.len()) {
+      return Err(HdError::Seed);
+    }
+    Ok(Self::expand(&[MASTER_LABEL, seed].concat()))
+  }
+
+  /// Hardened child; `index` must include [`HARDENED`]
+  pub fn child(&self, index: u32) -> Result<ExtendedKey, HdError>
+  {
+    if index < HARDENED {
+      return Err(HdError::NonHardened);
+    }
+    let input = [
+      CHILD_LABEL,
+      &self.chain_code,
+      &self.key,
+      &index.to_be_bytes(),
+    ]
+    .concat();
+    Ok(Self::expand(&input))
+  }
+
+  /// Follows a path such as `m/44'/501'/0'/0'` from this key.
+  pub fn derive_path(&self, path: &str) -> Result<ExtendedKey, HdError>
+  {
+    parse_path(path)?
+      .into_iter()
+      .try_fold(self.clone(), |key, index| key.child(index))
+  }
+
+  /// Seed passed to `crypto_sign_keypair` for this node
+  pub fn keygen_seed(&self) -> [u8; SEEDBYTES]
+  {
+    let input = [KEYGEN_LABEL, ALGORITHM.name().as_bytes(), &self.key].concat();
+    let mut seed = [0u8; SEEDBYTES];
+    shake256(&mut seed, SEEDBYTES, &input, input.len());
+    seed
+  }
+
+  pub fn keypair(&self) -> Keypair
+  {
+    Keypair::from_seed(&self.keygen_seed())
+  }
+
+  fn expand(input: &[u8]) -> ExtendedKey
+  {
+    let mut out = [0u8; 64];
+    shake256(&mut out, 64, input, input.len());
+    let mut key = ExtendedKey {
+      key: [0u8; 32],
+      chain_code: [0u8; 32],
+    };
+    key.key.copy_from_slice(&out[..32]);
+    key.chain_code.copy_from_slice(&out[32..]);
+    out.fill(0);
+    key
+  }
+}
+
+/// Parses `m/a'/b'/...` into child indices. Every index must be
+/// hardened.
+pub fn parse_path(path: &str) -> Result<Vec<u32>, HdError>
+{
+  let mut parts = path.split('/');
+  if parts.next() != Some("m") {
+    return Err(HdError::Path);
+  }
+  parts
+    .map(|part| {
+      let (digits, hardened) = match part.strip_suffix(['\'', 'h']) {
+        Some(digits) => (digits, true),
+        None => (part, false),
+      };
+      // no signs, leading zeros or empty components
+      if digits.is_empty()
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+        || (digits.len() > 1 && digits.starts_with('0'))
+      {
+        return Err(HdError::Path);
+      }
+      let index: u32 = digits.parse().map_err(|_| HdError::Path)?;
+      match (index < HARDENED, hardened) {
+        (true, true) => Ok(index | HARDENED),
+        (true, false) => Err(HdError::NonHardened),
+        (false, _) => Err(HdError::Path),
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  const ABANDON: &str = "abandon abandon abandon abandon abandon abandon \
+                         abandon abandon abandon abandon abandon about";
+
+  fn digest(bytes: &[u8]) -> String
+  {
+    let mut out = [0u8; 32];
+    shake256(&mut out, 32, bytes, bytes.len());
+    hex::encode(out)
+  }
+
+  #[test]
+  fn bip39_vectors()
+  {
+    // from the reference test vectors (passphrase "TREZOR")
+    let vectors = [
+      (
+        ABANDON,
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e5349553\
+         1f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+      ),
+      (
+        "legal winner thank year wave sausage worth useful legal winner \
+         thank yellow",
+        "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6f\
+         a457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+      ),
+    ];
+    for (mnemonic, seed) in vectors {
+      let derived = mnemonic_to_seed(mnemonic, "TREZOR").unwrap();
+      assert_eq!(hex::encode(derived), seed);
+    }
+    // bad checksum
+    let bad = ABANDON.replace("about", "abandon");
+    assert_eq!(mnemonic_to_seed(&bad, ""), Err(HdError::Mnemonic));
+  }
+
+  #[test]
+  fn generated_mnemonics()
+  {
+    for words in [12, 15, 18, 21, 24] {
+      let mnemonic = generate_mnemonic(words).unwrap();
+      assert_eq!(mnemonic.split(' ').count(), words);
+      assert!(mnemonic_to_seed(&mnemonic, "").is_ok());
+    }
+    assert_eq!(generate_mnemonic(13), Err(HdError::Mnemonic));
+    assert_eq!(generate_mnemonic(27), Err(HdError::Mnemonic));
+  }
+
+  #[test]
+  fn paths()
+  {
+    let h = |i| i | HARDENED;
+    assert_eq!(parse_path("m"), Ok(vec![]));
+    assert_eq!(
+      parse_path("m/44'/501'/0h/2147483647'"),
+      Ok(vec![h(44), h(501), h(0), h(2147483647)])
+    );
+    assert_eq!(parse_path("m/44'/0"), Err(HdError::NonHardened));
+    for bad in ["", "44'", "m/", "m//0'", "m/01'", "m/+1'", "m/2147483648'"] {
+      assert_eq!(parse_path(bad), Err(HdError::Path), "{}", bad);
+    }
+
+    let master = ExtendedKey::master(&[1u8; 64]).unwrap();
+    assert_eq!(master.child(0), Err(HdError::NonHardened));
+    assert_eq!(
+      master.derive_path("m/1'/2'").unwrap(),
+      master.child(h(1)).unwrap().child(h(2)).unwrap()
+    );
+    assert_ne!(master.child(h(1)), master.child(h(2)));
+    assert_eq!(ExtendedKey::master(&[0u8; 15]), Err(HdError::Seed));
+  }
+
+  /// Published vectors, see the README. Mnemonic ABANDON, empty
+  /// passphrase; the columns are the keygen seed and SHAKE256-256 of the
+  /// packed public key.
+  #[test]
+  fn derivation_vectors()
+  {
+    let seed = mnemonic_to_seed(ABANDON, "").unwrap();
+    let master = ExtendedKey::master(&seed).unwrap();
+    for (path, keygen_seed, pk_digest) in VECTORS {
+      let node = master.derive_path(path).unwrap();
+      assert_eq!(hex::encode(node.keygen_seed()), keygen_seed, "{}", path);
+      let keypair = derive_keypair(ABANDON, "", path).unwrap();
+      assert_eq!(keypair, node.keypair());
+      assert_eq!(digest(keypair.public()), pk_digest, "{}", path);
+    }
+  }
+
+  #[cfg(feature = "mode2")]
+  const VECTORS: [(&str, &str, &str); 3] = [
+    (
+      "m/0'",
+      "867995c10f4a3b646fcb7cedf8a160966b56e7862a95852604deaee6c2487b09",
+      "b88c76bb61ce800e5a04fa7e56dbb8dc9124532af46b1483294b962c74c13782",
+    ),
+    (
+      "m/44'/501'/0'/0'",
+      "37ecdb5ff91daee958f88c0799fa55b459a61b85b4a755fee2015fc188527d3d",
+      "f9e2861dc1f71d2293cf8bc384fd7512e7e47ab2b1287f3662d53bd7f9b15139",
+    ),
+    (
+      "m/44'/501'/2147483647'/1'",
+      "4f8ed2195965afd211771a534f9728edcf86ec5dcdf424f0407278e4c753283d",
+      "c110673b6f2956df706c55c54813ed579da959333a45ce17325bdef1ce3aeb59",
+    ),
+  ];
+  #[cfg(not(any(feature = "mode2", feature = "mode5")))]
+  const VECTORS: [(&str, &str, &str); 3] = [
+    (
+      "m/0'",
+      "3d4056a17643b5e98b67ffab4c478b79aa6b3787bdadb8a063df5547e1212b29",
+      "134a41b541b53944247a8dab220b12edd63aa1243a5160654104f0da33894aa9",
+    ),
+    (
+      "m/44'/501'/0'/0'",
+      "205856fd69a53be4d6d025e5ec557a1a04e6523c7f81f8fa19fdc1d8f94fd626",
+      "3b9f32f6db8ebb2da51598620134e71ee1485835848d5f7ed1b86571fa5b3f34",
+    ),
+    (
+      "m/44'/501'/2147483647'/1'",
+      "77e636702954cd269a4f5abc00d50de622e60891ded945b0adf0dd598ccea7ff",
+      "27e36f8cbe7c88bfc09605912701b767f4d4d5c3519b83c116b66c80d4c12a8f",
+    ),
+  ];
+  #[cfg(feature = "mode5")]
+  const VECTORS: [(&str, &str, &str); 3] = [
+    (
+      "m/0'",
+      "bc9c62aaf7ab3281eb36cc53c3053e553d0de016474e6be06b021feead7b78a7",
+      "9d3e97537ad012f718647cc4196154991438be21d2e97e794a1ad6b2a762c18a",
+    ),
+    (
+      "m/44'/501'/0'/0'",
+      "ea469c5b1cca9a3c62cad26d82990e414e95d16000465fc57368502010b2018f",
+      "b333f202a55a23ab2c0a1e5363f25b23c9319659fa7901edfab1c05de0dadda0",
+    ),
+    (
+      "m/44'/501'/2147483647'/1'",
+      "2773df85a53f7a78b6c4285bf2c4afde41986c5dafd0b6164e96ebc2ee5b69d3",
+      "f22a4ecf1fb6ef91366fd68d0ff972eb8cbac11eec89ba3be9de70e77a83ccfa",
+    ),
+  ];
+}
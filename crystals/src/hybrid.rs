@@ -0,0 +1,343 @@
+//! Hybrid X25519 + ML-KEM-768 key encapsulation following X-Wing
+//! (draft-connolly-cfrg-xwing-kem).
+//!
+//! Wire encodings are fixed:
+//! - public key:  ML-KEM-768 encapsulation key (1184) || X25519 point (32)
+//! - secret key:  32-byte seed, expanded with SHAKE256 on every use
+//! - ciphertext:  ML-KEM-768 ciphertext (1088) || X25519 point (32)
+//!
+//! The shared secret is SHA3-256(ss_M || ss_X || ct_X || pk_X || label),
+//! so it stays secret as long as either component is unbroken.
+
+use crate::{fips202::*, mlkem, KemError};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+
+#[cfg(feature = "offchain")]
+use crate::randombytes::*;
+
+pub const PUBLICKEYBYTES: usize = mlkem::PUBLICKEYBYTES + X25519_BYTES;
+pub const SECRETKEYBYTES: usize = 32;
+pub const CIPHERTEXTBYTES: usize = mlkem::CIPHERTEXTBYTES + X25519_BYTES;
+pub const SSBYTES: usize = 32;
+/// Randomness consumed by a single encapsulation
+pub const ENCAPSSEEDBYTES: usize = 64;
+
+const X25519_BYTES: usize = 32;
+const XWING_LABEL: [u8; 6] = *b"\\.//^\\";
+
+/// Expanded form of the 32-byte X-Wing secret seed
+struct ExpandedKey
+{
+  sk_m: Box<[u8]>,
+  sk_x: [u8; X25519_BYTES],
+  pk: Box<[u8]>,
+}
+
+fn expand_secret(sk: &[u8]) -> ExpandedKey
+{
+  let mut expanded = [0u8; 96];
+  shake256(&mut expanded, 96, sk, SECRETKEYBYTES);
+
+  let mut pk = vec![0u8; PUBLICKEYBYTES].into_boxed_slice();
+  let mut sk_m = vec![0u8; mlkem::SECRETKEYBYTES].into_boxed_slice();
+  mlkem::crypto_kem_keypair_derand(
+    &mut pk[..mlkem::PUBLICKEYBYTES],
+    &mut sk_m,
+    &expanded[..64],
+  )
+  .expect("buffers sized for ML-KEM-768");
+
+  let mut sk_x = [0u8; X25519_BYTES];
+  sk_x.copy_from_slice(&expanded[64..]);
+  pk[mlkem::PUBLICKEYBYTES..]
+    .copy_from_slice(&x25519(sk_x, X25519_BASEPOINT_BYTES));
+
+  ExpandedKey { sk_m, sk_x, pk }
+}
+
+fn combiner(ss: &mut [u8], ss_m: &[u8], ss_x: &[u8], ct_x: &[u8], pk_x: &[u8])
+{
+  let mut input = [0u8; 32 + 32 + 32 + 32 + XWING_LABEL.len()];
+  input[..32].copy_from_slice(ss_m);
+  input[32..64].copy_from_slice(ss_x);
+  input[64..96].copy_from_slice(ct_x);
+  input[96..128].copy_from_slice(pk_x);
+  input[128..].copy_from_slice(&XWING_LABEL);
+  sha3_256(ss, &input, input.len());
+}
+
+/// Derives the public key for a 32-byte secret seed.
+pub fn crypto_kem_keypair_derand(
+  pk: &mut [u8],
+  sk: &[u8],
+) -> Result<(), KemError>
+{
+  if sk.len() != SECRETKEYBYTES {
+    return Err(KemError::InvalidSecretKey);
+  }
+  if pk.len() < PUBLICKEYBYTES {
+    return Err(KemError::InvalidLength);
+  }
+  pk[..PUBLICKEYBYTES].copy_from_slice(&expand_secret(sk).pk);
+  Ok(())
+}
+
+/// Encapsulates to `pk` using 64 bytes of caller-provided randomness.
+pub fn crypto_kem_enc_derand(
+  ct: &mut [u8],
+  ss: &mut [u8],
+  pk: &[u8],
+  eseed: &[u8],
+) -> Result<(), KemError>
+{
+  if pk.len() != PUBLICKEYBYTES {
+    return Err(KemError::InvalidPublicKey);
+  }
+  if ct.len() < CIPHERTEXTBYTES
+    || ss.len() < SSBYTES
+    || eseed.len() != ENCAPSSEEDBYTES
+  {
+    return Err(KemError::InvalidLength);
+  }
+  let (pk_m, pk_x) = pk.split_at(mlkem::PUBLICKEYBYTES);
+
+  let mut ek_x = [0u8; X25519_BYTES];
+  ek_x.copy_from_slice(&eseed[32..ENCAPSSEEDBYTES]);
+  let mut pk_x_arr = [0u8; X25519_BYTES];
+  pk_x_arr.copy_from_slice(pk_x);
+  let ct_x = x25519(ek_x, X25519_BASEPOINT_BYTES);
+  let ss_x = x25519(ek_x, pk_x_arr);
+
+  let mut ss_m = [0u8; mlkem::SSBYTES];
+  mlkem::crypto_kem_enc_derand(
+    &mut ct[..mlkem::CIPHERTEXTBYTES],
+    &mut ss_m,
+    pk_m,
+    &eseed[..32],
+  )?;
+  ct[mlkem::CIPHERTEXTBYTES..CIPHERTEXTBYTES].copy_from_slice(&ct_x);
+
+  combiner(ss, &ss_m, &ss_x, &ct_x, pk_x);
+  Ok(())
+}
+
+/// Recovers the shared secret for `ct` with the 32-byte secret seed.
+pub fn crypto_kem_dec(
+  ss: &mut [u8],
+  ct: &[u8],
+  sk: &[u8],
+) -> Result<(), KemError>
+{
+  if ct.len() != CIPHERTEXTBYTES {
+    return Err(KemError::InvalidCiphertext);
+  }
+  if sk.len() != SECRETKEYBYTES {
+    return Err(KemError::InvalidSecretKey);
+  }
+  if ss.len() < SSBYTES {
+    return Err(KemError::InvalidLength);
+  }
+  let key = expand_secret(sk);
+  let (ct_m, ct_x) = ct.split_at(mlkem::CIPHERTEXTBYTES);
+
+  let mut ss_m = [0u8; mlkem::SSBYTES];
+  mlkem::crypto_kem_dec(&mut ss_m, ct_m, &key.sk_m)?;
+
+  let mut ct_x_arr = [0u8; X25519_BYTES];
+  ct_x_arr.copy_from_slice(ct_x);
+  let ss_x = x25519(key.sk_x, ct_x_arr);
+
+  combiner(ss, &ss_m, &ss_x, ct_x, &key.pk[mlkem::PUBLICKEYBYTES..]);
+  Ok(())
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct HybridKeypair
+{
+  public: Box<[u8]>,
+  secret: Box<[u8]>,
+}
+
+/// Secret key elided
+impl std::fmt::Debug for HybridKeypair
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(f, "public: {:?}\nsecret: <elided>", self.public)
+  }
+}
+
+impl HybridKeypair
+{
+  /// Explicitly expose secret key
+  pub fn expose_secret(&self) -> &[u8]
+  {
+    &self.secret
+  }
+
+  /// Encoded public key, ML-KEM-768 key followed by the X25519 point
+  pub fn public(&self) -> &[u8]
+  {
+    &self.public
+  }
+
+  /// Generates a hybrid keypair from system randomness
+  #[cfg(feature = "offchain")]
+  pub fn generate() -> HybridKeypair
+  {
+    let mut seed = [0u8; SECRETKEYBYTES];
+    randombytes(&mut seed, SECRETKEYBYTES);
+    Self::from_seed(&seed)
+  }
+
+  /// Deterministically derives a hybrid keypair from a 32-byte seed
+  pub fn from_seed(seed: &[u8; SECRETKEYBYTES]) -> HybridKeypair
+  {
+    let mut public = vec![0u8; PUBLICKEYBYTES].into_boxed_slice();
+    crypto_kem_keypair_derand(&mut public, seed)
+      .expect("buffer sized for the public key");
+    HybridKeypair {
+      public,
+      secret: seed.to_vec().into_boxed_slice(),
+    }
+  }
+
+  /// Recovers the shared secret from a ciphertext
+  pub fn decapsulate(&self, ct: &[u8]) -> Result<[u8; SSBYTES], KemError>
+  {
+    let mut ss = [0u8; SSBYTES];
+    crypto_kem_dec(&mut ss, ct, &self.secret)?;
+    Ok(ss)
+  }
+}
+
+/// Encapsulates a fresh shared secret to a hybrid public key.
+///
+/// Returns the ciphertext and the shared secret.
+#[cfg(feature = "offchain")]
+pub fn encapsulate(
+  pk: &[u8],
+) -> Result<([u8; CIPHERTEXTBYTES], [u8; SSBYTES]), KemError>
+{
+  let mut eseed = [0u8; ENCAPSSEEDBYTES];
+  randombytes(&mut eseed, ENCAPSSEEDBYTES);
+  encapsulate_derand(pk, &eseed)
+}
+
+/// Deterministic variant of `encapsulate` for test vectors and
+/// environments that supply their own randomness.
+pub fn encapsulate_derand(
+  pk: &[u8],
+  eseed: &[u8; ENCAPSSEEDBYTES],
+) -> Result<([u8; CIPHERTEXTBYTES], [u8; SSBYTES]), KemError>
+{
+  let mut ct = [0u8; CIPHERTEXTBYTES];
+  let mut ss = [0u8; SSBYTES];
+  crypto_kem_enc_derand(&mut ct, &mut ss, pk, eseed)?;
+  Ok((ct, ss))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  // First vector of the X-Wing draft (spec/test-vectors.json). The public
+  // key and ciphertext are compared by their SHA3-256 digests.
+  const SEED: &str =
+    "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26";
+  const ESEED: &str = "3cb1eea988004b93103cfb0aeefd2a686e01fa4a58e8a3639ca8a1e3f9ae57e2\
+                       35b8cc873c23dc62b8d260169afa2f75ab916a58d974918835d25e6a435085b2";
+  const PK_DIGEST: &str =
+    "5121745904643ad9dfacca7869292c19a8a69533b53e60666b7db910b4ad6367";
+  const CT_DIGEST: &str =
+    "c0abd149f83f45324ac3a7ddc7606c71f257e5ea86113522834a0ee1bcb34e3e";
+  const SS: &str =
+    "d2df0522128f09dd8e2c92b1e905c793d8f57a54c3da25861f10bf4ca613e384";
+
+  fn digest(x: &[u8]) -> String
+  {
+    let mut h = [0u8; 32];
+    sha3_256(&mut h, x, x.len());
+    hex::encode(h)
+  }
+
+  #[test]
+  fn xwing_test_vector()
+  {
+    let seed: [u8; SECRETKEYBYTES] =
+      hex::decode(SEED).unwrap().try_into().unwrap();
+    let eseed: [u8; ENCAPSSEEDBYTES] = hex::decode(ESEED.replace(' ', ""))
+      .unwrap()
+      .try_into()
+      .unwrap();
+
+    let keypair = HybridKeypair::from_seed(&seed);
+    assert_eq!(digest(keypair.public()), PK_DIGEST);
+
+    let (ct, ss) = encapsulate_derand(keypair.public(), &eseed).unwrap();
+    assert_eq!(digest(&ct), CT_DIGEST);
+    assert_eq!(hex::encode(ss), SS);
+    assert_eq!(keypair.decapsulate(&ct).unwrap(), ss);
+  }
+
+  #[test]
+  fn tampered_ciphertext()
+  {
+    let keypair = HybridKeypair::from_seed(&[7u8; SECRETKEYBYTES]);
+    let (mut ct, ss) =
+      encapsulate_derand(keypair.public(), &[9u8; ENCAPSSEEDBYTES]).unwrap();
+
+    // Either half of the ciphertext changes the combined secret
+    ct[0] ^= 1;
+    assert_ne!(keypair.decapsulate(&ct).unwrap(), ss);
+    ct[0] ^= 1;
+    ct[CIPHERTEXTBYTES - 1] ^= 1;
+    assert_ne!(keypair.decapsulate(&ct).unwrap(), ss);
+
+    assert_eq!(
+      keypair.decapsulate(&ct[1..]),
+      Err(KemError::InvalidCiphertext)
+    );
+    assert_eq!(
+      encapsulate_derand(&keypair.public()[1..], &[0u8; ENCAPSSEEDBYTES]),
+      Err(KemError::InvalidPublicKey)
+    );
+  }
+
+  #[test]
+  fn buffer_lengths()
+  {
+    let keypair = HybridKeypair::from_seed(&[7u8; SECRETKEYBYTES]);
+    let (mut ct, mut ss) = ([0u8; CIPHERTEXTBYTES], [0u8; SSBYTES]);
+    let mut pk = [0u8; PUBLICKEYBYTES];
+    let pk_in = keypair.public();
+    let eseed = [9u8; ENCAPSSEEDBYTES];
+
+    assert_eq!(
+      crypto_kem_keypair_derand(&mut pk, &[7u8; SECRETKEYBYTES - 1]),
+      Err(KemError::InvalidSecretKey)
+    );
+    assert_eq!(
+      crypto_kem_keypair_derand(&mut pk[1..], &[7u8; SECRETKEYBYTES]),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct, &mut ss, pk_in, &eseed[1..]),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct[1..], &mut ss, pk_in, &eseed),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct, &mut ss[1..], pk_in, &eseed),
+      Err(KemError::InvalidLength)
+    );
+    crypto_kem_enc_derand(&mut ct, &mut ss, pk_in, &eseed).unwrap();
+    assert_eq!(
+      crypto_kem_dec(&mut ss[1..], &ct, keypair.expose_secret()),
+      Err(KemError::InvalidLength)
+    );
+  }
+}
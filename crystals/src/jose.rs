@@ -0,0 +1,639 @@
+//! JOSE and COSE: AKP keys as JWK and COSE_Key, JWS compact
+//! serialization (RFC 7515) and COSE_Sign1 (RFC 9052).
+//!
+//! Keys use the Algorithm Key Pair ("AKP") key type of
+//! draft-ietf-cose-dilithium, with the packed public key in `pub` and the
+//! key generation seed in `priv`. The ML-DSA identifiers registered there
+//! (ML-DSA-44/65/87, COSE -48/-49/-50) are recognised and rejected: these
+//! are round-3 Dilithium signatures, which do not verify as ML-DSA. They
+//! carry the JOSE names DILITHIUM2/3/5 and COSE values from the
+//! private-use range instead.
+
+use ciborium::value::Value;
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+  pkcs8::{base64_decode, base64_encode, Algorithm, ALGORITHM, BASE64URL},
+  verify, JoseError, Keypair, SignError, PUBLICKEYBYTES, SEEDBYTES, SIGNBYTES,
+};
+
+/// COSE_Key labels and the AKP key type
+const KEY_KTY: i64 = 1;
+const KEY_ALG: i64 = 3;
+const KEY_PUB: i64 = -1;
+const KEY_PRIV: i64 = -2;
+const KTY_AKP: i64 = 7;
+
+/// COSE header labels
+const HEADER_ALG: i64 = 1;
+const HEADER_CRIT: i64 = 2;
+
+const COSE_SIGN1_TAG: u64 = 18;
+
+impl Algorithm
+{
+  /// JOSE "alg" value
+  pub fn jose_name(self) -> &'static str
+  {
+    match self {
+      Algorithm::Dilithium2 => "DILITHIUM2",
+      Algorithm::Dilithium3 => "DILITHIUM3",
+      Algorithm::Dilithium5 => "DILITHIUM5",
+      Algorithm::MlDsa44 => "ML-DSA-44",
+      Algorithm::MlDsa65 => "ML-DSA-65",
+      Algorithm::MlDsa87 => "ML-DSA-87",
+    }
+  }
+
+  /// COSE "alg" value; the round-3 values are private use
+  pub fn cose_alg(self) -> i64
+  {
+    match self {
+      Algorithm::Dilithium2 => -65537,
+      Algorithm::Dilithium3 => -65538,
+      Algorithm::Dilithium5 => -65539,
+      Algorithm::MlDsa44 => -48,
+      Algorithm::MlDsa65 => -49,
+      Algorithm::MlDsa87 => -50,
+    }
+  }
+
+  pub fn from_jose_name(name: &str) -> Option<Algorithm>
+  {
+    Algorithm::ALL.into_iter().find(|a| a.jose_name() == name)
+  }
+
+  pub fn from_cose_alg(alg: i64) -> Option<Algorithm>
+  {
+    Algorithm::ALL.into_iter().find(|a| a.cose_alg() == alg)
+  }
+}
+
+/// Public key as an AKP JWK.
+pub fn public_jwk(pk: &[u8]) -> Result<String, JoseError>
+{
+  let pk = public_key(pk)?;
+  Ok(jwk(pk, None))
+}
+
+/// Private key as an AKP JWK holding the seed and its public key.
+#[cfg(feature = "offchain")]
+pub fn private_jwk(seed: &[u8; SEEDBYTES]) -> String
+{
+  jwk(Keypair::from_seed(seed).public(), Some(seed))
+}
+
+pub fn public_key_from_jwk(jwk: &str) -> Result<Vec<u8>, JoseError>
+{
+  Ok(parse_jwk(jwk)?.0)
+}
+
+/// Rebuilds the keypair of a private JWK and checks it against `pub`.
+#[cfg(feature = "offchain")]
+pub fn keypair_from_jwk(jwk: &str) -> Result<Keypair, JoseError>
+{
+  let (pk, seed) = parse_jwk(jwk)?;
+  keypair(&pk, seed)
+}
+
+/// Public key as an AKP COSE_Key.
+pub fn public_cose_key(pk: &[u8]) -> Result<Vec<u8>, JoseError>
+{
+  let pk = public_key(pk)?;
+  Ok(cose_key(pk, None))
+}
+
+/// Private key as an AKP COSE_Key holding the seed and its public key.
+#[cfg(feature = "offchain")]
+pub fn private_cose_key(seed: &[u8; SEEDBYTES]) -> Vec<u8>
+{
+  cose_key(Keypair::from_seed(seed).public(), Some(seed))
+}
+
+pub fn public_key_from_cose_key(key: &[u8]) -> Result<Vec<u8>, JoseError>
+{
+  Ok(parse_cose_key(key)?.0)
+}
+
+/// Rebuilds the keypair of a private COSE_Key and checks it against
+/// `pub`.
+#[cfg(feature = "offchain")]
+pub fn keypair_from_cose_key(key: &[u8]) -> Result<Keypair, JoseError>
+{
+  let (pk, seed) = parse_cose_key(key)?;
+  keypair(&pk, seed)
+}
+
+/// Signs `payload` as a JWS in compact serialization.
+pub fn jws_sign(keypair: &Keypair, payload: &[u8]) -> String
+{
+  let header = json!({ "alg": ALGORITHM.jose_name() });
+  let input = format!(
+    "{}.{}",
+    base64url(header.to_string().as_bytes()),
+    base64url(payload)
+  );
+  let sig = keypair.sign(input.as_bytes());
+  format!("{}.{}", input, base64url(&sig))
+}
+
+/// Verifies a compact JWS and returns its payload. The protected header
+/// must name this parameter set and may not carry `crit`, as no
+/// extensions are understood.
+pub fn jws_verify(jws: &str, pk: &[u8]) -> Result<Vec<u8>, JoseError>
+{
+  let pk = public_key(pk)?;
+  let parts: Vec<&str> = jws.split('.').collect();
+  let [header, payload, sig] = parts[..] else {
+    return Err(JoseError::Malformed);
+  };
+  let header = match serde_json::from_slice(&base64url_decode(header)?) {
+    Ok(Json::Object(header)) => header,
+    _ => return Err(JoseError::Malformed),
+  };
+  check_jose_alg(header.get("alg"))?;
+  if header.contains_key("crit") {
+    return Err(JoseError::Header);
+  }
+  // RFC 7797 unencoded payloads need crit, but don't guess
+  if header
+    .get("b64")
+    .is_some_and(|b64| b64 != &Json::Bool(true))
+  {
+    return Err(JoseError::Header);
+  }
+
+  let sig = signature(&base64url_decode(sig)?)?;
+  let decoded = base64url_decode(payload)?;
+  let input = &jws[..jws.len() - parts[2].len() - 1];
+  verify(&sig, input.as_bytes(), pk).map_err(JoseError::Signature)?;
+  Ok(decoded)
+}
+
+/// Signs `payload` as a tagged COSE_Sign1 with the algorithm in the
+/// protected header.
+pub fn cose_sign1(keypair: &Keypair, payload: &[u8]) -> Vec<u8>
+{
+  let protected = to_cbor(&Value::Map(vec![(
+    int(HEADER_ALG),
+    int(ALGORITHM.cose_alg()),
+  )]));
+  let sig = keypair.sign(&sig_structure(&protected, payload));
+  to_cbor(&Value::Tag(
+    COSE_SIGN1_TAG,
+    Box::new(Value::Array(vec![
+      Value::Bytes(protected),
+      Value::Map(vec![]),
+      Value::Bytes(payload.to_vec()),
+      Value::Bytes(sig.to_vec()),
+    ])),
+  ))
+}
+
+/// Verifies a COSE_Sign1, tagged or untagged, and returns its payload.
+/// The algorithm must be protected, `crit` is rejected, and no label may
+/// appear twice or in both header buckets. Detached payloads are not
+/// supported.
+pub fn cose_sign1_verify(msg: &[u8], pk: &[u8]) -> Result<Vec<u8>, JoseError>
+{
+  let pk = public_key(pk)?;
+  let items = match from_cbor(msg)? {
+    Value::Tag(COSE_SIGN1_TAG, inner) => *inner,
+    Value::Tag(..) => return Err(JoseError::Malformed),
+    untagged => untagged,
+  };
+  let items = match items {
+    Value::Array(items) => items,
+    _ => return Err(JoseError::Malformed),
+  };
+  let (protected_bytes, unprotected, payload, sig) = match &items[..] {
+    [
+      Value::Bytes(protected),
+      Value::Map(unprotected),
+      Value::Bytes(payload),
+      Value::Bytes(sig),
+    ] => (protected, unprotected, payload, sig),
+    _ => return Err(JoseError::Malformed),
+  };
+  let protected = match protected_bytes.is_empty() {
+    true => vec![],
+    false => match from_cbor(protected_bytes)? {
+      Value::Map(protected) => protected,
+      _ => return Err(JoseError::Malformed),
+    },
+  };
+
+  check_cose_alg(lookup(&protected, HEADER_ALG)?)?;
+  for label in [HEADER_ALG, HEADER_CRIT] {
+    if lookup(unprotected, label)?.is_some() {
+      return Err(JoseError::Header);
+    }
+  }
+  if lookup(&protected, HEADER_CRIT)?.is_some() {
+    return Err(JoseError::Header);
+  }
+  for (label, _) in unprotected {
+    if protected.iter().any(|(p, _)| p == label) {
+      return Err(JoseError::Header);
+    }
+  }
+
+  let sig = signature(sig)?;
+  verify(&sig, &sig_structure(protected_bytes, payload), pk)
+    .map_err(JoseError::Signature)?;
+  Ok(payload.clone())
+}
+
+fn public_key(pk: &[u8]) -> Result<&[u8; PUBLICKEYBYTES], JoseError>
+{
+  pk.try_into().map_err(|_| JoseError::InvalidKey)
+}
+
+fn signature(sig: &[u8]) -> Result<[u8; SIGNBYTES], JoseError>
+{
+  sig
+    .try_into()
+    .map_err(|_| JoseError::Signature(SignError::Input))
+}
+
+#[cfg(feature = "offchain")]
+fn keypair(
+  pk: &[u8],
+  seed: Option<[u8; SEEDBYTES]>,
+) -> Result<Keypair, JoseError>
+{
+  let keypair = Keypair::from_seed(&seed.ok_or(JoseError::InvalidKey)?);
+  match keypair.public() == pk {
+    true => Ok(keypair),
+    false => Err(JoseError::InvalidKey),
+  }
+}
+
+fn seed(bytes: &[u8]) -> Result<[u8; SEEDBYTES], JoseError>
+{
+  bytes.try_into().map_err(|_| JoseError::InvalidKey)
+}
+
+fn base64url(data: &[u8]) -> String
+{
+  base64_encode(data, BASE64URL, false)
+}
+
+fn base64url_decode(text: &str) -> Result<Vec<u8>, JoseError>
+{
+  base64_decode(text, BASE64URL, false).ok_or(JoseError::Malformed)
+}
+
+fn jwk(pk: &[u8], seed: Option<&[u8; SEEDBYTES]>) -> String
+{
+  let mut jwk = Map::new();
+  jwk.insert("kty".into(), "AKP".into());
+  jwk.insert("alg".into(), ALGORITHM.jose_name().into());
+  jwk.insert("pub".into(), base64url(pk).into());
+  if let Some(seed) = seed {
+    jwk.insert("priv".into(), base64url(seed).into());
+  }
+  Json::Object(jwk).to_string()
+}
+
+fn parse_jwk(jwk: &str)
+  -> Result<(Vec<u8>, Option<[u8; SEEDBYTES]>), JoseError>
+{
+  let jwk = match serde_json::from_str(jwk) {
+    Ok(Json::Object(jwk)) => jwk,
+    _ => return Err(JoseError::Malformed),
+  };
+  if jwk.get("kty") != Some(&json!("AKP")) {
+    return Err(JoseError::InvalidKey);
+  }
+  check_jose_alg(jwk.get("alg"))?;
+  let field = |name| match jwk.get(name) {
+    Some(Json::String(value)) => base64url_decode(value).map(Some),
+    Some(_) => Err(JoseError::Malformed),
+    None => Ok(None),
+  };
+  let pk = field("pub")?.ok_or(JoseError::InvalidKey)?;
+  public_key(&pk)?;
+  let seed = field("priv")?.as_deref().map(seed).transpose()?;
+  Ok((pk, seed))
+}
+
+fn check_jose_alg(alg: Option<&Json>) -> Result<(), JoseError>
+{
+  match alg {
+    Some(Json::String(name)) => match Algorithm::from_jose_name(name) {
+      Some(ALGORITHM) => Ok(()),
+      _ => Err(JoseError::UnsupportedAlgorithm),
+    },
+    Some(_) => Err(JoseError::Malformed),
+    None => Err(JoseError::Header),
+  }
+}
+
+fn cose_key(pk: &[u8], seed: Option<&[u8; SEEDBYTES]>) -> Vec<u8>
+{
+  let mut key = vec![
+    (int(KEY_KTY), int(KTY_AKP)),
+    (int(KEY_ALG), int(ALGORITHM.cose_alg())),
+    (int(KEY_PUB), Value::Bytes(pk.to_vec())),
+  ];
+  if let Some(seed) = seed {
+    key.push((int(KEY_PRIV), Value::Bytes(seed.to_vec())));
+  }
+  to_cbor(&Value::Map(key))
+}
+
+fn parse_cose_key(
+  key: &[u8],
+) -> Result<(Vec<u8>, Option<[u8; SEEDBYTES]>), JoseError>
+{
+  let key = match from_cbor(key)? {
+    Value::Map(key) => key,
+    _ => return Err(JoseError::Malformed),
+  };
+  if lookup(&key, KEY_KTY)? != Some(&int(KTY_AKP)) {
+    return Err(JoseError::InvalidKey);
+  }
+  check_cose_alg(lookup(&key, KEY_ALG)?)?;
+  let field = |label| match lookup(&key, label)? {
+    Some(Value::Bytes(value)) => Ok(Some(value)),
+    Some(_) => Err(JoseError::Malformed),
+    None => Ok(None),
+  };
+  let pk = field(KEY_PUB)?.ok_or(JoseError::InvalidKey)?;
+  public_key(pk)?;
+  let seed = field(KEY_PRIV)?.map(|s| seed(s)).transpose()?;
+  Ok((pk.clone(), seed))
+}
+
+fn check_cose_alg(alg: Option<&Value>) -> Result<(), JoseError>
+{
+  match alg {
+    Some(Value::Integer(alg)) => {
+      let alg = i64::try_from(*alg).ok().and_then(Algorithm::from_cose_alg);
+      match alg {
+        Some(ALGORITHM) => Ok(()),
+        _ => Err(JoseError::UnsupportedAlgorithm),
+      }
+    }
+    Some(Value::Text(_)) => Err(JoseError::UnsupportedAlgorithm),
+    Some(_) => Err(JoseError::Malformed),
+    None => Err(JoseError::Header),
+  }
+}
+
+/// Sig_structure of a COSE_Sign1 without external data
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8>
+{
+  to_cbor(&Value::Array(vec![
+    Value::Text("Signature1".into()),
+    Value::Bytes(protected.to_vec()),
+    Value::Bytes(vec![]),
+    Value::Bytes(payload.to_vec()),
+  ]))
+}
+
+/// The value of `label` in a COSE map; duplicate labels are an error
+fn lookup(
+  map: &[(Value, Value)],
+  label: i64,
+) -> Result<Option<&Value>, JoseError>
+{
+  let mut values = map.iter().filter(|(k, _)| k == &int(label));
+  match (values.next(), values.next()) {
+    (value, None) => Ok(value.map(|(_, v)| v)),
+    _ => Err(JoseError::Header),
+  }
+}
+
+fn int(value: i64) -> Value
+{
+  Value::Integer(value.into())
+}
+
+fn to_cbor(value: &Value) -> Vec<u8>
+{
+  let mut out = vec![];
+  ciborium::ser::into_writer(value, &mut out).expect("writing to a Vec");
+  out
+}
+
+/// Decodes exactly one CBOR item.
+fn from_cbor(mut bytes: &[u8]) -> Result<Value, JoseError>
+{
+  let value =
+    ciborium::de::from_reader(&mut bytes).map_err(|_| JoseError::Malformed)?;
+  match bytes.is_empty() {
+    true => Ok(value),
+    false => Err(JoseError::Malformed),
+  }
+}
+
+#[cfg(all(test, feature = "offchain"))]
+mod tests
+{
+  use super::*;
+
+  const SEED: [u8; SEEDBYTES] = [0x42; SEEDBYTES];
+
+  /// Signs a compact JWS with an arbitrary protected header.
+  fn jws_with_header(keypair: &Keypair, header: Json, payload: &[u8])
+    -> String
+  {
+    let input = format!(
+      "{}.{}",
+      base64url(header.to_string().as_bytes()),
+      base64url(payload)
+    );
+    let sig = keypair.sign(input.as_bytes());
+    format!("{}.{}", input, base64url(&sig))
+  }
+
+  /// Signs an untagged COSE_Sign1 with arbitrary header maps.
+  fn cose_with_headers(
+    keypair: &Keypair,
+    protected: Vec<(Value, Value)>,
+    unprotected: Vec<(Value, Value)>,
+  ) -> Vec<u8>
+  {
+    let protected = to_cbor(&Value::Map(protected));
+    let sig = keypair.sign(&sig_structure(&protected, b"payload"));
+    to_cbor(&Value::Array(vec![
+      Value::Bytes(protected),
+      Value::Map(unprotected),
+      Value::Bytes(b"payload".to_vec()),
+      Value::Bytes(sig.to_vec()),
+    ]))
+  }
+
+  #[test]
+  fn identifiers()
+  {
+    for alg in Algorithm::ALL {
+      assert_eq!(Algorithm::from_jose_name(alg.jose_name()), Some(alg));
+      assert_eq!(Algorithm::from_cose_alg(alg.cose_alg()), Some(alg));
+      // private use values are below -65536
+      assert!(
+        matches!(
+          alg,
+          Algorithm::MlDsa44 | Algorithm::MlDsa65 | Algorithm::MlDsa87
+        ) || alg.cose_alg() < -65536
+      );
+    }
+  }
+
+  #[test]
+  fn jwk()
+  {
+    let keypair = Keypair::from_seed(&SEED);
+    let public = public_jwk(keypair.public()).unwrap();
+    assert_eq!(public_key_from_jwk(&public).unwrap(), keypair.public());
+    assert_eq!(keypair_from_jwk(&public), Err(JoseError::InvalidKey));
+
+    let private = private_jwk(&SEED);
+    assert_eq!(keypair_from_jwk(&private), Ok(keypair.clone()));
+    let parsed: Json = serde_json::from_str(&private).unwrap();
+    assert_eq!(parsed["kty"], "AKP");
+    assert_eq!(parsed["alg"], ALGORITHM.jose_name());
+    assert_eq!(parsed["priv"], base64url(&SEED));
+
+    let mut other = parsed.clone();
+    other["priv"] = base64url(&[0x43; SEEDBYTES]).into();
+    assert_eq!(
+      keypair_from_jwk(&other.to_string()),
+      Err(JoseError::InvalidKey)
+    );
+    let mut other = parsed.clone();
+    other["alg"] = "ML-DSA-65".into();
+    assert_eq!(
+      public_key_from_jwk(&other.to_string()),
+      Err(JoseError::UnsupportedAlgorithm)
+    );
+    let mut other = parsed.clone();
+    other["kty"] = "OKP".into();
+    assert_eq!(
+      public_key_from_jwk(&other.to_string()),
+      Err(JoseError::InvalidKey)
+    );
+    let mut other = parsed;
+    other["pub"] = "AAAA".into();
+    assert_eq!(
+      public_key_from_jwk(&other.to_string()),
+      Err(JoseError::InvalidKey)
+    );
+  }
+
+  #[test]
+  fn cose_key()
+  {
+    let keypair = Keypair::from_seed(&SEED);
+    let public = public_cose_key(keypair.public()).unwrap();
+    assert_eq!(public_key_from_cose_key(&public).unwrap(), keypair.public());
+    assert_eq!(keypair_from_cose_key(&public), Err(JoseError::InvalidKey));
+    let private = private_cose_key(&SEED);
+    assert_eq!(keypair_from_cose_key(&private), Ok(keypair.clone()));
+
+    let mut trailing = private.clone();
+    trailing.push(0);
+    assert_eq!(keypair_from_cose_key(&trailing), Err(JoseError::Malformed));
+
+    let ml_dsa = to_cbor(&Value::Map(vec![
+      (int(KEY_KTY), int(KTY_AKP)),
+      (int(KEY_ALG), int(Algorithm::MlDsa65.cose_alg())),
+      (int(KEY_PUB), Value::Bytes(keypair.public().to_vec())),
+    ]));
+    assert_eq!(
+      public_key_from_cose_key(&ml_dsa),
+      Err(JoseError::UnsupportedAlgorithm)
+    );
+  }
+
+  #[test]
+  fn jws()
+  {
+    let keypair = Keypair::from_seed(&SEED);
+    let pk = keypair.public();
+    let jws = jws_sign(&keypair, b"{\"sub\":\"service\"}");
+    assert_eq!(jws_verify(&jws, pk).unwrap(), b"{\"sub\":\"service\"}");
+
+    let other = Keypair::from_seed(&[0x43; SEEDBYTES]);
+    assert_eq!(
+      jws_verify(&jws, other.public()),
+      Err(JoseError::Signature(SignError::Verify))
+    );
+    let parts: Vec<&str> = jws.split('.').collect();
+    let swapped = format!("{}.{}.{}", parts[0], base64url(b"{}"), parts[2]);
+    assert_eq!(
+      jws_verify(&swapped, pk),
+      Err(JoseError::Signature(SignError::Verify))
+    );
+    assert_eq!(
+      jws_verify(&parts[..2].join("."), pk),
+      Err(JoseError::Malformed)
+    );
+    assert_eq!(
+      jws_verify(&format!("{}=", jws), pk),
+      Err(JoseError::Malformed)
+    );
+
+    let alg = ALGORITHM.jose_name();
+    let cases = [
+      (
+        json!({ "alg": "ML-DSA-65" }),
+        JoseError::UnsupportedAlgorithm,
+      ),
+      (json!({ "alg": "none" }), JoseError::UnsupportedAlgorithm),
+      (json!({ "typ": "JWT" }), JoseError::Header),
+      (json!({ "alg": alg, "crit": ["exp"] }), JoseError::Header),
+      (json!({ "alg": alg, "b64": false }), JoseError::Header),
+      (json!([alg]), JoseError::Malformed),
+    ];
+    for (header, err) in cases {
+      let jws = jws_with_header(&keypair, header.clone(), b"payload");
+      assert_eq!(jws_verify(&jws, pk), Err(err), "{}", header);
+    }
+    let typed = json!({ "alg": alg, "typ": "JWT", "kid": "k1" });
+    let jws = jws_with_header(&keypair, typed, b"payload");
+    assert_eq!(jws_verify(&jws, pk).unwrap(), b"payload");
+  }
+
+  #[test]
+  fn cose_sign1()
+  {
+    let keypair = Keypair::from_seed(&SEED);
+    let pk = keypair.public();
+    let msg = super::cose_sign1(&keypair, b"payload");
+    assert_eq!(msg[0], 0xD2);
+    assert_eq!(cose_sign1_verify(&msg, pk).unwrap(), b"payload");
+
+    let mut tampered = msg.clone();
+    // last payload byte, before the signature and its 3-byte header
+    let at = tampered.len() - SIGNBYTES - 4;
+    tampered[at] ^= 1;
+    assert_eq!(
+      cose_sign1_verify(&tampered, pk),
+      Err(JoseError::Signature(SignError::Verify))
+    );
+    let mut trailing = msg;
+    trailing.push(0xF6);
+    assert_eq!(cose_sign1_verify(&trailing, pk), Err(JoseError::Malformed));
+
+    let alg = || (int(HEADER_ALG), int(ALGORITHM.cose_alg()));
+    let ml_dsa = (int(HEADER_ALG), int(Algorithm::MlDsa65.cose_alg()));
+    let kid = || (int(4), Value::Bytes(b"k1".to_vec()));
+    let crit = (int(HEADER_CRIT), Value::Array(vec![int(4)]));
+    let cases = [
+      (vec![ml_dsa], vec![], JoseError::UnsupportedAlgorithm),
+      (vec![], vec![alg()], JoseError::Header),
+      (vec![alg(), alg()], vec![], JoseError::Header),
+      (vec![alg(), crit], vec![], JoseError::Header),
+      (vec![alg(), kid()], vec![kid()], JoseError::Header),
+    ];
+    for (protected, unprotected, err) in cases {
+      let msg = cose_with_headers(&keypair, protected, unprotected);
+      assert_eq!(cose_sign1_verify(&msg, pk), Err(err));
+    }
+    let msg = cose_with_headers(&keypair, vec![alg()], vec![kid()]);
+    assert_eq!(cose_sign1_verify(&msg, pk).unwrap(), b"payload");
+  }
+}
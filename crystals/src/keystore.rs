@@ -0,0 +1,528 @@
+//! Password-encrypted keystore files.
+//!
+//! A keystore is a JSON envelope holding the secret key encrypted with
+//! XChaCha20-Poly1305 under a key derived from the password with
+//! Argon2id. The public key, mode, label and creation time are stored in
+//! the clear, so [`inspect`] can read them without the password, and are
+//! authenticated as associated data:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "mode": "dilithium3",
+//!   "public_key": "<hex>",
+//!   "label": "release signing",
+//!   "created_at": 1760000000,
+//!   "kdf": { "name": "argon2id", "memory_kib": 65536, "iterations": 3,
+//!            "parallelism": 4, "salt": "<hex>" },
+//!   "cipher": { "name": "xchacha20poly1305", "nonce": "<hex>" },
+//!   "ciphertext": "<hex>"
+//! }
+//! ```
+//!
+//! Raw secret key files, as written from [`Keypair::expose_secret`], can
+//! be converted with [`migrate`].
+
+use std::{
+  fs,
+  io::Write,
+  path::Path,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use argon2::{Argon2, Params, Version};
+use chacha20poly1305::{
+  aead::{Aead, KeyInit, Payload},
+  XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{
+  pkcs8::{PrivateKey, ALGORITHM},
+  randombytes::*,
+  serde::{hex, unhex},
+  Keypair, KeystoreError, PUBLICKEYBYTES, SECRETKEYBYTES,
+};
+
+const VERSION: u32 = 1;
+const KDF: &str = "argon2id";
+const CIPHER: &str = "xchacha20poly1305";
+const SALTBYTES: usize = 16;
+const NONCEBYTES: usize = 24;
+const KEYBYTES: usize = 32;
+
+/// Upper bounds on the KDF cost accepted from a file, so a crafted
+/// keystore cannot make `decrypt` allocate or spin without limit
+const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 64;
+
+/// Argon2id cost parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams
+{
+  pub memory_kib: u32,
+  pub iterations: u32,
+  pub parallelism: u32,
+}
+
+/// The second recommended option of RFC 9106: 64 MiB, 3 passes, 4 lanes
+impl Default for KdfParams
+{
+  fn default() -> Self
+  {
+    KdfParams {
+      memory_kib: 64 * 1024,
+      iterations: 3,
+      parallelism: 4,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Metadata
+{
+  pub label: String,
+  /// Seconds since the Unix epoch
+  pub created_at: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope
+{
+  version: u32,
+  mode: String,
+  public_key: String,
+  label: String,
+  created_at: u64,
+  kdf: Kdf,
+  cipher: Cipher,
+  ciphertext: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Kdf
+{
+  name: String,
+  memory_kib: u32,
+  iterations: u32,
+  parallelism: u32,
+  salt: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Cipher
+{
+  name: String,
+  nonce: String,
+}
+
+/// Encrypts a keypair with the default KDF cost, labelled `label` and
+/// timestamped now.
+pub fn encrypt(
+  keypair: &Keypair,
+  password: &[u8],
+  label: &str,
+) -> Result<String, KeystoreError>
+{
+  let created_at = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs());
+  let metadata = Metadata {
+    label: label.to_string(),
+    created_at,
+  };
+  encrypt_with(keypair, password, &metadata, &KdfParams::default())
+}
+
+pub fn encrypt_with(
+  keypair: &Keypair,
+  password: &[u8],
+  metadata: &Metadata,
+  params: &KdfParams,
+) -> Result<String, KeystoreError>
+{
+  let (mut salt, mut nonce) = ([0u8; SALTBYTES], [0u8; NONCEBYTES]);
+  randombytes(&mut salt, SALTBYTES);
+  randombytes(&mut nonce, NONCEBYTES);
+
+  let mut envelope = Envelope {
+    version: VERSION,
+    mode: ALGORITHM.name().to_string(),
+    public_key: hex(keypair.public()),
+    label: metadata.label.clone(),
+    created_at: metadata.created_at,
+    kdf: Kdf {
+      name: KDF.to_string(),
+      memory_kib: params.memory_kib,
+      iterations: params.iterations,
+      parallelism: params.parallelism,
+      salt: hex(&salt),
+    },
+    cipher: Cipher {
+      name: CIPHER.to_string(),
+      nonce: hex(&nonce),
+    },
+    ciphertext: String::new(),
+  };
+  let key = derive_key(password, params, &salt)?;
+  let payload = Payload {
+    msg: keypair.expose_secret(),
+    aad: &associated_data(&envelope),
+  };
+  let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+    .encrypt(XNonce::from_slice(&nonce), payload)
+    .expect("in-memory encryption");
+  envelope.ciphertext = hex(&ciphertext);
+  Ok(serde_json::to_string_pretty(&envelope).expect("serializing a struct"))
+}
+
+/// Decrypts a keystore. The secret key must be consistent and match the
+/// public key stored in the envelope.
+pub fn decrypt(
+  keystore: &str,
+  password: &[u8],
+) -> Result<(Keypair, Metadata), KeystoreError>
+{
+  let envelope = parse(keystore)?;
+  let kdf = &envelope.kdf;
+  let params = KdfParams {
+    memory_kib: kdf.memory_kib,
+    iterations: kdf.iterations,
+    parallelism: kdf.parallelism,
+  };
+  if params.memory_kib > MAX_MEMORY_KIB
+    || params.iterations > MAX_ITERATIONS
+    || params.parallelism > MAX_PARALLELISM
+  {
+    return Err(KeystoreError::Kdf);
+  }
+  let salt = bytes(&kdf.salt)?;
+  let nonce: [u8; NONCEBYTES] = bytes(&envelope.cipher.nonce)?
+    .try_into()
+    .map_err(|_| KeystoreError::Malformed)?;
+
+  let key = derive_key(password, &params, &salt)?;
+  let ciphertext = bytes(&envelope.ciphertext)?;
+  let payload = Payload {
+    msg: &ciphertext,
+    aad: &associated_data(&envelope),
+  };
+  let secret = XChaCha20Poly1305::new(key.as_ref().into())
+    .decrypt(XNonce::from_slice(&nonce), payload)
+    .map_err(|_| KeystoreError::Decrypt)?;
+  let secret = Zeroizing::new(secret);
+
+  let keypair =
+    Keypair::from_private_key(&PrivateKey::Expanded(secret.to_vec()))
+      .map_err(|_| KeystoreError::InvalidKey)?;
+  if hex(keypair.public()) != envelope.public_key {
+    return Err(KeystoreError::InvalidKey);
+  }
+  Ok((keypair, metadata(&envelope)))
+}
+
+/// Public key and metadata of a keystore, without decrypting it.
+pub fn inspect(keystore: &str) -> Result<(Vec<u8>, Metadata), KeystoreError>
+{
+  let envelope = parse(keystore)?;
+  let pk = bytes(&envelope.public_key)?;
+  Ok((pk, metadata(&envelope)))
+}
+
+/// Encrypts a keypair into a new file, readable only by its owner on
+/// Unix. Existing files are not overwritten.
+pub fn save(
+  path: impl AsRef<Path>,
+  keypair: &Keypair,
+  password: &[u8],
+  label: &str,
+) -> Result<(), KeystoreError>
+{
+  let keystore = encrypt(keypair, password, label)?;
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+  let mut file = options.open(path)?;
+  file.write_all(keystore.as_bytes())?;
+  Ok(file.sync_all()?)
+}
+
+pub fn load(
+  path: impl AsRef<Path>,
+  password: &[u8],
+) -> Result<(Keypair, Metadata), KeystoreError>
+{
+  decrypt(&fs::read_to_string(path)?, password)
+}
+
+/// Rebuilds a keypair from a raw secret key, either the bytes of
+/// [`Keypair::expose_secret`] or the same in hex. The public key is
+/// recomputed from the secret key.
+pub fn from_raw(raw: &[u8]) -> Result<Keypair, KeystoreError>
+{
+  let secret = match raw.len() == SECRETKEYBYTES {
+    true => Zeroizing::new(raw.to_vec()),
+    false => core::str::from_utf8(raw)
+      .ok()
+      .and_then(|text| unhex(text.trim()))
+      .map(Zeroizing::new)
+      .ok_or(KeystoreError::InvalidKey)?,
+  };
+  if secret.len() != SECRETKEYBYTES {
+    return Err(KeystoreError::InvalidKey);
+  }
+  Keypair::from_private_key(&PrivateKey::Expanded(secret.to_vec()))
+    .map_err(|_| KeystoreError::InvalidKey)
+}
+
+/// Converts a raw secret key file into a keystore at `keystore`. The raw
+/// file is left in place; remove it once the keystore has been checked
+/// with [`load`].
+pub fn migrate(
+  raw: impl AsRef<Path>,
+  keystore: impl AsRef<Path>,
+  password: &[u8],
+  label: &str,
+) -> Result<Keypair, KeystoreError>
+{
+  let keypair = from_raw(&Zeroizing::new(fs::read(raw)?))?;
+  save(keystore, &keypair, password, label)?;
+  Ok(keypair)
+}
+
+fn parse(keystore: &str) -> Result<Envelope, KeystoreError>
+{
+  let envelope: Envelope =
+    serde_json::from_str(keystore).map_err(|_| KeystoreError::Malformed)?;
+  if envelope.version != VERSION
+    || envelope.kdf.name != KDF
+    || envelope.cipher.name != CIPHER
+  {
+    return Err(KeystoreError::Unsupported);
+  }
+  if envelope.mode != ALGORITHM.name() {
+    return Err(KeystoreError::WrongMode);
+  }
+  if bytes(&envelope.public_key)?.len() != PUBLICKEYBYTES {
+    return Err(KeystoreError::Malformed);
+  }
+  Ok(envelope)
+}
+
+fn metadata(envelope: &Envelope) -> Metadata
+{
+  Metadata {
+    label: envelope.label.clone(),
+    created_at: envelope.created_at,
+  }
+}
+
+/// Every field but the ciphertext, as serialized JSON
+fn associated_data(envelope: &Envelope) -> Vec<u8>
+{
+  let header = Envelope {
+    ciphertext: String::new(),
+    ..envelope.clone()
+  };
+  serde_json::to_vec(&header).expect("serializing a struct")
+}
+
+fn derive_key(
+  password: &[u8],
+  params: &KdfParams,
+  salt: &[u8],
+) -> Result<Zeroizing<[u8; KEYBYTES]>, KeystoreError>
+{
+  let params = Params::new(
+    params.memory_kib,
+    params.iterations,
+    params.parallelism,
+    Some(KEYBYTES),
+  )
+  .map_err(|_| KeystoreError::Kdf)?;
+  let argon2 = Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params);
+  let mut key = Zeroizing::new([0u8; KEYBYTES]);
+  argon2
+    .hash_password_into(password, salt, key.as_mut())
+    .map_err(|_| KeystoreError::Kdf)?;
+  Ok(key)
+}
+
+fn bytes(text: &str) -> Result<Vec<u8>, KeystoreError>
+{
+  unhex(text).ok_or(KeystoreError::Malformed)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use serde_json::Value;
+
+  use super::*;
+  use crate::SEEDBYTES;
+
+  /// Cheap enough for tests
+  const PARAMS: KdfParams = KdfParams {
+    memory_kib: 64,
+    iterations: 1,
+    parallelism: 1,
+  };
+
+  fn keystore(keypair: &Keypair) -> String
+  {
+    let metadata = Metadata {
+      label: "release signing".into(),
+      created_at: 1_760_000_000,
+    };
+    encrypt_with(keypair, b"correct horse", &metadata, &PARAMS).unwrap()
+  }
+
+  type Edit = fn(&mut Value);
+
+  /// Applies `edit` to the parsed envelope and serializes it again.
+  fn edited(keystore: &str, edit: impl FnOnce(&mut Value)) -> String
+  {
+    let mut value: Value = serde_json::from_str(keystore).unwrap();
+    edit(&mut value);
+    value.to_string()
+  }
+
+  fn temp_dir(name: &str) -> std::path::PathBuf
+  {
+    let dir = std::env::temp_dir().join(format!(
+      "crystals-keystore-{}-{}",
+      name,
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn round_trip()
+  {
+    let keypair = Keypair::from_seed(&[5; SEEDBYTES]);
+    let keystore = keystore(&keypair);
+    let (decrypted, metadata) = decrypt(&keystore, b"correct horse").unwrap();
+    assert_eq!(decrypted, keypair);
+    assert_eq!(metadata.label, "release signing");
+    assert_eq!(metadata.created_at, 1_760_000_000);
+
+    let (pk, _) = inspect(&keystore).unwrap();
+    assert_eq!(pk, keypair.public());
+    let value: Value = serde_json::from_str(&keystore).unwrap();
+    assert_eq!(value["mode"], ALGORITHM.name());
+    assert_eq!(value["kdf"]["name"], "argon2id");
+    // secret key plus the Poly1305 tag
+    let ciphertext = value["ciphertext"].as_str().unwrap();
+    assert_eq!(ciphertext.len(), 2 * (SECRETKEYBYTES + 16));
+    assert!(!ciphertext.contains(&hex(&keypair.expose_secret()[..32])));
+  }
+
+  #[test]
+  fn rejects_tampering()
+  {
+    let keypair = Keypair::from_seed(&[5; SEEDBYTES]);
+    let keystore = keystore(&keypair);
+    assert_eq!(
+      decrypt(&keystore, b"wrong horse").map(|_| ()),
+      Err(KeystoreError::Decrypt)
+    );
+
+    let cases: [(Edit, KeystoreError); 7] = [
+      (|v| v["label"] = "other".into(), KeystoreError::Decrypt),
+      (|v| v["created_at"] = 0.into(), KeystoreError::Decrypt),
+      (|v| v["version"] = 2.into(), KeystoreError::Unsupported),
+      (
+        |v| v["kdf"]["name"] = "scrypt".into(),
+        KeystoreError::Unsupported,
+      ),
+      (
+        |v| v["mode"] = "dilithium9".into(),
+        KeystoreError::WrongMode,
+      ),
+      (|v| v["extra"] = true.into(), KeystoreError::Malformed),
+      (
+        |v| v["kdf"]["memory_kib"] = (MAX_MEMORY_KIB + 1).into(),
+        KeystoreError::Kdf,
+      ),
+    ];
+    for (edit, err) in cases {
+      let keystore = edited(&keystore, edit);
+      assert_eq!(decrypt(&keystore, b"correct horse").map(|_| ()), Err(err));
+    }
+
+    // a public key that does not belong to the secret key
+    let other = Keypair::from_seed(&[6; SEEDBYTES]);
+    let metadata = Metadata {
+      label: String::new(),
+      created_at: 0,
+    };
+    let mismatched = edited(
+      &encrypt_with(&keypair, b"pw", &metadata, &PARAMS).unwrap(),
+      |v| v["public_key"] = hex(other.public()).into(),
+    );
+    // the public key is authenticated, so this fails before the check
+    assert_eq!(
+      decrypt(&mismatched, b"pw").map(|_| ()),
+      Err(KeystoreError::Decrypt)
+    );
+  }
+
+  #[test]
+  fn raw_keys()
+  {
+    let keypair = Keypair::from_seed(&[7; SEEDBYTES]);
+    let secret = keypair.expose_secret();
+    assert_eq!(from_raw(secret).unwrap(), keypair);
+    let text = format!("{}\n", hex(secret));
+    assert_eq!(from_raw(text.as_bytes()).unwrap(), keypair);
+
+    assert_eq!(
+      from_raw(&secret[1..]).map(|_| ()),
+      Err(KeystoreError::InvalidKey)
+    );
+    let mut corrupted = secret.to_vec();
+    corrupted[SECRETKEYBYTES - 1] ^= 1;
+    assert_eq!(
+      from_raw(&corrupted).map(|_| ()),
+      Err(KeystoreError::InvalidKey)
+    );
+  }
+
+  #[test]
+  fn files()
+  {
+    let dir = temp_dir("files");
+    let keypair = Keypair::from_seed(&[8; SEEDBYTES]);
+    let raw = dir.join("raw.key");
+    fs::write(&raw, keypair.expose_secret()).unwrap();
+
+    // migrate and save use the default, slower KDF cost
+    let path = dir.join("key.json");
+    assert_eq!(migrate(&raw, &path, b"pw", "ops").unwrap(), keypair);
+    let (loaded, metadata) = load(&path, b"pw").unwrap();
+    assert_eq!((loaded, metadata.label.as_str()), (keypair.clone(), "ops"));
+    assert!(metadata.created_at > 1_700_000_000);
+    assert!(raw.exists());
+
+    assert_eq!(
+      save(&path, &keypair, b"pw", "ops"),
+      Err(KeystoreError::Io(std::io::ErrorKind::AlreadyExists))
+    );
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      let mode = fs::metadata(&path).unwrap().permissions().mode();
+      assert_eq!(mode & 0o777, 0o600);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,15 +1,49 @@
 mod api;
+#[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+mod avx2;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "composite")]
+pub mod composite;
+pub mod diagnostics;
 mod fips202;
+mod fips202x4;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+#[cfg(feature = "hd")]
+pub mod hd;
+#[cfg(feature = "hybrid")]
+pub mod hybrid;
+#[cfg(feature = "jose")]
+pub mod jose;
+#[cfg(feature = "keystore")]
+pub mod keystore;
+#[cfg(feature = "hybrid")]
+pub mod mlkem;
 mod ntt;
 mod packing;
 mod params;
+pub mod pkcs8;
 #[cfg(feature = "offchain")]
 mod randombytes;
 mod poly;
 mod polyvec;
 mod reduce;
 mod rounding;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "offchain")]
+pub mod selftest;
+pub mod sha3;
 mod sign;
+pub mod slhdsa;
+#[cfg(feature = "ssh")]
+pub mod ssh;
+pub mod stateful;
 mod symmetric;
 pub use params::*;
 
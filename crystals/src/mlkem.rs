@@ -0,0 +1,314 @@
+//! ML-KEM-768 key encapsulation (FIPS 203).
+//!
+//! The module mirrors the layout of the signature code: byte-oriented
+//! `crypto_kem_*` functions over caller-provided buffers, with the
+//! deterministic `_derand` variants available on-chain and the
+//! randomised ones behind the `offchain` feature.
+
+mod indcpa;
+mod ntt;
+mod params;
+mod poly;
+mod reduce;
+
+pub use self::params::{
+  CIPHERTEXTBYTES, PUBLICKEYBYTES, SECRETKEYBYTES, SSBYTES, SYMBYTES,
+};
+
+use self::{indcpa::*, params::*, poly::*};
+use crate::{fips202::*, KemError};
+
+#[cfg(feature = "offchain")]
+use crate::randombytes::*;
+
+/// Encapsulation key check of FIPS 203, section 7.2. Every
+/// coefficient of the encoded vector must already be reduced mod Q.
+fn check_pk(pk: &[u8]) -> Result<(), KemError>
+{
+  if pk.len() != PUBLICKEYBYTES {
+    return Err(KemError::InvalidPublicKey);
+  }
+  let mut pkpv = Polyvec::default();
+  let mut reencoded = [0u8; POLYVECBYTES];
+  polyvec_frombytes(&mut pkpv, pk);
+  polyvec_reduce(&mut pkpv);
+  polyvec_tobytes(&mut reencoded, &pkpv);
+  if reencoded[..] != pk[..POLYVECBYTES] {
+    return Err(KemError::InvalidPublicKey);
+  }
+  Ok(())
+}
+
+/// Decapsulation key check of FIPS 203, section 7.3. The embedded
+/// hash must match the embedded encapsulation key.
+fn check_sk(sk: &[u8]) -> Result<(), KemError>
+{
+  if sk.len() != SECRETKEYBYTES {
+    return Err(KemError::InvalidSecretKey);
+  }
+  let mut h = [0u8; SYMBYTES];
+  let pk = &sk[INDCPA_SECRETKEYBYTES..INDCPA_SECRETKEYBYTES + PUBLICKEYBYTES];
+  sha3_256(&mut h, pk, PUBLICKEYBYTES);
+  let stored = &sk[SECRETKEYBYTES - 2 * SYMBYTES..SECRETKEYBYTES - SYMBYTES];
+  if h[..] != stored[..] {
+    return Err(KemError::InvalidSecretKey);
+  }
+  Ok(())
+}
+
+/// Compare two arrays for equality in constant time.
+///
+/// Returns 0 if the byte arrays are equal, 1 otherwise.
+fn verify(a: &[u8], b: &[u8]) -> u8
+{
+  let mut r = 0u8;
+  for i in 0..a.len() {
+    r |= a[i] ^ b[i];
+  }
+  ((0u64.wrapping_sub(r as u64)) >> 63) as u8
+}
+
+/// Copy `x` into `r` if `b` is 1, leave `r` untouched if `b` is 0,
+/// without branching on `b`.
+fn cmov(r: &mut [u8], x: &[u8], b: u8)
+{
+  let mask = b.wrapping_neg();
+  for i in 0..r.len() {
+    r[i] ^= mask & (r[i] ^ x[i]);
+  }
+}
+
+/// Generates an ML-KEM-768 keypair from 64 bytes of randomness (d || z).
+pub fn crypto_kem_keypair_derand(
+  pk: &mut [u8],
+  sk: &mut [u8],
+  coins: &[u8],
+) -> Result<(), KemError>
+{
+  if pk.len() < PUBLICKEYBYTES
+    || sk.len() < SECRETKEYBYTES
+    || coins.len() != 2 * SYMBYTES
+  {
+    return Err(KemError::InvalidLength);
+  }
+  indcpa_keypair_derand(pk, sk, &coins[..SYMBYTES]);
+  sk[INDCPA_SECRETKEYBYTES..INDCPA_SECRETKEYBYTES + PUBLICKEYBYTES]
+    .copy_from_slice(&pk[..PUBLICKEYBYTES]);
+  sha3_256(
+    &mut sk[SECRETKEYBYTES - 2 * SYMBYTES..],
+    &pk[..PUBLICKEYBYTES],
+    PUBLICKEYBYTES,
+  );
+  sk[SECRETKEYBYTES - SYMBYTES..SECRETKEYBYTES]
+    .copy_from_slice(&coins[SYMBYTES..2 * SYMBYTES]);
+  Ok(())
+}
+
+/// Generates an ML-KEM-768 keypair using system randomness.
+#[cfg(feature = "offchain")]
+pub fn crypto_kem_keypair(pk: &mut [u8], sk: &mut [u8])
+  -> Result<(), KemError>
+{
+  let mut coins = [0u8; 2 * SYMBYTES];
+  randombytes(&mut coins, 2 * SYMBYTES);
+  crypto_kem_keypair_derand(pk, sk, &coins)
+}
+
+/// Generates a ciphertext and shared secret for the given public key,
+/// using 32 bytes of caller-provided randomness.
+pub fn crypto_kem_enc_derand(
+  ct: &mut [u8],
+  ss: &mut [u8],
+  pk: &[u8],
+  coins: &[u8],
+) -> Result<(), KemError>
+{
+  check_pk(pk)?;
+  if ct.len() < CIPHERTEXTBYTES || ss.len() < SSBYTES || coins.len() != SYMBYTES
+  {
+    return Err(KemError::InvalidLength);
+  }
+  let mut buf = [0u8; 2 * SYMBYTES];
+  let mut kr = [0u8; 2 * SYMBYTES];
+
+  buf[..SYMBYTES].copy_from_slice(&coins[..SYMBYTES]);
+  // Multitarget countermeasure for coins + contributory KEM
+  sha3_256(&mut buf[SYMBYTES..], pk, PUBLICKEYBYTES);
+  sha3_512(&mut kr, &buf, 2 * SYMBYTES);
+
+  // coins are in kr+SYMBYTES
+  indcpa_enc(ct, &buf[..SYMBYTES], pk, &kr[SYMBYTES..]);
+
+  ss[..SSBYTES].copy_from_slice(&kr[..SYMBYTES]);
+  Ok(())
+}
+
+/// Generates a ciphertext and shared secret for the given public key
+/// using system randomness.
+#[cfg(feature = "offchain")]
+pub fn crypto_kem_enc(
+  ct: &mut [u8],
+  ss: &mut [u8],
+  pk: &[u8],
+) -> Result<(), KemError>
+{
+  let mut coins = [0u8; SYMBYTES];
+  randombytes(&mut coins, SYMBYTES);
+  crypto_kem_enc_derand(ct, ss, pk, &coins)
+}
+
+/// Recovers the shared secret from a ciphertext. A ciphertext that
+/// fails re-encryption yields the implicit-rejection key
+/// SHAKE256(z || ct) rather than an error.
+pub fn crypto_kem_dec(
+  ss: &mut [u8],
+  ct: &[u8],
+  sk: &[u8],
+) -> Result<(), KemError>
+{
+  if ct.len() != CIPHERTEXTBYTES {
+    return Err(KemError::InvalidCiphertext);
+  }
+  check_sk(sk)?;
+  if ss.len() < SSBYTES {
+    return Err(KemError::InvalidLength);
+  }
+  let mut buf = [0u8; 2 * SYMBYTES];
+  let mut kr = [0u8; 2 * SYMBYTES];
+  let mut cmp = [0u8; CIPHERTEXTBYTES];
+  let pk = &sk[INDCPA_SECRETKEYBYTES..INDCPA_SECRETKEYBYTES + PUBLICKEYBYTES];
+
+  indcpa_dec(&mut buf, ct, sk);
+
+  // Multitarget countermeasure for coins + contributory KEM
+  buf[SYMBYTES..].copy_from_slice(
+    &sk[SECRETKEYBYTES - 2 * SYMBYTES..SECRETKEYBYTES - SYMBYTES],
+  );
+  sha3_512(&mut kr, &buf, 2 * SYMBYTES);
+
+  // coins are in kr+SYMBYTES
+  indcpa_enc(&mut cmp, &buf[..SYMBYTES], pk, &kr[SYMBYTES..]);
+
+  let fail = verify(ct, &cmp);
+
+  // Compute rejection key
+  let mut state = KeccakState::default();
+  shake256_absorb(&mut state, &sk[SECRETKEYBYTES - SYMBYTES..], SYMBYTES);
+  shake256_absorb(&mut state, ct, CIPHERTEXTBYTES);
+  shake256_finalize(&mut state);
+  shake256_squeeze(ss, SSBYTES, &mut state);
+
+  // Copy true key to return buffer if fail is false
+  cmov(&mut ss[..SSBYTES], &kr[..SYMBYTES], 1 - fail);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  fn keypair() -> (Vec<u8>, Vec<u8>)
+  {
+    let mut pk = vec![0u8; PUBLICKEYBYTES];
+    let mut sk = vec![0u8; SECRETKEYBYTES];
+    crypto_kem_keypair_derand(&mut pk, &mut sk, &[1u8; 2 * SYMBYTES]).unwrap();
+    (pk, sk)
+  }
+
+  #[test]
+  fn encaps_decaps()
+  {
+    let (pk, sk) = keypair();
+    let (mut ct, mut ss, mut ss2) =
+      ([0u8; CIPHERTEXTBYTES], [0u8; SSBYTES], [0u8; SSBYTES]);
+    crypto_kem_enc_derand(&mut ct, &mut ss, &pk, &[2u8; SYMBYTES]).unwrap();
+    crypto_kem_dec(&mut ss2, &ct, &sk).unwrap();
+    assert_eq!(ss, ss2);
+  }
+
+  #[test]
+  fn implicit_rejection()
+  {
+    let (pk, sk) = keypair();
+    let (mut ct, mut ss, mut ss2) =
+      ([0u8; CIPHERTEXTBYTES], [0u8; SSBYTES], [0u8; SSBYTES]);
+    crypto_kem_enc_derand(&mut ct, &mut ss, &pk, &[2u8; SYMBYTES]).unwrap();
+    ct[5] ^= 0x40;
+    crypto_kem_dec(&mut ss2, &ct, &sk).unwrap();
+
+    // A rejected ciphertext decapsulates to SHAKE256(z || ct)
+    let mut rejection = [0u8; SSBYTES];
+    let mut input = sk[SECRETKEYBYTES - SYMBYTES..].to_vec();
+    input.extend_from_slice(&ct);
+    shake256(&mut rejection, SSBYTES, &input, input.len());
+    assert_eq!(ss2, rejection);
+    assert_ne!(ss2, ss);
+  }
+
+  #[test]
+  fn input_checks()
+  {
+    let (mut pk, mut sk) = keypair();
+    let (mut ct, mut ss) = ([0u8; CIPHERTEXTBYTES], [0u8; SSBYTES]);
+
+    // First coefficient set to 4095 > Q - 1
+    let saved = (pk[0], pk[1]);
+    pk[0] = 0xFF;
+    pk[1] |= 0x0F;
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct, &mut ss, &pk, &[0u8; SYMBYTES]),
+      Err(KemError::InvalidPublicKey)
+    );
+    (pk[0], pk[1]) = saved;
+    crypto_kem_enc_derand(&mut ct, &mut ss, &pk, &[0u8; SYMBYTES]).unwrap();
+
+    sk[INDCPA_SECRETKEYBYTES] ^= 1;
+    assert_eq!(
+      crypto_kem_dec(&mut ss, &ct, &sk),
+      Err(KemError::InvalidSecretKey)
+    );
+    assert_eq!(
+      crypto_kem_dec(&mut ss, &ct[1..], &sk),
+      Err(KemError::InvalidCiphertext)
+    );
+  }
+
+  #[test]
+  fn buffer_lengths()
+  {
+    let (pk, sk) = keypair();
+    let (mut pk2, mut sk2) = (vec![0u8; PUBLICKEYBYTES], sk.clone());
+    let (mut ct, mut ss) = ([0u8; CIPHERTEXTBYTES], [0u8; SSBYTES]);
+    let coins = [1u8; 2 * SYMBYTES];
+    assert_eq!(
+      crypto_kem_keypair_derand(&mut pk2, &mut sk2, &coins[1..]),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_keypair_derand(&mut pk2[1..], &mut sk2, &coins),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_keypair_derand(&mut pk2, &mut sk2[1..], &coins),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct, &mut ss, &pk, &coins[..SYMBYTES - 1]),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct[1..], &mut ss, &pk, &coins[..SYMBYTES]),
+      Err(KemError::InvalidLength)
+    );
+    assert_eq!(
+      crypto_kem_enc_derand(&mut ct, &mut ss[1..], &pk, &coins[..SYMBYTES]),
+      Err(KemError::InvalidLength)
+    );
+    crypto_kem_enc_derand(&mut ct, &mut ss, &pk, &coins[..SYMBYTES]).unwrap();
+    assert_eq!(
+      crypto_kem_dec(&mut ss[1..], &ct, &sk),
+      Err(KemError::InvalidLength)
+    );
+  }
+}
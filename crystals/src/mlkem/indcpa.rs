@@ -0,0 +1,214 @@
+#![allow(clippy::needless_range_loop)]
+
+use super::{params::*, poly::*};
+use crate::fips202::*;
+
+const GEN_MATRIX_NBLOCKS: usize =
+  (12 * N / 8 * (1 << 12) / Q + SHAKE128_RATE) / SHAKE128_RATE;
+
+/// Serialize the public key as concatenation of the
+/// serialized vector of polynomials pk and the
+/// public seed used to generate the matrix A.
+fn pack_pk(r: &mut [u8], pk: &Polyvec, seed: &[u8])
+{
+  polyvec_tobytes(r, pk);
+  r[POLYVECBYTES..INDCPA_PUBLICKEYBYTES].copy_from_slice(&seed[..SYMBYTES]);
+}
+
+/// De-serialize public key from a byte array;
+/// approximate inverse of pack_pk.
+fn unpack_pk(pk: &mut Polyvec, seed: &mut [u8], packedpk: &[u8])
+{
+  polyvec_frombytes(pk, packedpk);
+  seed[..SYMBYTES]
+    .copy_from_slice(&packedpk[POLYVECBYTES..INDCPA_PUBLICKEYBYTES]);
+}
+
+/// Serialize the ciphertext as concatenation of the
+/// compressed and serialized vector of polynomials b
+/// and the compressed and serialized polynomial v.
+fn pack_ciphertext(r: &mut [u8], b: &Polyvec, v: &Poly)
+{
+  polyvec_compress(r, b);
+  poly_compress(&mut r[POLYVECCOMPRESSEDBYTES..], v);
+}
+
+/// De-serialize and decompress ciphertext from a byte array;
+/// approximate inverse of pack_ciphertext.
+fn unpack_ciphertext(b: &mut Polyvec, v: &mut Poly, c: &[u8])
+{
+  polyvec_decompress(b, c);
+  poly_decompress(v, &c[POLYVECCOMPRESSEDBYTES..]);
+}
+
+/// Run rejection sampling on uniform random bytes to generate
+/// uniform random integers mod q.
+///
+/// Returns number of sampled 16-bit integers (at most len).
+fn rej_uniform(r: &mut [i16], len: usize, buf: &[u8], buflen: usize) -> usize
+{
+  let (mut ctr, mut pos) = (0usize, 0usize);
+  while ctr < len && pos + 3 <= buflen {
+    let val0 = (buf[pos] as u16 | (buf[pos + 1] as u16) << 8) & 0xFFF;
+    let val1 = (buf[pos + 1] as u16 >> 4 | (buf[pos + 2] as u16) << 4) & 0xFFF;
+    pos += 3;
+
+    if val0 < Q as u16 {
+      r[ctr] = val0 as i16;
+      ctr += 1;
+    }
+    if ctr < len && val1 < Q as u16 {
+      r[ctr] = val1 as i16;
+      ctr += 1;
+    }
+  }
+  ctr
+}
+
+/// Deterministically generate matrix A (or the transpose of A)
+/// from a seed. Entries of the matrix are polynomials that look
+/// uniformly random. Performs rejection sampling on output of
+/// SHAKE128(seed|j|i).
+pub fn gen_matrix(a: &mut [Polyvec], seed: &[u8], transposed: bool)
+{
+  let mut buf = [0u8; GEN_MATRIX_NBLOCKS * SHAKE128_RATE];
+  let mut extseed = [0u8; SYMBYTES + 2];
+  extseed[..SYMBYTES].copy_from_slice(&seed[..SYMBYTES]);
+
+  for i in 0..K {
+    for j in 0..K {
+      if transposed {
+        extseed[SYMBYTES] = i as u8;
+        extseed[SYMBYTES + 1] = j as u8;
+      } else {
+        extseed[SYMBYTES] = j as u8;
+        extseed[SYMBYTES + 1] = i as u8;
+      }
+      let mut state = KeccakState::default();
+      shake128_absorb(&mut state, &extseed, SYMBYTES + 2);
+      shake128_finalize(&mut state);
+      shake128_squeezeblocks(&mut buf, GEN_MATRIX_NBLOCKS, &mut state);
+
+      let coeffs = &mut a[i].vec[j].coeffs;
+      let mut ctr = rej_uniform(coeffs, N, &buf, buf.len());
+      while ctr < N {
+        shake128_squeezeblocks(&mut buf, 1, &mut state);
+        ctr += rej_uniform(&mut coeffs[ctr..], N - ctr, &buf, SHAKE128_RATE);
+      }
+    }
+  }
+}
+
+/// Generates public and private key for the CPA-secure
+/// public-key encryption scheme underlying ML-KEM.
+pub fn indcpa_keypair_derand(pk: &mut [u8], sk: &mut [u8], coins: &[u8])
+{
+  let mut buf = [0u8; 2 * SYMBYTES];
+  let mut a = vec![Polyvec::default(); K];
+  let (mut e, mut pkpv, mut skpv) =
+    (Polyvec::default(), Polyvec::default(), Polyvec::default());
+  let mut nonce = 0u8;
+
+  // Domain separate with the module rank
+  let mut input = [0u8; SYMBYTES + 1];
+  input[..SYMBYTES].copy_from_slice(&coins[..SYMBYTES]);
+  input[SYMBYTES] = K as u8;
+  sha3_512(&mut buf, &input, SYMBYTES + 1);
+  let (publicseed, noiseseed) = buf.split_at(SYMBYTES);
+
+  gen_matrix(&mut a, publicseed, false);
+
+  for i in 0..K {
+    poly_getnoise_eta(&mut skpv.vec[i], noiseseed, nonce);
+    nonce += 1;
+  }
+  for i in 0..K {
+    poly_getnoise_eta(&mut e.vec[i], noiseseed, nonce);
+    nonce += 1;
+  }
+
+  polyvec_ntt(&mut skpv);
+  polyvec_ntt(&mut e);
+
+  // Matrix-vector multiplication
+  for i in 0..K {
+    polyvec_basemul_acc_montgomery(&mut pkpv.vec[i], &a[i], &skpv);
+    poly_tomont(&mut pkpv.vec[i]);
+  }
+
+  polyvec_add(&mut pkpv, &e);
+  polyvec_reduce(&mut pkpv);
+
+  polyvec_tobytes(sk, &skpv);
+  pack_pk(pk, &pkpv, publicseed);
+}
+
+/// Encryption function of the CPA-secure
+/// public-key encryption scheme underlying ML-KEM.
+pub fn indcpa_enc(c: &mut [u8], m: &[u8], pk: &[u8], coins: &[u8])
+{
+  let mut seed = [0u8; SYMBYTES];
+  let mut at = vec![Polyvec::default(); K];
+  let (mut sp, mut pkpv, mut ep, mut b) = (
+    Polyvec::default(),
+    Polyvec::default(),
+    Polyvec::default(),
+    Polyvec::default(),
+  );
+  let (mut v, mut k, mut epp) =
+    (Poly::default(), Poly::default(), Poly::default());
+  let mut nonce = 0u8;
+
+  unpack_pk(&mut pkpv, &mut seed, pk);
+  poly_frommsg(&mut k, m);
+  gen_matrix(&mut at, &seed, true);
+
+  for i in 0..K {
+    poly_getnoise_eta(&mut sp.vec[i], coins, nonce);
+    nonce += 1;
+  }
+  for i in 0..K {
+    poly_getnoise_eta(&mut ep.vec[i], coins, nonce);
+    nonce += 1;
+  }
+  poly_getnoise_eta(&mut epp, coins, nonce);
+
+  polyvec_ntt(&mut sp);
+
+  // Matrix-vector multiplication
+  for i in 0..K {
+    polyvec_basemul_acc_montgomery(&mut b.vec[i], &at[i], &sp);
+  }
+  polyvec_basemul_acc_montgomery(&mut v, &pkpv, &sp);
+
+  polyvec_invntt_tomont(&mut b);
+  poly_invntt_tomont(&mut v);
+
+  polyvec_add(&mut b, &ep);
+  poly_add(&mut v, &epp);
+  poly_add(&mut v, &k);
+  polyvec_reduce(&mut b);
+  poly_reduce(&mut v);
+
+  pack_ciphertext(c, &b, &v);
+}
+
+/// Decryption function of the CPA-secure
+/// public-key encryption scheme underlying ML-KEM.
+pub fn indcpa_dec(m: &mut [u8], c: &[u8], sk: &[u8])
+{
+  let (mut b, mut skpv) = (Polyvec::default(), Polyvec::default());
+  let (mut v, mut mp) = (Poly::default(), Poly::default());
+
+  unpack_ciphertext(&mut b, &mut v, c);
+  polyvec_frombytes(&mut skpv, sk);
+
+  polyvec_ntt(&mut b);
+  polyvec_basemul_acc_montgomery(&mut mp, &skpv, &b);
+  poly_invntt_tomont(&mut mp);
+
+  poly_sub_from(&mut mp, &v);
+  poly_reduce(&mut mp);
+
+  poly_tomsg(m, &mp);
+}
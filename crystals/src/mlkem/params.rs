@@ -0,0 +1,27 @@
+pub const K: usize = 3;
+pub const N: usize = 256;
+pub const Q: usize = 3329;
+pub const ETA1: usize = 2;
+
+pub const SYMBYTES: usize = 32;
+pub const SSBYTES: usize = 32;
+
+pub const POLYBYTES: usize = 384;
+pub const POLYVECBYTES: usize = K * POLYBYTES;
+pub const POLYCOMPRESSEDBYTES: usize = 128;
+pub const POLYVECCOMPRESSEDBYTES: usize = K * 320;
+
+pub const INDCPA_PUBLICKEYBYTES: usize = POLYVECBYTES + SYMBYTES;
+pub const INDCPA_SECRETKEYBYTES: usize = POLYVECBYTES;
+pub const INDCPA_BYTES: usize = POLYVECCOMPRESSEDBYTES + POLYCOMPRESSEDBYTES;
+
+pub const PUBLICKEYBYTES: usize = INDCPA_PUBLICKEYBYTES;
+// 32 bytes of additional space to save H(pk)
+pub const SECRETKEYBYTES: usize =
+  INDCPA_SECRETKEYBYTES + INDCPA_PUBLICKEYBYTES + 2 * SYMBYTES;
+pub const CIPHERTEXTBYTES: usize = INDCPA_BYTES;
+
+// Concise types to avoid cast cluttering
+pub const Q_I16: i16 = Q as i16;
+pub const Q_I32: i32 = Q as i32;
+pub const Q_U32: u32 = Q as u32;
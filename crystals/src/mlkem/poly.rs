@@ -0,0 +1,343 @@
+#![allow(clippy::needless_range_loop)]
+
+use super::{ntt::*, params::*, reduce::*};
+use crate::fips202::*;
+
+#[derive(Clone)]
+pub struct Poly
+{
+  pub coeffs: Box<[i16]>,
+}
+
+impl Default for Poly
+{
+  fn default() -> Self
+  {
+    Poly {
+      coeffs: vec![0; N].into_boxed_slice(),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Polyvec
+{
+  pub vec: Box<[Poly]>,
+}
+
+impl Default for Polyvec
+{
+  fn default() -> Self
+  {
+    Polyvec {
+      vec: vec![Poly::default(); K].into_boxed_slice(),
+    }
+  }
+}
+
+/// Compression and subsequent serialization of a polynomial.
+pub fn poly_compress(r: &mut [u8], a: &Poly)
+{
+  let mut t = [0u8; 8];
+  for i in 0..N / 8 {
+    for j in 0..8 {
+      // map to positive standard representatives
+      let mut u = a.coeffs[8 * i + j] as i32;
+      u += (u >> 15) & Q_I32;
+      // t[j] = ((((u as u32) << 4) + Q/2) / Q) & 15
+      let mut d0 = (u as u32) << 4;
+      d0 += 1665;
+      d0 = d0.wrapping_mul(80635);
+      d0 >>= 28;
+      t[j] = (d0 & 0xf) as u8;
+    }
+    r[4 * i] = t[0] | (t[1] << 4);
+    r[4 * i + 1] = t[2] | (t[3] << 4);
+    r[4 * i + 2] = t[4] | (t[5] << 4);
+    r[4 * i + 3] = t[6] | (t[7] << 4);
+  }
+}
+
+/// De-serialization and subsequent decompression of a polynomial;
+/// approximate inverse of poly_compress.
+pub fn poly_decompress(r: &mut Poly, a: &[u8])
+{
+  for i in 0..N / 2 {
+    r.coeffs[2 * i] = (((a[i] & 15) as u32 * Q_U32 + 8) >> 4) as i16;
+    r.coeffs[2 * i + 1] = (((a[i] >> 4) as u32 * Q_U32 + 8) >> 4) as i16;
+  }
+}
+
+/// Serialization of a polynomial.
+/// Input coefficients are assumed to lie in [-Q+1, Q-1].
+pub fn poly_tobytes(r: &mut [u8], a: &Poly)
+{
+  for i in 0..N / 2 {
+    // map to positive standard representatives
+    let mut t0 = a.coeffs[2 * i];
+    t0 += (t0 >> 15) & Q_I16;
+    let mut t1 = a.coeffs[2 * i + 1];
+    t1 += (t1 >> 15) & Q_I16;
+    let (t0, t1) = (t0 as u16, t1 as u16);
+    r[3 * i] = t0 as u8;
+    r[3 * i + 1] = ((t0 >> 8) | (t1 << 4)) as u8;
+    r[3 * i + 2] = (t1 >> 4) as u8;
+  }
+}
+
+/// De-serialization of a polynomial; inverse of poly_tobytes.
+/// Output coefficients lie in [0, 4095].
+pub fn poly_frombytes(r: &mut Poly, a: &[u8])
+{
+  for i in 0..N / 2 {
+    r.coeffs[2 * i] =
+      ((a[3 * i] as u16 | (a[3 * i + 1] as u16) << 8) & 0xFFF) as i16;
+    r.coeffs[2 * i + 1] =
+      ((a[3 * i + 1] as u16 >> 4 | (a[3 * i + 2] as u16) << 4) & 0xFFF) as i16;
+  }
+}
+
+/// Convert 32-byte message to polynomial.
+pub fn poly_frommsg(r: &mut Poly, msg: &[u8])
+{
+  for i in 0..N / 8 {
+    for j in 0..8 {
+      let mask = -(((msg[i] >> j) & 1) as i16);
+      r.coeffs[8 * i + j] = mask & ((Q_I16 + 1) / 2);
+    }
+  }
+}
+
+/// Convert polynomial to 32-byte message.
+pub fn poly_tomsg(msg: &mut [u8], a: &Poly)
+{
+  for i in 0..N / 8 {
+    msg[i] = 0;
+    for j in 0..8 {
+      // t = ((((a as u32) << 1) + Q/2) / Q) & 1
+      let mut t = a.coeffs[8 * i + j] as i32;
+      t += (t >> 15) & Q_I32;
+      let mut t = (t as u32) << 1;
+      t += 1665;
+      t = t.wrapping_mul(80635);
+      t >>= 28;
+      t &= 1;
+      msg[i] |= (t << j) as u8;
+    }
+  }
+}
+
+/// Given an array of uniformly random bytes, compute polynomial
+/// with coefficients distributed according to a centered binomial
+/// distribution with parameter eta=2.
+fn cbd2(r: &mut Poly, buf: &[u8])
+{
+  for i in 0..N / 8 {
+    let t = u32::from_le_bytes([
+      buf[4 * i],
+      buf[4 * i + 1],
+      buf[4 * i + 2],
+      buf[4 * i + 3],
+    ]);
+    let mut d = t & 0x55555555;
+    d += (t >> 1) & 0x55555555;
+    for j in 0..8 {
+      let a = ((d >> (4 * j)) & 0x3) as i16;
+      let b = ((d >> (4 * j + 2)) & 0x3) as i16;
+      r.coeffs[8 * i + j] = a - b;
+    }
+  }
+}
+
+/// Sample a polynomial deterministically from a seed and a nonce,
+/// with output polynomial close to centered binomial distribution
+/// with parameter ETA1 (equal to ETA2 for this parameter set).
+pub fn poly_getnoise_eta(r: &mut Poly, seed: &[u8], nonce: u8)
+{
+  let mut buf = [0u8; ETA1 * N / 4];
+  let mut extkey = [0u8; SYMBYTES + 1];
+  extkey[..SYMBYTES].copy_from_slice(&seed[..SYMBYTES]);
+  extkey[SYMBYTES] = nonce;
+  shake256(&mut buf, ETA1 * N / 4, &extkey, SYMBYTES + 1);
+  cbd2(r, &buf);
+}
+
+/// Computes negacyclic number-theoretic transform (NTT) of
+/// a polynomial in place; inputs assumed to be in normal order,
+/// output in bitreversed order.
+pub fn poly_ntt(r: &mut Poly)
+{
+  ntt(&mut r.coeffs);
+  poly_reduce(r);
+}
+
+/// Computes inverse of negacyclic number-theoretic transform (NTT)
+/// of a polynomial in place and multiplies by Montgomery factor 2^16.
+pub fn poly_invntt_tomont(r: &mut Poly)
+{
+  invntt(&mut r.coeffs);
+}
+
+/// Multiplication of two polynomials in NTT domain.
+pub fn poly_basemul_montgomery(r: &mut Poly, a: &Poly, b: &Poly)
+{
+  for i in 0..N / 4 {
+    let zeta = ZETAS[64 + i];
+    basemul(
+      &mut r.coeffs[4 * i..],
+      &a.coeffs[4 * i..],
+      &b.coeffs[4 * i..],
+      zeta,
+    );
+    basemul(
+      &mut r.coeffs[4 * i + 2..],
+      &a.coeffs[4 * i + 2..],
+      &b.coeffs[4 * i + 2..],
+      -zeta,
+    );
+  }
+}
+
+/// Inplace conversion of all coefficients of a polynomial
+/// from normal domain to Montgomery domain.
+pub fn poly_tomont(r: &mut Poly)
+{
+  const F: i32 = ((1u64 << 32) % Q as u64) as i32;
+  for i in 0..N {
+    r.coeffs[i] = montgomery_reduce(r.coeffs[i] as i32 * F);
+  }
+}
+
+/// Applies Barrett reduction to all coefficients of a polynomial.
+pub fn poly_reduce(r: &mut Poly)
+{
+  for i in 0..N {
+    r.coeffs[i] = barrett_reduce(r.coeffs[i]);
+  }
+}
+
+/// Add two polynomials. No modular reduction is performed.
+pub fn poly_add(r: &mut Poly, b: &Poly)
+{
+  for i in 0..N {
+    r.coeffs[i] += b.coeffs[i];
+  }
+}
+
+/// Subtract two polynomials, r = b - r. No modular reduction
+/// is performed.
+pub fn poly_sub_from(r: &mut Poly, b: &Poly)
+{
+  for i in 0..N {
+    r.coeffs[i] = b.coeffs[i] - r.coeffs[i];
+  }
+}
+
+/// Compress and serialize vector of polynomials.
+pub fn polyvec_compress(r: &mut [u8], a: &Polyvec)
+{
+  let mut t = [0u16; 4];
+  for i in 0..K {
+    for j in 0..N / 4 {
+      for k in 0..4 {
+        let mut u = a.vec[i].coeffs[4 * j + k] as i32;
+        u += (u >> 15) & Q_I32;
+        // t[k] = ((((u as u64) << 10) + Q/2) / Q) & 0x3ff
+        let mut d0 = (u as u64) << 10;
+        d0 += 1665;
+        d0 *= 1290167;
+        d0 >>= 32;
+        t[k] = (d0 & 0x3ff) as u16;
+      }
+      let idx = 320 * i + 5 * j;
+      r[idx] = t[0] as u8;
+      r[idx + 1] = ((t[0] >> 8) | (t[1] << 2)) as u8;
+      r[idx + 2] = ((t[1] >> 6) | (t[2] << 4)) as u8;
+      r[idx + 3] = ((t[2] >> 4) | (t[3] << 6)) as u8;
+      r[idx + 4] = (t[3] >> 2) as u8;
+    }
+  }
+}
+
+/// De-serialize and decompress vector of polynomials;
+/// approximate inverse of polyvec_compress.
+pub fn polyvec_decompress(r: &mut Polyvec, a: &[u8])
+{
+  let mut t = [0u16; 4];
+  for i in 0..K {
+    for j in 0..N / 4 {
+      let idx = 320 * i + 5 * j;
+      t[0] = a[idx] as u16 | (a[idx + 1] as u16) << 8;
+      t[1] = (a[idx + 1] >> 2) as u16 | (a[idx + 2] as u16) << 6;
+      t[2] = (a[idx + 2] >> 4) as u16 | (a[idx + 3] as u16) << 4;
+      t[3] = (a[idx + 3] >> 6) as u16 | (a[idx + 4] as u16) << 2;
+      for k in 0..4 {
+        r.vec[i].coeffs[4 * j + k] =
+          (((t[k] & 0x3FF) as u32 * Q_U32 + 512) >> 10) as i16;
+      }
+    }
+  }
+}
+
+/// Serialize vector of polynomials.
+pub fn polyvec_tobytes(r: &mut [u8], a: &Polyvec)
+{
+  for i in 0..K {
+    poly_tobytes(&mut r[i * POLYBYTES..], &a.vec[i]);
+  }
+}
+
+/// De-serialize vector of polynomials; inverse of polyvec_tobytes.
+pub fn polyvec_frombytes(r: &mut Polyvec, a: &[u8])
+{
+  for i in 0..K {
+    poly_frombytes(&mut r.vec[i], &a[i * POLYBYTES..]);
+  }
+}
+
+/// Apply forward NTT to all elements of a vector of polynomials.
+pub fn polyvec_ntt(r: &mut Polyvec)
+{
+  for i in 0..K {
+    poly_ntt(&mut r.vec[i]);
+  }
+}
+
+/// Apply inverse NTT to all elements of a vector of polynomials
+/// and multiply by Montgomery factor 2^16.
+pub fn polyvec_invntt_tomont(r: &mut Polyvec)
+{
+  for i in 0..K {
+    poly_invntt_tomont(&mut r.vec[i]);
+  }
+}
+
+/// Multiply elements of a and b in NTT domain, accumulate into r,
+/// and multiply by 2^-16.
+pub fn polyvec_basemul_acc_montgomery(r: &mut Poly, a: &Polyvec, b: &Polyvec)
+{
+  let mut t = Poly::default();
+  poly_basemul_montgomery(r, &a.vec[0], &b.vec[0]);
+  for i in 1..K {
+    poly_basemul_montgomery(&mut t, &a.vec[i], &b.vec[i]);
+    poly_add(r, &t);
+  }
+  poly_reduce(r);
+}
+
+/// Applies Barrett reduction to each coefficient
+/// of each element of a vector of polynomials.
+pub fn polyvec_reduce(r: &mut Polyvec)
+{
+  for i in 0..K {
+    poly_reduce(&mut r.vec[i]);
+  }
+}
+
+/// Add vectors of polynomials. No modular reduction is performed.
+pub fn polyvec_add(r: &mut Polyvec, b: &Polyvec)
+{
+  for i in 0..K {
+    poly_add(&mut r.vec[i], &b.vec[i]);
+  }
+}
@@ -0,0 +1,24 @@
+use super::params::*;
+
+pub const QINV: i16 = -3327; // q^-1 mod 2^16
+
+/// For finite field element a with -2^{15}Q <= a <= Q*2^15,
+/// compute r \equiv a*R^{-1} (mod Q) such that -Q < r < Q.
+///
+/// Returns r.
+pub fn montgomery_reduce(a: i32) -> i16
+{
+  let t = (a as i16).wrapping_mul(QINV);
+  ((a - t as i32 * Q_I32) >> 16) as i16
+}
+
+/// Barrett reduction; given a 16-bit integer a, computes
+/// centered representative congruent to a mod q in {-(q-1)/2,...,(q-1)/2}
+///
+/// Returns r.
+pub fn barrett_reduce(a: i16) -> i16
+{
+  const V: i32 = ((1 << 26) + Q_I32 / 2) / Q_I32;
+  let t = ((V * a as i32 + (1 << 25)) >> 26) as i16;
+  a - t * Q_I16
+}
@@ -43,6 +43,16 @@ pub const ZETAS: [i32; N] = [
 //
 /// Arguments: - uint32_t p[N]: input/output coefficient array
 pub fn ntt(a: &mut [i32]) {
+  #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+  if crate::avx2::enabled() {
+    // SAFETY: the CPU supports AVX2
+    return unsafe { crate::avx2::ntt(a) };
+  }
+  ntt_portable(a)
+}
+
+/// Portable forward NTT.
+pub fn ntt_portable(a: &mut [i32]) {
   let mut j;
   let mut k = 0usize;
   let mut len = 128;
@@ -76,6 +86,16 @@ pub fn ntt(a: &mut [i32]) {
 //
 /// Arguments:   - uint32_t p[N]: input/output coefficient array
 pub fn invntt_tomont(a: &mut [i32]) {
+  #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+  if crate::avx2::enabled() {
+    // SAFETY: the CPU supports AVX2
+    return unsafe { crate::avx2::invntt_tomont(a) };
+  }
+  invntt_tomont_portable(a)
+}
+
+/// Portable inverse NTT.
+pub fn invntt_tomont_portable(a: &mut [i32]) {
   let mut j;
   let mut k = 256usize;
   let mut len = 1;
@@ -104,7 +124,11 @@ pub fn invntt_tomont(a: &mut [i32]) {
   }
 }
 
+#[cfg(test)]
 mod tests {
+  use crate::{params::*, reduce::reduce32};
+  use proptest::prelude::*;
+
   #[test]
   fn ntt() {
     let mut a = [
@@ -240,4 +264,25 @@ mod tests {
     super::invntt_tomont(&mut a);
     assert_eq!(a, a_output);
   }
+
+  proptest! {
+    /// The NTT followed by its inverse multiplies by the Montgomery
+    /// factor 2^32, with every output coefficient below Q.
+    #[test]
+    fn invntt_inverts_ntt(
+      a in prop::collection::vec(-(Q_I32 - 1)..Q_I32, N)
+    ) {
+      let mut r = a.clone();
+      super::ntt(&mut r);
+      for c in r.iter_mut() {
+        *c = reduce32(*c);
+      }
+      super::invntt_tomont(&mut r);
+      for (&x, &y) in a.iter().zip(r.iter()) {
+        prop_assert!(y.abs() < Q_I32);
+        let diff = y as i64 - ((x as i64) << 32);
+        prop_assert_eq!(diff.rem_euclid(Q as i64), 0);
+      }
+    }
+  }
 }
@@ -161,3 +161,100 @@ pub fn unpack_sig(
 
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  type Coeffs = Vec<Vec<i32>>;
+
+  fn polys(
+    n: usize,
+    range: core::ops::RangeInclusive<i32>,
+  ) -> impl Strategy<Value = Coeffs> {
+    prop::collection::vec(prop::collection::vec(range, N), n)
+  }
+
+  fn to_polys(v: &Coeffs) -> Box<[Poly]> {
+    v.iter().map(|c| Poly { coeffs: c.clone().into() }).collect()
+  }
+
+  fn from_polys(v: &[Poly]) -> Coeffs {
+    v.iter().map(|p| p.coeffs.to_vec()).collect()
+  }
+
+  /// Positions of the ones of a hint vector, at most OMEGA in total.
+  fn hints() -> impl Strategy<Value = Vec<(usize, usize)>> {
+    prop::collection::vec((0..K, 0..N), 0..=OMEGA)
+  }
+
+  proptest! {
+    #[cfg(feature = "offchain")]
+    #[test]
+    fn pk_round_trip(
+      rho in prop::array::uniform32(any::<u8>()),
+      t1 in polys(K, 0..=(1 << 10) - 1),
+    ) {
+      let mut pk = [0u8; PUBLICKEYBYTES];
+      pack_pk(&mut pk, &rho, &Polyveck { vec: to_polys(&t1) });
+
+      let (mut rho2, mut t1_2) = ([0u8; SEEDBYTES], Polyveck::default());
+      unpack_pk(&mut rho2, &mut t1_2, &pk);
+      prop_assert_eq!(rho2, rho);
+      prop_assert_eq!(from_polys(&t1_2.vec), t1);
+    }
+
+    #[cfg(feature = "offchain")]
+    #[test]
+    fn sk_round_trip(
+      seeds in prop::array::uniform3(prop::array::uniform32(any::<u8>())),
+      s1 in polys(L, -ETA_I32..=ETA_I32),
+      s2 in polys(K, -ETA_I32..=ETA_I32),
+      t0 in polys(K, -(1 << (D - 1)) + 1..=1 << (D - 1)),
+    ) {
+      let [rho, tr, key] = seeds;
+      let mut sk = vec![0u8; SECRETKEYBYTES];
+      pack_sk(
+        &mut sk,
+        &rho,
+        &tr,
+        &key,
+        &Polyveck { vec: to_polys(&t0) },
+        &Polyvecl { vec: to_polys(&s1) },
+        &Polyveck { vec: to_polys(&s2) },
+      );
+
+      let mut out = [[0u8; SEEDBYTES]; 3];
+      let [rho2, tr2, key2] = &mut out;
+      let (mut s1_2, mut s2_2, mut t0_2) =
+        (Polyvecl::default(), Polyveck::default(), Polyveck::default());
+      unpack_sk(rho2, tr2, key2, &mut t0_2, &mut s1_2, &mut s2_2, &sk);
+      prop_assert_eq!(out, [rho, tr, key]);
+      prop_assert_eq!(from_polys(&s1_2.vec), s1);
+      prop_assert_eq!(from_polys(&s2_2.vec), s2);
+      prop_assert_eq!(from_polys(&t0_2.vec), t0);
+    }
+
+    #[test]
+    fn sig_round_trip(
+      c in prop::array::uniform32(any::<u8>()),
+      z in polys(L, -(GAMMA1_I32 - 1)..=GAMMA1_I32),
+      ones in hints(),
+    ) {
+      let mut h = Polyveck::default();
+      for (i, j) in ones {
+        h.vec[i].coeffs[j] = 1;
+      }
+      let mut sig = vec![0u8; SIGNBYTES];
+      pack_sig(&mut sig, Some(&c), &Polyvecl { vec: to_polys(&z) }, &h);
+
+      let (mut c2, mut z2, mut h2) =
+        ([0u8; SEEDBYTES], Polyvecl::default(), Polyveck::default());
+      prop_assert!(unpack_sig(&mut c2, &mut z2, &mut h2, &sig).is_ok());
+      prop_assert_eq!(c2, c);
+      prop_assert_eq!(from_polys(&z2.vec), z);
+      prop_assert_eq!(from_polys(&h2.vec), from_polys(&h.vec));
+    }
+  }
+}
@@ -0,0 +1,619 @@
+//! DER and PEM encodings of Dilithium keys: SubjectPublicKeyInfo
+//! (RFC 5280) for public keys and PKCS#8 OneAsymmetricKey (RFC 5958) for
+//! private keys.
+//!
+//! The privateKey field follows the ML-DSA private key CHOICE of
+//! draft-ietf-lamps-dilithium-certificates: the 32-byte key generation
+//! seed, the expanded (packed) secret key, or both. The keys here are
+//! round-3 Dilithium rather than ML-DSA, so they are labelled with the
+//! round-3 OIDs of the 1.3.6.1.4.1.2.267 arc used by Open Quantum Safe.
+//! ML-DSA OIDs are recognised, and rejected as unsupported.
+
+use crate::{EncodingError, PUBLICKEYBYTES, SECRETKEYBYTES, SEEDBYTES};
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+/// [0] IMPLICIT OCTET STRING, the seed alternative of the private key
+const SEED: u8 = 0x80;
+/// [0] IMPLICIT Attributes of OneAsymmetricKey
+const ATTRIBUTES: u8 = 0xA0;
+/// [1] IMPLICIT BIT STRING, the publicKey of OneAsymmetricKey
+const PUBLIC_KEY: u8 = 0x81;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm
+{
+  Dilithium2,
+  Dilithium3,
+  Dilithium5,
+  MlDsa44,
+  MlDsa65,
+  MlDsa87,
+}
+
+/// The parameter set this crate was built for
+#[cfg(feature = "mode2")]
+pub const ALGORITHM: Algorithm = Algorithm::Dilithium2;
+#[cfg(not(any(feature = "mode2", feature = "mode5")))]
+pub const ALGORITHM: Algorithm = Algorithm::Dilithium3;
+#[cfg(feature = "mode5")]
+pub const ALGORITHM: Algorithm = Algorithm::Dilithium5;
+
+impl Algorithm
+{
+  pub const ALL: [Algorithm; 6] = [
+    Algorithm::Dilithium2,
+    Algorithm::Dilithium3,
+    Algorithm::Dilithium5,
+    Algorithm::MlDsa44,
+    Algorithm::MlDsa65,
+    Algorithm::MlDsa87,
+  ];
+
+  /// Dotted form of the OID
+  pub fn oid_string(self) -> &'static str
+  {
+    match self {
+      Algorithm::Dilithium2 => "1.3.6.1.4.1.2.267.7.4.4",
+      Algorithm::Dilithium3 => "1.3.6.1.4.1.2.267.7.6.5",
+      Algorithm::Dilithium5 => "1.3.6.1.4.1.2.267.7.8.7",
+      Algorithm::MlDsa44 => "2.16.840.1.101.3.4.3.17",
+      Algorithm::MlDsa65 => "2.16.840.1.101.3.4.3.18",
+      Algorithm::MlDsa87 => "2.16.840.1.101.3.4.3.19",
+    }
+  }
+
+  /// DER contents octets of the OID
+  pub fn oid(self) -> &'static [u8]
+  {
+    match self {
+      Algorithm::Dilithium2 => &[43, 6, 1, 4, 1, 2, 130, 11, 7, 4, 4],
+      Algorithm::Dilithium3 => &[43, 6, 1, 4, 1, 2, 130, 11, 7, 6, 5],
+      Algorithm::Dilithium5 => &[43, 6, 1, 4, 1, 2, 130, 11, 7, 8, 7],
+      Algorithm::MlDsa44 => &[96, 134, 72, 1, 101, 3, 4, 3, 17],
+      Algorithm::MlDsa65 => &[96, 134, 72, 1, 101, 3, 4, 3, 18],
+      Algorithm::MlDsa87 => &[96, 134, 72, 1, 101, 3, 4, 3, 19],
+    }
+  }
+
+  pub fn from_oid(oid: &[u8]) -> Option<Algorithm>
+  {
+    Algorithm::ALL.into_iter().find(|a| a.oid() == oid)
+  }
+
+  /// Lowercase name, e.g. "dilithium3" or "ml-dsa-65"
+  pub fn name(self) -> &'static str
+  {
+    match self {
+      Algorithm::Dilithium2 => "dilithium2",
+      Algorithm::Dilithium3 => "dilithium3",
+      Algorithm::Dilithium5 => "dilithium5",
+      Algorithm::MlDsa44 => "ml-dsa-44",
+      Algorithm::MlDsa65 => "ml-dsa-65",
+      Algorithm::MlDsa87 => "ml-dsa-87",
+    }
+  }
+}
+
+/// Contents of the PKCS#8 privateKey field
+#[derive(Clone, PartialEq, Eq)]
+pub enum PrivateKey
+{
+  /// The key generation seed
+  Seed([u8; SEEDBYTES]),
+  /// The packed secret key
+  Expanded(Vec<u8>),
+  /// Seed and packed secret key. Decoding does not check that they
+  /// agree; Keypair::from_private_key does.
+  Both([u8; SEEDBYTES], Vec<u8>),
+}
+
+/// Key material elided
+impl std::fmt::Debug for PrivateKey
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    match self {
+      PrivateKey::Seed(_) => write!(f, "Seed(<elided>)"),
+      PrivateKey::Expanded(_) => write!(f, "Expanded(<elided>)"),
+      PrivateKey::Both(..) => write!(f, "Both(<elided>)"),
+    }
+  }
+}
+
+/// Encodes a packed public key as SubjectPublicKeyInfo.
+pub fn public_key_to_der(pk: &[u8]) -> Result<Vec<u8>, EncodingError>
+{
+  if pk.len() != PUBLICKEYBYTES {
+    return Err(EncodingError::InvalidKey);
+  }
+  let mut bits = vec![0u8];
+  bits.extend_from_slice(pk);
+  let mut content = algorithm_identifier();
+  content.extend(tlv(BIT_STRING, &bits));
+  Ok(tlv(SEQUENCE, &content))
+}
+
+/// Decodes a SubjectPublicKeyInfo into a packed public key.
+pub fn public_key_from_der(der: &[u8]) -> Result<Vec<u8>, EncodingError>
+{
+  let mut outer = Reader(der);
+  let mut spki = Reader(outer.read(SEQUENCE)?);
+  outer.finish()?;
+  read_algorithm(&mut spki)?;
+  let bits = spki.read(BIT_STRING)?;
+  spki.finish()?;
+
+  match bits.split_first() {
+    Some((0, pk)) if pk.len() == PUBLICKEYBYTES => Ok(pk.to_vec()),
+    Some((0, _)) => Err(EncodingError::InvalidKey),
+    _ => Err(EncodingError::Der),
+  }
+}
+
+pub fn public_key_to_pem(pk: &[u8]) -> Result<String, EncodingError>
+{
+  Ok(pem_encode("PUBLIC KEY", &public_key_to_der(pk)?, 64))
+}
+
+pub fn public_key_from_pem(pem: &str) -> Result<Vec<u8>, EncodingError>
+{
+  let der = pem_decode("PUBLIC KEY", pem).ok_or(EncodingError::Pem)?;
+  public_key_from_der(&der)
+}
+
+/// Encodes a private key as a version 1 OneAsymmetricKey without
+/// attributes or public key.
+pub fn private_key_to_der(key: &PrivateKey) -> Result<Vec<u8>, EncodingError>
+{
+  let choice = match key {
+    PrivateKey::Seed(seed) => tlv(SEED, seed),
+    PrivateKey::Expanded(sk) if sk.len() == SECRETKEYBYTES => {
+      tlv(OCTET_STRING, sk)
+    }
+    PrivateKey::Both(seed, sk) if sk.len() == SECRETKEYBYTES => {
+      let mut both = tlv(OCTET_STRING, seed);
+      both.extend(tlv(OCTET_STRING, sk));
+      tlv(SEQUENCE, &both)
+    }
+    _ => return Err(EncodingError::InvalidKey),
+  };
+  let mut content = tlv(INTEGER, &[0]);
+  content.extend(algorithm_identifier());
+  content.extend(tlv(OCTET_STRING, &choice));
+  Ok(tlv(SEQUENCE, &content))
+}
+
+/// Decodes a OneAsymmetricKey. Attributes and an embedded public key
+/// are accepted and ignored, as the public key follows from the private
+/// key.
+pub fn private_key_from_der(der: &[u8]) -> Result<PrivateKey, EncodingError>
+{
+  let mut outer = Reader(der);
+  let mut info = Reader(outer.read(SEQUENCE)?);
+  outer.finish()?;
+  let version = info.read(INTEGER)?;
+  if version != [0] && version != [1] {
+    return Err(EncodingError::Der);
+  }
+  read_algorithm(&mut info)?;
+  let mut choice = Reader(info.read(OCTET_STRING)?);
+  if info.peek() == Some(ATTRIBUTES) {
+    info.read(ATTRIBUTES)?;
+  }
+  if version == [1] && info.peek() == Some(PUBLIC_KEY) {
+    info.read(PUBLIC_KEY)?;
+  }
+  info.finish()?;
+
+  let key = match choice.peek() {
+    Some(SEED) => PrivateKey::Seed(seed(choice.read(SEED)?)?),
+    Some(OCTET_STRING) => {
+      PrivateKey::Expanded(expanded(choice.read(OCTET_STRING)?)?)
+    }
+    Some(SEQUENCE) => {
+      let mut both = Reader(choice.read(SEQUENCE)?);
+      let seed = seed(both.read(OCTET_STRING)?)?;
+      let sk = expanded(both.read(OCTET_STRING)?)?;
+      both.finish()?;
+      PrivateKey::Both(seed, sk)
+    }
+    _ => return Err(EncodingError::Der),
+  };
+  choice.finish()?;
+  Ok(key)
+}
+
+pub fn private_key_to_pem(key: &PrivateKey) -> Result<String, EncodingError>
+{
+  Ok(pem_encode("PRIVATE KEY", &private_key_to_der(key)?, 64))
+}
+
+pub fn private_key_from_pem(pem: &str) -> Result<PrivateKey, EncodingError>
+{
+  let der = pem_decode("PRIVATE KEY", pem).ok_or(EncodingError::Pem)?;
+  private_key_from_der(&der)
+}
+
+fn seed(bytes: &[u8]) -> Result<[u8; SEEDBYTES], EncodingError>
+{
+  bytes.try_into().map_err(|_| EncodingError::InvalidKey)
+}
+
+fn expanded(bytes: &[u8]) -> Result<Vec<u8>, EncodingError>
+{
+  match bytes.len() == SECRETKEYBYTES {
+    true => Ok(bytes.to_vec()),
+    false => Err(EncodingError::InvalidKey),
+  }
+}
+
+/// AlgorithmIdentifier with absent parameters
+fn algorithm_identifier() -> Vec<u8>
+{
+  tlv(SEQUENCE, &tlv(OID, ALGORITHM.oid()))
+}
+
+fn read_algorithm(r: &mut Reader) -> Result<(), EncodingError>
+{
+  let mut id = Reader(r.read(SEQUENCE)?);
+  let oid = id.read(OID)?;
+  id.finish()?;
+  match Algorithm::from_oid(oid) {
+    Some(ALGORITHM) => Ok(()),
+    _ => Err(EncodingError::UnsupportedAlgorithm),
+  }
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8>
+{
+  let mut out = vec![tag];
+  let len = content.len();
+  if len < 0x80 {
+    out.push(len as u8);
+  } else {
+    let bytes = len.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    out.push(0x80 | (bytes.len() - skip) as u8);
+    out.extend_from_slice(&bytes[skip..]);
+  }
+  out.extend_from_slice(content);
+  out
+}
+
+/// Reads DER with definite, minimally encoded lengths.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a>
+{
+  fn peek(&self) -> Option<u8>
+  {
+    self.0.first().copied()
+  }
+
+  /// Returns the contents of the next element, which must have `tag`.
+  fn read(&mut self, tag: u8) -> Result<&'a [u8], EncodingError>
+  {
+    let err = EncodingError::Der;
+    let (&t, rest) = self.0.split_first().ok_or(err)?;
+    let (&first, rest) = rest.split_first().ok_or(EncodingError::Der)?;
+    if t != tag {
+      return Err(EncodingError::Der);
+    }
+    let (len, rest) = if first < 0x80 {
+      (first as usize, rest)
+    } else {
+      let n = (first & 0x7F) as usize;
+      if n == 0
+        || n > core::mem::size_of::<usize>()
+        || rest.len() < n
+        || rest[0] == 0
+      {
+        return Err(EncodingError::Der);
+      }
+      let len = rest[..n].iter().fold(0, |acc, &b| acc << 8 | b as usize);
+      if len < 0x80 {
+        return Err(EncodingError::Der);
+      }
+      (len, &rest[n..])
+    };
+    if rest.len() < len {
+      return Err(EncodingError::Der);
+    }
+    let (content, rest) = rest.split_at(len);
+    self.0 = rest;
+    Ok(content)
+  }
+
+  fn finish(self) -> Result<(), EncodingError>
+  {
+    match self.0.is_empty() {
+      true => Ok(()),
+      false => Err(EncodingError::Der),
+    }
+  }
+}
+
+pub(crate) const BASE64: &[u8; 64] =
+  b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// The URL and filename safe alphabet of RFC 4648, section 5
+#[cfg(any(test, feature = "jose"))]
+pub(crate) const BASE64URL: &[u8; 64] =
+  b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+pub(crate) fn base64_encode(
+  data: &[u8],
+  alphabet: &[u8; 64],
+  pad: bool,
+) -> String
+{
+  let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+  for chunk in data.chunks(3) {
+    let b = [
+      chunk[0],
+      *chunk.get(1).unwrap_or(&0),
+      *chunk.get(2).unwrap_or(&0),
+    ];
+    let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+    for i in 0..4 {
+      match i <= chunk.len() {
+        true => out.push(alphabet[(n >> (18 - 6 * i)) as usize & 63] as char),
+        false if pad => out.push('='),
+        false => (),
+      }
+    }
+  }
+  out
+}
+
+/// Strict decoding: no whitespace, and the unused bits of the last
+/// symbol must be zero. Without `pad`, padding characters are rejected.
+pub(crate) fn base64_decode(
+  text: &str,
+  alphabet: &[u8; 64],
+  pad: bool,
+) -> Option<Vec<u8>>
+{
+  let mut text = text.as_bytes().to_vec();
+  if !pad {
+    if text.contains(&b'=') || text.len() % 4 == 1 {
+      return None;
+    }
+    text.resize(text.len().div_ceil(4) * 4, b'=');
+  }
+  if text.len() % 4 != 0 {
+    return None;
+  }
+  let mut out = Vec::with_capacity(text.len() / 4 * 3);
+  for (i, chunk) in text.chunks(4).enumerate() {
+    let last = i == text.len() / 4 - 1;
+    let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+    if pad > 2 || (pad > 0 && !last) {
+      return None;
+    }
+    let mut n = 0u32;
+    for &c in &chunk[..4 - pad] {
+      let v = alphabet.iter().position(|&b| b == c)?;
+      n = n << 6 | v as u32;
+    }
+    n <<= 6 * pad;
+    if n & ((1 << (8 * pad)) - 1) != 0 {
+      return None;
+    }
+    out.extend_from_slice(&n.to_be_bytes()[1..4 - pad]);
+  }
+  Some(out)
+}
+
+/// Armors `der` in base64 lines of `width` characters.
+pub(crate) fn pem_encode(label: &str, der: &[u8], width: usize) -> String
+{
+  let body = base64_encode(der, BASE64, true);
+  let mut out = format!("-----BEGIN {}-----\n", label);
+  for line in body.as_bytes().chunks(width) {
+    out.push_str(core::str::from_utf8(line).unwrap());
+    out.push('\n');
+  }
+  out.push_str(&format!("-----END {}-----\n", label));
+  out
+}
+
+pub(crate) fn pem_decode(label: &str, pem: &str) -> Option<Vec<u8>>
+{
+  let begin = format!("-----BEGIN {}-----", label);
+  let end = format!("-----END {}-----", label);
+  let body = pem
+    .trim()
+    .strip_prefix(&begin)
+    .and_then(|rest| rest.strip_suffix(&end))?;
+  let body: String = body.split_whitespace().collect();
+  base64_decode(&body, BASE64, true)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  /// Encodes a dotted OID into DER contents octets.
+  fn encode_oid(dotted: &str) -> Vec<u8>
+  {
+    let arcs: Vec<u64> =
+      dotted.split('.').map(|a| a.parse().unwrap()).collect();
+    let mut out = vec![];
+    for &arc in [arcs[0] * 40 + arcs[1]].iter().chain(&arcs[2..]) {
+      let mut groups = vec![(arc & 0x7F) as u8];
+      let mut rest = arc >> 7;
+      while rest > 0 {
+        groups.push(0x80 | (rest & 0x7F) as u8);
+        rest >>= 7;
+      }
+      out.extend(groups.iter().rev());
+    }
+    out
+  }
+
+  fn pk() -> Vec<u8>
+  {
+    (0..PUBLICKEYBYTES).map(|i| (i * 7) as u8).collect()
+  }
+
+  fn sk() -> Vec<u8>
+  {
+    (0..SECRETKEYBYTES).map(|i| (i * 11) as u8).collect()
+  }
+
+  #[test]
+  fn oids()
+  {
+    for alg in Algorithm::ALL {
+      assert_eq!(alg.oid(), encode_oid(alg.oid_string()), "{:?}", alg);
+      assert_eq!(Algorithm::from_oid(alg.oid()), Some(alg));
+    }
+  }
+
+  #[test]
+  fn base64()
+  {
+    // RFC 4648, section 10
+    let vectors = [
+      ("", ""),
+      ("f", "Zg=="),
+      ("fo", "Zm8="),
+      ("foo", "Zm9v"),
+      ("foob", "Zm9vYg=="),
+      ("fooba", "Zm9vYmE="),
+      ("foobar", "Zm9vYmFy"),
+    ];
+    for (plain, encoded) in vectors {
+      assert_eq!(base64_encode(plain.as_bytes(), BASE64, true), encoded);
+      assert_eq!(
+        base64_decode(encoded, BASE64, true).unwrap(),
+        plain.as_bytes()
+      );
+      let unpadded = encoded.trim_end_matches('=');
+      assert_eq!(base64_encode(plain.as_bytes(), BASE64URL, false), unpadded);
+      assert_eq!(
+        base64_decode(unpadded, BASE64URL, false).unwrap(),
+        plain.as_bytes()
+      );
+    }
+    for bad in ["Zg=", "Zh==", "Z===", "Zg==Zg==", "Zm9*"] {
+      assert_eq!(base64_decode(bad, BASE64, true), None, "{}", bad);
+    }
+    for bad in ["Zg==", "Z", "Zh", "Zm9+"] {
+      assert_eq!(base64_decode(bad, BASE64URL, false), None, "{}", bad);
+    }
+    assert_eq!(base64_decode("-_8", BASE64URL, false).unwrap(), [251, 255]);
+  }
+
+  #[test]
+  fn public_key_round_trip()
+  {
+    let pk = pk();
+    let der = public_key_to_der(&pk).unwrap();
+    assert_eq!(public_key_from_der(&der).unwrap(), pk);
+
+    let pem = public_key_to_pem(&pk).unwrap();
+    assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+    assert!(pem.lines().all(|line| line.len() <= 64));
+    assert_eq!(public_key_from_pem(&pem).unwrap(), pk);
+    assert_eq!(
+      public_key_from_pem(&pem.replace("PUBLIC", "PRIVATE")),
+      Err(EncodingError::Pem)
+    );
+    assert_eq!(public_key_to_der(&pk[1..]), Err(EncodingError::InvalidKey));
+  }
+
+  #[test]
+  fn private_key_round_trip()
+  {
+    let keys = [
+      PrivateKey::Seed([9u8; SEEDBYTES]),
+      PrivateKey::Expanded(sk()),
+      PrivateKey::Both([9u8; SEEDBYTES], sk()),
+    ];
+    for key in keys {
+      let der = private_key_to_der(&key).unwrap();
+      assert_eq!(private_key_from_der(&der).unwrap(), key);
+      let pem = private_key_to_pem(&key).unwrap();
+      assert_eq!(private_key_from_pem(&pem).unwrap(), key);
+    }
+
+    // seed-only form: version, algorithm, [0] seed
+    let der = private_key_to_der(&PrivateKey::Seed([9u8; SEEDBYTES])).unwrap();
+    let oid = ALGORITHM.oid();
+    let mut expected = vec![0x30, (43 + oid.len()) as u8, 0x02, 0x01, 0x00];
+    expected.extend([0x30, (2 + oid.len()) as u8, 0x06, oid.len() as u8]);
+    expected.extend(oid);
+    expected.extend([0x04, 34, 0x80, 32]);
+    expected.extend([9; SEEDBYTES]);
+    assert_eq!(der, expected);
+
+    let short = PrivateKey::Expanded(sk()[1..].to_vec());
+    assert_eq!(private_key_to_der(&short), Err(EncodingError::InvalidKey));
+  }
+
+  #[test]
+  fn rejects_other_algorithms()
+  {
+    for alg in Algorithm::ALL.into_iter().filter(|&a| a != ALGORITHM) {
+      let mut content = tlv(SEQUENCE, &tlv(OID, alg.oid()));
+      content.extend(tlv(BIT_STRING, &[&[0u8][..], &pk()].concat()));
+      assert_eq!(
+        public_key_from_der(&tlv(SEQUENCE, &content)),
+        Err(EncodingError::UnsupportedAlgorithm),
+        "{:?}",
+        alg
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_malformed_der()
+  {
+    let der = public_key_to_der(&pk()).unwrap();
+
+    let mut trailing = der.clone();
+    trailing.push(0);
+    assert_eq!(public_key_from_der(&trailing), Err(EncodingError::Der));
+    assert_eq!(
+      public_key_from_der(&der[..der.len() - 1]),
+      Err(EncodingError::Der)
+    );
+
+    // the same length in a longer, non-minimal form
+    let mut long = vec![0x30, 0x83, 0x00];
+    long.extend_from_slice(&der[2..]);
+    assert_eq!(public_key_from_der(&long), Err(EncodingError::Der));
+
+    // one unused bit in the BIT STRING
+    let mut unused = der.clone();
+    let pos = der.len() - PUBLICKEYBYTES - 1;
+    unused[pos] = 1;
+    assert_eq!(public_key_from_der(&unused), Err(EncodingError::Der));
+
+    let mut version = private_key_to_der(&PrivateKey::Seed([0; 32])).unwrap();
+    version[4] = 2;
+    assert_eq!(private_key_from_der(&version), Err(EncodingError::Der));
+  }
+
+  #[test]
+  fn accepts_version_2_fields()
+  {
+    // attributes and a public key are skipped
+    let mut content = tlv(INTEGER, &[1]);
+    content.extend(algorithm_identifier());
+    content.extend(tlv(OCTET_STRING, &tlv(SEED, &[3u8; SEEDBYTES])));
+    content.extend(tlv(ATTRIBUTES, &[]));
+    content.extend(tlv(PUBLIC_KEY, &[&[0u8][..], &pk()].concat()));
+    let der = tlv(SEQUENCE, &content);
+    assert_eq!(
+      private_key_from_der(&der).unwrap(),
+      PrivateKey::Seed([3u8; SEEDBYTES])
+    );
+  }
+}
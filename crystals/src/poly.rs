@@ -80,6 +80,18 @@ pub fn poly_invntt_tomont(a: &mut Poly) {
 /// by 2^{-32}. Output coefficients are less than 2*Q if input
 /// coefficient are less than 22*Q.
 pub fn poly_pointwise_montgomery(c: &mut Poly, a: &Poly, b: &Poly) {
+  #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+  if crate::avx2::enabled() {
+    // SAFETY: the CPU supports AVX2
+    return unsafe {
+      crate::avx2::pointwise_montgomery(&mut c.coeffs, &a.coeffs, &b.coeffs)
+    };
+  }
+  poly_pointwise_montgomery_portable(c, a, b)
+}
+
+/// Portable poly_pointwise_montgomery.
+pub fn poly_pointwise_montgomery_portable(c: &mut Poly, a: &Poly, b: &Poly) {
   for i in 0..N {
       c.coeffs[i] = montgomery_reduce((a.coeffs[i] as i64) * b.coeffs[i] as i64);
   }
@@ -134,21 +146,19 @@ pub fn poly_use_hint(b: &mut Poly, h: &Poly) {
 /// Assumes input coefficients to be standard representatives.
 /// Returns 0 if norm is strictly smaller than B and 1 otherwise.
 pub fn poly_chknorm(a: &Poly, b: i32) -> u8 {
-    // It is ok to leak which coefficient violates the bound since
-  // the probability for each coefficient is independent of secret
-  // data but we must not leak the sign of the centralized representative.
-  let mut t;
+  // Every coefficient is checked so that the time does not depend on
+  // where the bound is violated, and the sign of the centralized
+  // representative is never branched on.
   if b > (Q_I32 - 1) / 8 {
       return 1;
   }
+  let mut r = 0i32;
   for i in 0..N {
-      t = a.coeffs[i] >> 31;
-      t = a.coeffs[i] - (t & 2 * a.coeffs[i]);
-      if t >= b {
-          return 1;
-      }
+      let mut t = a.coeffs[i] >> 31;
+      t = a.coeffs[i] - (t & (2 * a.coeffs[i]));
+      r |= (b - 1 - t) >> 31;
   }
-  return 0;
+  (r & 1) as u8
 }
 
 /// Sample uniformly random coefficients in [0, Q-1] by
@@ -156,6 +166,21 @@ pub fn poly_chknorm(a: &Poly, b: i32) -> u8 {
 /// Returns number of sampled coefficients. Can be smaller than len if not enough
 /// random bytes were given.
 pub fn rej_uniform(a: &mut [i32], len: u32, buf: &[u8], buflen: usize) -> u32 {
+  #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+  if crate::avx2::enabled() {
+    // SAFETY: the CPU supports AVX2
+    return unsafe { crate::avx2::rej_uniform(a, len, buf, buflen) };
+  }
+  rej_uniform_portable(a, len, buf, buflen)
+}
+
+/// Portable rej_uniform.
+pub fn rej_uniform_portable(
+  a: &mut [i32],
+  len: u32,
+  buf: &[u8],
+  buflen: usize,
+) -> u32 {
   let (mut ctr, mut pos) = (0usize, 0usize);
   let mut t;
   while ctr < len as usize && pos + 3 <= buflen {
@@ -198,37 +223,74 @@ pub fn poly_uniform(a: &mut Poly, seed: &[u8], nonce: u16) {
   }
 }
 
+// The serial sampler carries buflen % 3 leftover bytes into the next
+// block. Both buffer sizes are multiples of 3, so nothing is ever carried
+// and the four-way sampler can skip that step.
+const _: () = assert!(STREAM128_BLOCKBYTES % 3 == 0);
+const _: () =
+  assert!((POLY_UNIFORM_NBLOCKS * STREAM128_BLOCKBYTES) % 3 == 0);
+
+/// Splits four lane buffers into the form the four-way streams take.
+fn lanes<const M: usize>(buf: &mut [[u8; M]; 4]) -> [&mut [u8]; 4] {
+  let [b0, b1, b2, b3] = buf;
+  [b0, b1, b2, b3]
+}
+
+/// Four-way poly_uniform. a[l] receives exactly the coefficients
+/// poly_uniform(a[l], seed, nonce[l]) would produce.
+pub fn poly_uniform_4x(a: [&mut Poly; 4], seed: &[u8], nonce: [u16; 4]) {
+  let buflen = POLY_UNIFORM_NBLOCKS * STREAM128_BLOCKBYTES;
+  let mut buf = [[0u8; POLY_UNIFORM_NBLOCKS * STREAM128_BLOCKBYTES]; 4];
+  let mut state = Stream128Statex4::default();
+  stream128x4_init(&mut state, seed, nonce);
+  stream128x4_squeezeblocks(
+    lanes(&mut buf),
+    POLY_UNIFORM_NBLOCKS as u64,
+    &mut state,
+  );
+
+  let mut ctr = [0u32; 4];
+  for l in 0..4 {
+    ctr[l] = rej_uniform(&mut a[l].coeffs, N_U32, &buf[l], buflen);
+  }
+  while ctr.iter().any(|&c| c < N_U32) {
+    stream128x4_squeezeblocks(lanes(&mut buf), 1, &mut state);
+    for l in 0..4 {
+      ctr[l] += rej_uniform(
+        &mut a[l].coeffs[ctr[l] as usize..],
+        N_U32 - ctr[l],
+        &buf[l],
+        STREAM128_BLOCKBYTES,
+      );
+    }
+  }
+}
+
 #[cfg(feature = "offchain")]
-/// Sample uniformly random coefficients in [-ETA, ETA] by
-/// performing rejection sampling using array of random bytes.
+/// Sample uniformly random coefficients in [-ETA, ETA] by rejection
+/// sampling on the nibbles of buf. Every candidate is written and the
+/// counter advances by the acceptance bit, so the time does not depend
+/// on the values of the accepted coefficients.
+///
+/// Returns number of sampled coefficients.
 pub fn rej_eta(a: &mut [i32], len: usize, buf: &[u8], buflen: usize) -> u32 {
   let (mut ctr, mut pos) = (0usize, 0usize);
-  let (mut t0, mut t1);
   while ctr < len && pos < buflen {
-    t0 = (buf[pos] & 0x0F) as u32;
-    t1 = (buf[pos] >> 4) as u32;
+    let t = [(buf[pos] & 0x0F) as u32, (buf[pos] >> 4) as u32];
     pos += 1;
 
-    if ETA == 2 {
-      if t0 < 15 {
-        t0 = t0 - (205 * t0 >> 10) * 5;
-        a[ctr] = 2 - t0 as i32;
-        ctr += 1;
-      }
-      if t1 < 15 && ctr < len {
-        t1 = t1 - (205 * t1 >> 10) * 5;
-        a[ctr] = 2 - t1 as i32;
-        ctr += 1;
-      }
-    } else if ETA == 4 {
-      if t0 < 9 {
-        a[ctr] = 4 - t0 as i32;
-        ctr += 1;
-      }
-      if t1 < 9 && ctr < len {
-        a[ctr] = 4 - t1 as i32;
-        ctr += 1;
+    for t in t {
+      if ctr == len {
+        break;
       }
+      let (coeff, accept) = if ETA == 2 {
+        // t mod 5 for t < 15
+        (2 - (t - ((205 * t) >> 10) * 5) as i32, t.wrapping_sub(15) >> 31)
+      } else {
+        (4 - t as i32, t.wrapping_sub(9) >> 31)
+      };
+      a[ctr] = coeff;
+      ctr += accept as usize;
     }
   }
   ctr as u32
@@ -269,6 +331,36 @@ pub fn poly_uniform_eta(a: &mut Poly, seed: &[u8], nonce: u16) {
   }
 }
 
+/// Four-way poly_uniform_eta with identical output per lane.
+#[cfg(feature = "offchain")]
+pub fn poly_uniform_eta_4x(a: [&mut Poly; 4], seed: &[u8], nonce: [u16; 4]) {
+  let buflen = POLY_UNIFORM_ETA_NBLOCKS * STREAM256_BLOCKBYTES;
+  let mut buf = [[0u8; POLY_UNIFORM_ETA_NBLOCKS * STREAM256_BLOCKBYTES]; 4];
+  let mut state = Stream256Statex4::default();
+  stream256x4_init(&mut state, seed, nonce);
+  stream256x4_squeezeblocks(
+    lanes(&mut buf),
+    POLY_UNIFORM_ETA_NBLOCKS as u64,
+    &mut state,
+  );
+
+  let mut ctr = [0u32; 4];
+  for l in 0..4 {
+    ctr[l] = rej_eta(&mut a[l].coeffs, N, &buf[l], buflen);
+  }
+  while ctr.iter().any(|&c| c < N_U32) {
+    stream256x4_squeezeblocks(lanes(&mut buf), 1, &mut state);
+    for l in 0..4 {
+      ctr[l] += rej_eta(
+        &mut a[l].coeffs[ctr[l] as usize..],
+        N - ctr[l] as usize,
+        &buf[l],
+        STREAM256_BLOCKBYTES,
+      );
+    }
+  }
+}
+
 const POLY_UNIFORM_GAMMA1_NBLOCKS: usize =
   (POLYZ_PACKEDBYTES + STREAM256_BLOCKBYTES - 1) / STREAM256_BLOCKBYTES;
 
@@ -289,6 +381,26 @@ pub fn poly_uniform_gamma1(a: &mut Poly, seed: &[u8], nonce: u16) {
   polyz_unpack(a, &mut buf);
 }
 
+/// Four-way poly_uniform_gamma1 with identical output per lane.
+pub fn poly_uniform_gamma1_4x(
+  a: [&mut Poly; 4],
+  seed: &[u8],
+  nonce: [u16; 4],
+) {
+  let mut buf = [[0u8; POLY_UNIFORM_GAMMA1_NBLOCKS * STREAM256_BLOCKBYTES]; 4];
+  let mut state = Stream256Statex4::default();
+
+  stream256x4_init(&mut state, seed, nonce);
+  stream256x4_squeezeblocks(
+    lanes(&mut buf),
+    POLY_UNIFORM_GAMMA1_NBLOCKS as u64,
+    &mut state,
+  );
+  for (a, buf) in a.into_iter().zip(buf.iter()) {
+    polyz_unpack(a, buf);
+  }
+}
+
 /// Implementation of H. Samples polynomial with TAU nonzero
 /// coefficients in {-1,1} using the output stream of
 /// SHAKE256(seed).
@@ -607,3 +719,77 @@ pub fn polyw1_pack(r: &mut [u8], a: &Poly) {
   }
 }
 
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+  use core::ops::RangeInclusive;
+
+  fn coeffs(range: RangeInclusive<i32>) -> impl Strategy<Value = Vec<i32>> {
+    prop::collection::vec(range, N)
+  }
+
+  /// Packs then unpacks `a`, returning the decoded coefficients.
+  fn round_trip(
+    a: &[i32],
+    bytes: usize,
+    pack: fn(&mut [u8], &Poly),
+    unpack: fn(&mut Poly, &[u8]),
+  ) -> Vec<i32> {
+    let a = Poly { coeffs: a.into() };
+    let mut buf = vec![0u8; bytes];
+    pack(&mut buf, &a);
+    let mut r = Poly::default();
+    unpack(&mut r, &buf);
+    r.coeffs.into_vec()
+  }
+
+  proptest! {
+    #[cfg(feature = "offchain")]
+    #[test]
+    fn polyeta_round_trip(a in coeffs(-ETA_I32..=ETA_I32)) {
+      let r =
+        round_trip(&a, POLYETA_PACKEDBYTES, polyeta_pack, polyeta_unpack);
+      prop_assert_eq!(r, a);
+    }
+
+    #[cfg(feature = "offchain")]
+    #[test]
+    fn polyt1_round_trip(a in coeffs(0..=(1 << 10) - 1)) {
+      let r = round_trip(&a, POLYT1_PACKEDBYTES, polyt1_pack, polyt1_unpack);
+      prop_assert_eq!(r, a);
+    }
+
+    #[cfg(feature = "offchain")]
+    #[test]
+    fn polyt0_round_trip(a in coeffs(-(D_SHL - 1)..=D_SHL)) {
+      let r = round_trip(&a, POLYT0_PACKEDBYTES, polyt0_pack, polyt0_unpack);
+      prop_assert_eq!(r, a);
+    }
+
+    #[test]
+    fn polyz_round_trip(a in coeffs(-(GAMMA1_I32 - 1)..=GAMMA1_I32)) {
+      let r = round_trip(&a, POLYZ_PACKEDBYTES, polyz_pack, polyz_unpack);
+      prop_assert_eq!(r, a);
+    }
+
+    /// w1 has no unpacking routine, so the packed bytes are read back as
+    /// a little-endian stream of fixed-width fields.
+    #[test]
+    fn polyw1_round_trip(
+      a in coeffs(0..=(Q_I32 - 1) / (2 * GAMMA2_I32) - 1)
+    ) {
+      let bits = 8 * POLYW1_PACKEDBYTES / N;
+      let mut buf = vec![0u8; POLYW1_PACKEDBYTES];
+      polyw1_pack(&mut buf, &Poly { coeffs: a.clone().into() });
+      for (i, &c) in a.iter().enumerate() {
+        let field = (0..bits).fold(0, |acc, b| {
+          let bit = i * bits + b;
+          acc | (((buf[bit / 8] >> (bit % 8)) & 1) as i32) << b
+        });
+        prop_assert_eq!(field, c);
+      }
+    }
+  }
+}
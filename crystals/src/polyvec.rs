@@ -42,10 +42,46 @@ impl Default for Polyvecl {
 /// sampling on the output stream of SHAKE128(rho|j|i)
 /// or AES256CTR(rho,j|i).
 pub fn polyvec_matrix_expand(mat: &mut [Polyvecl], rho: &[u8]) {
-  for i in 0..K {
-      for j in 0..L {
-          poly_uniform(&mut mat[i].vec[j], rho, ((i << 8) + j) as u16);
+  let polys = mat[..K].iter_mut().flat_map(|row| row.vec[..L].iter_mut());
+  let nonce = |idx: usize| (((idx / L) << 8) + idx % L) as u16;
+  sample_4x(polys, rho, nonce, poly_uniform_4x, poly_uniform);
+}
+
+/// Samples polys four at a time with `fourway` and the remainder with
+/// `single`; the i-th poly is sampled with nonce(i). On BPF every poly
+/// goes through `single`, which keeps one Keccak state on the stack
+/// instead of four.
+fn sample_4x<'a>(
+  polys: impl Iterator<Item = &'a mut Poly>,
+  seed: &[u8],
+  nonce: impl Fn(usize) -> u16,
+  fourway: fn([&mut Poly; 4], &[u8], [u16; 4]),
+  single: fn(&mut Poly, &[u8], u16),
+) {
+  #[cfg(not(target_os = "solana"))]
+  {
+    let mut polys = polys.enumerate();
+    loop {
+      match [polys.next(), polys.next(), polys.next(), polys.next()] {
+        [Some((i, a0)), Some((_, a1)), Some((_, a2)), Some((_, a3))] => {
+          let nonces = [nonce(i), nonce(i + 1), nonce(i + 2), nonce(i + 3)];
+          fourway([a0, a1, a2, a3], seed, nonces);
+        }
+        rest => {
+          for (i, a) in rest.into_iter().flatten() {
+            single(a, seed, nonce(i));
+          }
+          break;
+        }
       }
+    }
+  }
+  #[cfg(target_os = "solana")]
+  {
+    let _ = fourway;
+    for (i, a) in polys.enumerate() {
+      single(a, seed, nonce(i));
+    }
   }
 }
 
@@ -58,17 +94,16 @@ pub fn polyvec_matrix_pointwise_montgomery(t: &mut Polyveck, mat: &[Polyvecl], v
 
 //*********** Vectors of polynomials of length L ****************************
 #[cfg(feature = "offchain")]
-pub fn polyvecl_uniform_eta(v: &mut Polyvecl, seed: &[u8], mut nonce: u16) {
-  for i in 0..L {
-    poly_uniform_eta(&mut v.vec[i], seed, nonce);
-    nonce += 1;
-  }
+pub fn polyvecl_uniform_eta(v: &mut Polyvecl, seed: &[u8], nonce: u16) {
+  let polys = v.vec[..L].iter_mut();
+  let nonce = |i: usize| nonce + i as u16;
+  sample_4x(polys, seed, nonce, poly_uniform_eta_4x, poly_uniform_eta);
 }
 
 pub fn polyvecl_uniform_gamma1(v: &mut Polyvecl, seed: &[u8], nonce: u16) {
-  for i in 0..L {
-    poly_uniform_gamma1(&mut v.vec[i], seed, L_U16 * nonce + i as u16);
-  }
+  let polys = v.vec[..L].iter_mut();
+  let nonce = |i: usize| L_U16 * nonce + i as u16;
+  sample_4x(polys, seed, nonce, poly_uniform_gamma1_4x, poly_uniform_gamma1);
 }
 pub fn polyvecl_reduce(v: &mut Polyvecl) {
   for i in 0..L {
@@ -126,25 +161,19 @@ pub fn polyvecl_pointwise_acc_montgomery(w: &mut Poly, u: &Polyvecl, v: &Polyvec
 /// Check infinity norm of polynomials in vector of length L.
 /// Assumes input coefficients to be standard representatives.
 /// Returns 0 if norm of all polynomials is strictly smaller than B and 1
-/// otherwise.
+/// otherwise. All polynomials are checked.
 pub fn polyvecl_chknorm(v: &Polyvecl, bound: i32) -> u8 {
-  for i in 0..L {
-      if poly_chknorm(&v.vec[i], bound) > 0 {
-          return 1;
-      }
-  }
-  return 0;
+  v.vec.iter().fold(0, |r, p| r | poly_chknorm(p, bound))
 }
 
 
 
 //*********** Vectors of polynomials of length K ****************************
 #[cfg(feature = "offchain")]
-pub fn polyveck_uniform_eta(v: &mut Polyveck, seed: &[u8], mut nonce: u16) {
-  for i in 0..K {
-    poly_uniform_eta(&mut v.vec[i], seed, nonce);
-    nonce += 1
-  }
+pub fn polyveck_uniform_eta(v: &mut Polyveck, seed: &[u8], nonce: u16) {
+  let polys = v.vec[..K].iter_mut();
+  let nonce = |i: usize| nonce + i as u16;
+  sample_4x(polys, seed, nonce, poly_uniform_eta_4x, poly_uniform_eta);
 }
 
 /// Reduce coefficients of polynomials in vector of length K
@@ -217,14 +246,9 @@ pub fn polyveck_pointwise_poly_montgomery(r: &mut Polyveck, a: &Poly, v: &Polyve
 /// Assumes input coefficients to be standard representatives.
 //
 /// Returns 0 if norm of all polynomials are strictly smaller than B and 1
-/// otherwise.
+/// otherwise. All polynomials are checked.
 pub fn polyveck_chknorm(v: &Polyveck, bound: i32) -> u8 {
-  for i in 0..K {
-    if poly_chknorm(&v.vec[i], bound) > 0 {
-      return 1;
-    }
-  }
-  return 0;
+  v.vec.iter().fold(0, |r, p| r | poly_chknorm(p, bound))
 }
 
 #[cfg(feature = "offchain")]
@@ -276,4 +300,91 @@ pub fn polyveck_pack_w1(r: &mut [u8], w1: &Polyveck) {
   for i in 0..K {
       polyw1_pack(&mut r[i * POLYW1_PACKEDBYTES..], &w1.vec[i]);
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn seed(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 13 + 5) as u8).collect()
+  }
+
+  #[test]
+  fn matrix_expand_matches_serial() {
+    let rho = seed(SEEDBYTES);
+    let mut mat = vec![Polyvecl::default(); K];
+    polyvec_matrix_expand(&mut mat, &rho);
+    for (i, row) in mat.iter().enumerate() {
+      for j in 0..L {
+        let mut expect = Poly::default();
+        poly_uniform(&mut expect, &rho, ((i << 8) + j) as u16);
+        assert_eq!(row.vec[j].coeffs, expect.coeffs);
+      }
+    }
+  }
+
+  #[test]
+  fn uniform_4x_matches_serial() {
+    // enough nonces that some lanes need extra blocks after the first
+    // squeeze while others are already done
+    let rho = seed(SEEDBYTES);
+    for base in (0..512u16).step_by(4) {
+      let nonce = [base, base + 1, base + 2, base + 3];
+      let mut polys: [Poly; 4] = Default::default();
+      let [a0, a1, a2, a3] = &mut polys;
+      poly_uniform_4x([a0, a1, a2, a3], &rho, nonce);
+      for l in 0..4 {
+        let mut expect = Poly::default();
+        poly_uniform(&mut expect, &rho, nonce[l]);
+        assert_eq!(polys[l].coeffs, expect.coeffs);
+      }
+    }
+  }
+
+  #[test]
+  fn uniform_gamma1_matches_serial() {
+    let rhoprime = seed(CRHBYTES);
+    for nonce in 0..8 {
+      let mut y = Polyvecl::default();
+      polyvecl_uniform_gamma1(&mut y, &rhoprime, nonce);
+      for i in 0..L {
+        let mut expect = Poly::default();
+        poly_uniform_gamma1(&mut expect, &rhoprime, L_U16 * nonce + i as u16);
+        assert_eq!(y.vec[i].coeffs, expect.coeffs);
+      }
+    }
+  }
+
+  #[cfg(feature = "offchain")]
+  #[test]
+  fn uniform_eta_matches_serial() {
+    let rhoprime = seed(CRHBYTES);
+    for base in (0..512u16).step_by(4) {
+      let nonce = [base, base + 1, base + 2, base + 3];
+      let mut polys: [Poly; 4] = Default::default();
+      let [a0, a1, a2, a3] = &mut polys;
+      poly_uniform_eta_4x([a0, a1, a2, a3], &rhoprime, nonce);
+      for l in 0..4 {
+        let mut expect = Poly::default();
+        poly_uniform_eta(&mut expect, &rhoprime, nonce[l]);
+        assert_eq!(polys[l].coeffs, expect.coeffs);
+      }
+    }
+
+    let mut s1 = Polyvecl::default();
+    let mut s2 = Polyveck::default();
+    polyvecl_uniform_eta(&mut s1, &rhoprime, 0);
+    polyveck_uniform_eta(&mut s2, &rhoprime, L_U16);
+    for i in 0..L {
+      let mut expect = Poly::default();
+      poly_uniform_eta(&mut expect, &rhoprime, i as u16);
+      assert_eq!(s1.vec[i].coeffs, expect.coeffs);
+    }
+    for i in 0..K {
+      let mut expect = Poly::default();
+      poly_uniform_eta(&mut expect, &rhoprime, L_U16 + i as u16);
+      assert_eq!(s2.vec[i].coeffs, expect.coeffs);
+    }
+  }
+}
@@ -28,3 +28,35 @@ pub fn reduce32(a: i32) -> i32 {
 pub fn caddq(a: i32) -> i32 {
   a + ((a >> 31) & Q as i32)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  const BOUND: i64 = (Q as i64) << 31;
+
+  proptest! {
+    #[test]
+    fn montgomery_reduce_bounds(a in -BOUND..=BOUND) {
+      let r = montgomery_reduce(a);
+      prop_assert!(-Q_I32 < r && r < Q_I32);
+      prop_assert_eq!(((r as i64) << 32).rem_euclid(Q as i64),
+        a.rem_euclid(Q as i64));
+    }
+
+    #[test]
+    fn reduce32_bounds(a in i32::MIN..=i32::MAX - (1 << 22)) {
+      let r = reduce32(a);
+      prop_assert!((-6283009..=6283007).contains(&r));
+      prop_assert_eq!((a as i64 - r as i64).rem_euclid(Q as i64), 0);
+    }
+
+    #[test]
+    fn caddq_bounds(a in -(Q_I32 - 1)..Q_I32) {
+      let r = caddq(a);
+      prop_assert!((0..Q_I32).contains(&r));
+      prop_assert_eq!((a - r).rem_euclid(Q_I32), 0);
+    }
+  }
+}
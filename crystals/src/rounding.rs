@@ -1,5 +1,10 @@
 use crate::params::*;
 
+/// 1 if a != 0 and 0 otherwise, without branching on a.
+fn nonzero(a: i32) -> i32 {
+  ((a | a.wrapping_neg()) as u32 >> 31) as i32
+}
+
 #[cfg(feature = "offchain")]
 /// For finite field element a, compute a0, a1 such that
 /// a mod^+ Q = a1*2^D + a0 with -2^{D-1} < a0 <= 2^{D-1}.
@@ -9,7 +14,7 @@ use crate::params::*;
 pub fn power2round(a: i32, a0: &mut i32) -> i32 {
   let a1 = (a + (1 << (D - 1)) - 1) >> D;
   *a0 = a - (a1 << D);
-  return a1;
+  a1
 }
 
 /// For finite field element a, compute high and low bits a0, a1 such
@@ -18,6 +23,8 @@ pub fn power2round(a: i32, a0: &mut i32) -> i32 {
 /// -ALPHA/2 <= a0 = a mod^+ Q - Q < 0. Assumes a to be standard
 /// representative.
 ///
+/// Constant time in a; the GAMMA2 tests are on compile-time constants.
+///
 /// Returns a1.
 pub fn decompose(a0: &mut i32, a: i32) -> i32 {
   let mut a1 = (a + 127) >> 7;
@@ -34,45 +41,107 @@ pub fn decompose(a0: &mut i32, a: i32) -> i32 {
 }
 
 /// Compute hint bit indicating whether the low bits of the
-/// input element overflow into the high bits.
+/// input element overflow into the high bits. Constant time in a0
+/// and a1, which are derived from the secret key when signing.
 ///
 /// Returns 1 if overflow.
 pub fn make_hint(a0: i32, a1: i32) -> u8 {
-  if a0 > GAMMA2_I32 || a0 < -GAMMA2_I32 || (a0 == -GAMMA2_I32 && a1 != 0) {
-    return 1;
-  }
-  return 0;
+  let above = ((GAMMA2_I32 - a0) as u32 >> 31) as i32;
+  let below = ((a0 + GAMMA2_I32) as u32 >> 31) as i32;
+  let at_bound = 1 ^ nonzero(a0 + GAMMA2_I32);
+  (above | below | (at_bound & nonzero(a1))) as u8
 }
 
-/// Correct high bits according to hint.
+/// Correct high bits according to hint. Constant time in a and hint.
 ///
 /// Returns corrected high bits.
 pub fn use_hint(a: i32, hint: u8) -> i32 {
   let mut a0 = 0i32;
   let a1 = decompose(&mut a0, a);
-  if hint == 0 {
-    return a1;
-  }
+  // +1 if a0 > 0, -1 otherwise, and 0 without a hint
+  let positive = (a0.wrapping_neg() as u32 >> 31) as i32;
+  let r = a1 + (hint as i32 & 1) * (2 * positive - 1);
 
   if GAMMA2 == (Q - 1) / 32 {
-    if a0 > 0 {
-      return (a1 + 1) & 15;
-    } else {
-      return (a1 - 1) & 15;
-    }
+    r & 15
   } else {
-    if a0 > 0 {
-      if a1 == 43 {
-        return 0;
-      } else {
-        return a1 + 1;
-      };
-    } else {
-      if a1 == 0 {
-        return 43;
-      } else {
-        return a1 - 1;
-      }
+    // wrap -1 to 43 and 44 to 0
+    let r = r + (44 & (r >> 31));
+    r - (44 & ((43 - r) >> 31))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  /// The branching definition of make_hint.
+  fn make_hint_spec(a0: i32, a1: i32) -> u8 {
+    let outside = !(-GAMMA2_I32..=GAMMA2_I32).contains(&a0);
+    (outside || (a0 == -GAMMA2_I32 && a1 != 0)) as u8
+  }
+
+  /// The branching definition of use_hint.
+  fn use_hint_spec(a: i32, hint: u8) -> i32 {
+    let m = (Q_I32 - 1) / (2 * GAMMA2_I32);
+    let mut a0 = 0;
+    let a1 = decompose(&mut a0, a);
+    match (hint, a0 > 0) {
+      (0, _) => a1,
+      (_, true) => (a1 + 1) % m,
+      (_, false) => (a1 - 1 + m) % m,
+    }
+  }
+
+  proptest! {
+    #[cfg(feature = "offchain")]
+    #[test]
+    fn power2round_reconstructs(a in 0..Q_I32) {
+      let mut a0 = 0;
+      let a1 = power2round(a, &mut a0);
+      prop_assert_eq!((a1 << D) + a0, a);
+      prop_assert!(-(1 << (D - 1)) < a0 && a0 <= 1 << (D - 1));
+      prop_assert!((0..1 << 10).contains(&a1));
+    }
+
+    #[test]
+    fn decompose_reconstructs(a in 0..Q_I32) {
+      let mut a0 = 0;
+      let a1 = decompose(&mut a0, a);
+      prop_assert!((-GAMMA2_I32..=GAMMA2_I32).contains(&a0));
+      prop_assert!((0..(Q_I32 - 1) / (2 * GAMMA2_I32)).contains(&a1));
+      prop_assert_eq!((a1 * 2 * GAMMA2_I32 + a0 - a).rem_euclid(Q_I32), 0);
+    }
+
+    /// Mirrors signing: the signer hints the high bits of v from the
+    /// low bits of v shifted by t, the verifier sees only v + t. The
+    /// ranges are those enforced by the rejection checks in signing.
+    #[test]
+    fn use_hint_recovers_high_bits(
+      v in 0..Q_I32,
+      t in -(GAMMA2_I32 - 1)..GAMMA2_I32,
+    ) {
+      let mut v0 = 0;
+      let v1 = decompose(&mut v0, v);
+      prop_assume!(v0.abs() < GAMMA2_I32 - BETA_I32);
+      let hint = make_hint(v0 + t, v1);
+      prop_assert_eq!(use_hint((v + t).rem_euclid(Q_I32), hint), v1);
+    }
+
+    #[test]
+    fn make_hint_matches_definition(
+      a0 in -2 * GAMMA2_I32..=2 * GAMMA2_I32,
+      a1 in 0..(Q_I32 - 1) / (2 * GAMMA2_I32),
+    ) {
+      prop_assert_eq!(make_hint(a0, a1), make_hint_spec(a0, a1));
+      let edge = -GAMMA2_I32;
+      prop_assert_eq!(make_hint(edge, a1), make_hint_spec(edge, a1));
+    }
+
+    #[test]
+    fn use_hint_matches_definition(a in 0..Q_I32, hint in 0..=1u8) {
+      prop_assert_eq!(use_hint(a, hint), use_hint_spec(a, hint));
     }
   }
 }
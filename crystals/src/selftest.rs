@@ -0,0 +1,274 @@
+//! Power-on self tests and the module error state.
+//!
+//! The first key generation, signature or verification runs known-answer
+//! tests of SHAKE, the NTT and a fixed Dilithium key pair and signature.
+//! If any of them fails, or a freshly generated or derived key pair fails
+//! its pairwise consistency test, the module enters an error state. After
+//! that the Dilithium operations of the crate produce no output:
+//!
+//! - `verify`, `open`, `Keypair::sign_checked`, the `try_` constructors,
+//!   `restore` and `from_private_key` return a [`SelfTestError`], on its
+//!   own or inside their error type;
+//! - `Keypair::generate`, `from_seed` and `sign` panic.
+//!
+//! The C API, Python and wasm bindings use the fallible forms. SLH-DSA,
+//! the stateful schemes and the hybrid KEM are not covered.
+
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  OnceLock,
+};
+
+use crate::{
+  fips202::shake256,
+  ntt::{invntt_tomont, ntt},
+  params::*,
+  reduce::reduce32,
+  sha3::{Shake128, Shake256},
+  sign::{crypto_sign_keypair, crypto_sign_signature, crypto_sign_verify},
+  SelfTestError,
+};
+
+/// SHAKE128 and SHAKE256 of the bytes 0..200, 32 bytes of output each.
+const SHAKE128_KAT: [u8; 32] = [
+  0x0c, 0x42, 0x34, 0xca, 0x1e, 0x31, 0x80, 0x1a, 0xe6, 0x06, 0xf8, 0xb8, 0xd8,
+  0xe0, 0x66, 0x5c, 0x66, 0xf4, 0x2a, 0x21, 0xd6, 0x01, 0xc2, 0x68, 0x18, 0x58,
+  0xa9, 0x2c, 0x79, 0xad, 0x5d, 0x69,
+];
+const SHAKE256_KAT: [u8; 32] = [
+  0x4e, 0xe1, 0xca, 0x03, 0x27, 0x2b, 0x05, 0xd3, 0xbf, 0xb1, 0xe1, 0xc7, 0x9a,
+  0x96, 0x7f, 0x82, 0x3b, 0x9f, 0xc5, 0xe4, 0xbb, 0x39, 0x87, 0xb1, 0xba, 0x9e,
+  0x9c, 0xb5, 0xaf, 0xb0, 0x7a, 0x5e,
+];
+
+/// SHAKE256 of the little-endian coefficients of ntt(a) and of
+/// invntt_tomont(reduce32(ntt(a))), for a[i] = i * 31337 mod Q.
+const NTT_KAT: [u8; 32] = [
+  0x34, 0x74, 0x53, 0x43, 0x25, 0x76, 0x82, 0x6b, 0x9c, 0x5c, 0x5a, 0x27, 0xe2,
+  0x20, 0xca, 0xdb, 0x92, 0x8d, 0x6c, 0x23, 0x90, 0x28, 0x15, 0xc6, 0xbe, 0x70,
+  0x51, 0x38, 0x4e, 0xf1, 0xb3, 0x28,
+];
+const INVNTT_KAT: [u8; 32] = [
+  0x85, 0xcd, 0x10, 0x93, 0xc7, 0x18, 0x69, 0xf5, 0x13, 0x81, 0xe2, 0x0e, 0xaa,
+  0xd8, 0x89, 0x84, 0x36, 0x96, 0x7a, 0xbd, 0x0d, 0x29, 0xa4, 0xb2, 0x5e, 0x11,
+  0x79, 0x92, 0x72, 0x03, 0xc9, 0xa5,
+];
+
+/// SHAKE256(pk || sk || sig) for the key pair generated from
+/// SIGN_KAT_SEED and the signature of SIGN_KAT_MSG.
+const SIGN_KAT_SEED: [u8; SEEDBYTES] = [0x5a; SEEDBYTES];
+const SIGN_KAT_MSG: &[u8] = b"crystals self test";
+#[cfg(feature = "mode2")]
+const SIGN_KAT: [u8; 32] = [
+  0x09, 0x32, 0xd9, 0x5d, 0x96, 0x92, 0xa6, 0x64, 0xce, 0xbd, 0xf2, 0xd5, 0xb4,
+  0x69, 0x32, 0x48, 0x5a, 0x51, 0x85, 0x81, 0xcf, 0xb7, 0x8d, 0xe4, 0xb7, 0x4c,
+  0x40, 0x9c, 0x6b, 0xe1, 0xde, 0x10,
+];
+#[cfg(not(any(feature = "mode2", feature = "mode5")))]
+const SIGN_KAT: [u8; 32] = [
+  0xb6, 0xb8, 0xc3, 0x0f, 0x13, 0xb8, 0x7e, 0x82, 0x40, 0x74, 0x5b, 0x5b, 0xed,
+  0xd5, 0xef, 0xf5, 0xfa, 0x9d, 0x4e, 0x3c, 0x52, 0x93, 0xc2, 0x1b, 0xac, 0xcb,
+  0xf2, 0x69, 0xa6, 0xb3, 0xb0, 0xbe,
+];
+#[cfg(feature = "mode5")]
+const SIGN_KAT: [u8; 32] = [
+  0xcf, 0x0d, 0x80, 0x1a, 0xd7, 0xd0, 0x5b, 0xc0, 0x76, 0xec, 0x06, 0x15, 0xed,
+  0x2d, 0x51, 0x91, 0x0e, 0x42, 0x48, 0xd4, 0xdc, 0x10, 0x0e, 0x73, 0xf7, 0x89,
+  0xd8, 0x91, 0xbc, 0x30, 0xe0, 0xf0,
+];
+
+struct State
+{
+  power_on: OnceLock<Result<(), SelfTestError>>,
+  error: AtomicBool,
+}
+
+impl State
+{
+  const fn new() -> Self
+  {
+    State {
+      power_on: OnceLock::new(),
+      error: AtomicBool::new(false),
+    }
+  }
+
+  /// Runs the power-on tests on first use, then reports their result or
+  /// the error state entered since.
+  fn status(
+    &self,
+    power_on: impl FnOnce() -> Result<(), SelfTestError>,
+  ) -> Result<(), SelfTestError>
+  {
+    (*self.power_on.get_or_init(power_on))?;
+    match self.error.load(Ordering::SeqCst) {
+      true => Err(SelfTestError::ErrorState),
+      false => Ok(()),
+    }
+  }
+
+  fn fail(&self)
+  {
+    self.error.store(true, Ordering::SeqCst);
+  }
+}
+
+static STATE: State = State::new();
+
+/// Ok if the power-on self tests passed and the module has not entered
+/// the error state since. Runs the power-on self tests if they have not
+/// run yet.
+pub fn status() -> Result<(), SelfTestError>
+{
+  STATE.status(run_all)
+}
+
+/// Runs all known-answer tests on demand. A failure puts the module in
+/// the error state.
+pub fn run() -> Result<(), SelfTestError>
+{
+  status()?;
+  run_all().inspect_err(|_| STATE.fail())
+}
+
+/// Signs a fixed message with a new key pair and verifies the result.
+/// A failure puts the module in the error state.
+pub(crate) fn pairwise_consistency(
+  pk: &[u8],
+  sk: &[u8],
+) -> Result<(), SelfTestError>
+{
+  check_pair(&STATE, pk, sk)
+}
+
+fn check_pair(state: &State, pk: &[u8], sk: &[u8])
+  -> Result<(), SelfTestError>
+{
+  let mut sig = vec![0u8; SIGNBYTES];
+  crypto_sign_signature(&mut sig, SIGN_KAT_MSG, sk);
+  crypto_sign_verify(&sig, SIGN_KAT_MSG, pk).map_err(|_| {
+    state.fail();
+    SelfTestError::PairwiseConsistency
+  })
+}
+
+fn run_all() -> Result<(), SelfTestError>
+{
+  shake_kat()?;
+  ntt_kat()?;
+  sign_kat()
+}
+
+fn shake_kat() -> Result<(), SelfTestError>
+{
+  let input: Vec<u8> = (0..200).collect();
+  let (mut out128, mut out256) = ([0u8; 32], [0u8; 32]);
+
+  let mut h = Shake128::new();
+  h.update(&input);
+  h.finalize_xof().read(&mut out128);
+  let mut h = Shake256::new();
+  h.update(&input);
+  h.finalize_xof().read(&mut out256);
+
+  match out128 == SHAKE128_KAT && out256 == SHAKE256_KAT {
+    true => Ok(()),
+    false => Err(SelfTestError::Shake),
+  }
+}
+
+fn digest_coeffs(a: &[i32]) -> [u8; 32]
+{
+  let bytes: Vec<u8> = a.iter().flat_map(|c| c.to_le_bytes()).collect();
+  let mut out = [0u8; 32];
+  shake256(&mut out, 32, &bytes, bytes.len());
+  out
+}
+
+fn ntt_kat() -> Result<(), SelfTestError>
+{
+  let mut a: Vec<i32> = (0..N)
+    .map(|i| (i as i64 * 31337 % Q as i64) as i32)
+    .collect();
+  ntt(&mut a);
+  let forward = digest_coeffs(&a);
+  for c in a.iter_mut() {
+    *c = reduce32(*c);
+  }
+  invntt_tomont(&mut a);
+
+  match forward == NTT_KAT && digest_coeffs(&a) == INVNTT_KAT {
+    true => Ok(()),
+    false => Err(SelfTestError::Ntt),
+  }
+}
+
+fn sign_kat() -> Result<(), SelfTestError>
+{
+  let mut buf = vec![0u8; PUBLICKEYBYTES + SECRETKEYBYTES + SIGNBYTES];
+  let (pk, rest) = buf.split_at_mut(PUBLICKEYBYTES);
+  let (sk, sig) = rest.split_at_mut(SECRETKEYBYTES);
+  crypto_sign_keypair(pk, sk, Some(&SIGN_KAT_SEED));
+  crypto_sign_signature(sig, SIGN_KAT_MSG, sk);
+
+  let verified = crypto_sign_verify(sig, SIGN_KAT_MSG, pk).is_ok();
+  sig[0] ^= 1;
+  let rejected = crypto_sign_verify(sig, SIGN_KAT_MSG, pk).is_err();
+  sig[0] ^= 1;
+
+  let mut digest = [0u8; 32];
+  shake256(&mut digest, 32, &buf, buf.len());
+  match digest == SIGN_KAT && verified && rejected {
+    true => Ok(()),
+    false => Err(SelfTestError::SignVerify),
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn known_answers()
+  {
+    assert_eq!(shake_kat(), Ok(()));
+    assert_eq!(ntt_kat(), Ok(()));
+    assert_eq!(sign_kat(), Ok(()));
+    assert_eq!(run(), Ok(()));
+  }
+
+  #[test]
+  fn error_state()
+  {
+    let state = State::new();
+    assert_eq!(state.status(|| Ok(())), Ok(()));
+    state.fail();
+    assert_eq!(state.status(|| Ok(())), Err(SelfTestError::ErrorState));
+
+    let state = State::new();
+    let failing = || Err(SelfTestError::Ntt);
+    assert_eq!(state.status(failing), Err(SelfTestError::Ntt));
+    // power-on tests run once, their failure is permanent
+    assert_eq!(state.status(|| Ok(())), Err(SelfTestError::Ntt));
+  }
+
+  #[test]
+  fn pairwise_consistency()
+  {
+    let mut pk = vec![0u8; PUBLICKEYBYTES];
+    let mut sk = vec![0u8; SECRETKEYBYTES];
+    crypto_sign_keypair(&mut pk, &mut sk, Some(&SIGN_KAT_SEED));
+
+    let state = State::new();
+    assert_eq!(check_pair(&state, &pk, &sk), Ok(()));
+    assert_eq!(state.status(|| Ok(())), Ok(()));
+
+    pk[SEEDBYTES] ^= 1;
+    assert_eq!(
+      check_pair(&state, &pk, &sk),
+      Err(SelfTestError::PairwiseConsistency)
+    );
+    assert_eq!(state.status(|| Ok(())), Err(SelfTestError::ErrorState));
+  }
+}
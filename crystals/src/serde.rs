@@ -0,0 +1,354 @@
+//! serde support for keys and signatures.
+//!
+//! Keys and signatures are written as lowercase hex strings in
+//! human-readable formats such as JSON, and as raw byte strings in binary
+//! formats such as CBOR or bincode. Deserializing checks the length.
+//!
+//! [`Keypair`] implements `Serialize` and `Deserialize` directly. Public
+//! keys and signatures are plain arrays, so fields holding them name one
+//! of the modules below:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Release
+//! {
+//!   #[serde(with = "crystals::serde::public_key")]
+//!   signer: [u8; crystals::PUBLICKEYBYTES],
+//!   #[serde(with = "crystals::serde::signature")]
+//!   signature: [u8; crystals::SIGNBYTES],
+//! }
+//! ```
+
+use core::fmt;
+
+use ::serde::{
+  de::{self, SeqAccess, Visitor},
+  ser::SerializeStruct,
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+  params::{PUBLICKEYBYTES, SECRETKEYBYTES},
+  pkcs8::PrivateKey,
+  Keypair, KeypairError,
+};
+
+/// `#[serde(with = "crystals::serde::public_key")]` for
+/// `[u8; PUBLICKEYBYTES]`
+pub mod public_key
+{
+  use super::*;
+
+  pub fn serialize<S: Serializer>(
+    pk: &[u8; PUBLICKEYBYTES],
+    serializer: S,
+  ) -> Result<S::Ok, S::Error>
+  {
+    Bytes(pk).serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<[u8; PUBLICKEYBYTES], D::Error>
+  {
+    deserialize_array(deserializer)
+  }
+}
+
+/// `#[serde(with = "crystals::serde::signature")]` for
+/// `[u8; SIGNBYTES]`
+pub mod signature
+{
+  use super::*;
+  use crate::params::SIGNBYTES;
+
+  pub fn serialize<S: Serializer>(
+    sig: &[u8; SIGNBYTES],
+    serializer: S,
+  ) -> Result<S::Ok, S::Error>
+  {
+    Bytes(sig).serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<[u8; SIGNBYTES], D::Error>
+  {
+    deserialize_array(deserializer)
+  }
+}
+
+/// Serialized as `{ "public": .., "secret": .. }`
+impl Serialize for Keypair
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  {
+    let mut state = serializer.serialize_struct("Keypair", 2)?;
+    state.serialize_field("public", &Bytes(self.public()))?;
+    state.serialize_field("secret", &Bytes(self.expose_secret()))?;
+    state.end()
+  }
+}
+
+impl<'de> Deserialize<'de> for Keypair
+{
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error>
+  {
+    #[derive(Deserialize)]
+    #[serde(rename = "Keypair", deny_unknown_fields)]
+    struct Fields
+    {
+      #[serde(deserialize_with = "deserialize_array::<_, PUBLICKEYBYTES>")]
+      public: [u8; PUBLICKEYBYTES],
+      #[serde(deserialize_with = "deserialize_array::<_, SECRETKEYBYTES>")]
+      secret: [u8; SECRETKEYBYTES],
+    }
+
+    // the public key is recomputed from the secret key, not trusted
+    let fields = Fields::deserialize(deserializer)?;
+    let key = PrivateKey::Expanded(fields.secret.to_vec());
+    let keypair = Keypair::from_private_key(&key).map_err(|err| match err {
+      KeypairError::SelfTest(err) => {
+        de::Error::custom(format_args!("self test failed: {:?}", err))
+      }
+      _ => de::Error::custom("inconsistent secret key"),
+    })?;
+    match *keypair.public() == fields.public {
+      true => Ok(keypair),
+      false => Err(de::Error::custom(
+        "secret key does not match its public key",
+      )),
+    }
+  }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_>
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  {
+    match serializer.is_human_readable() {
+      true => serializer.serialize_str(&hex(self.0)),
+      false => serializer.serialize_bytes(self.0),
+    }
+  }
+}
+
+fn deserialize_array<'de, D: Deserializer<'de>, const LEN: usize>(
+  deserializer: D,
+) -> Result<[u8; LEN], D::Error>
+{
+  let bytes = match deserializer.is_human_readable() {
+    true => deserializer.deserialize_str(BytesVisitor(LEN))?,
+    // byte_buf: some formats only borrow small byte strings
+    false => deserializer.deserialize_byte_buf(BytesVisitor(LEN))?,
+  };
+  Ok(bytes.try_into().expect("length checked by the visitor"))
+}
+
+/// Accepts hex strings, byte strings and sequences of exactly `.0` bytes
+struct BytesVisitor(usize);
+
+impl BytesVisitor
+{
+  fn check<E: de::Error>(&self, bytes: Vec<u8>) -> Result<Vec<u8>, E>
+  {
+    match bytes.len() == self.0 {
+      true => Ok(bytes),
+      false => Err(E::invalid_length(bytes.len(), self)),
+    }
+  }
+}
+
+impl<'de> Visitor<'de> for BytesVisitor
+{
+  type Value = Vec<u8>;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "{} bytes or {} hex digits", self.0, 2 * self.0)
+  }
+
+  fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E>
+  {
+    if s.len() != 2 * self.0 {
+      return Err(E::invalid_length(s.len(), &self));
+    }
+    let bytes = unhex(s)
+      .ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))?;
+    self.check(bytes)
+  }
+
+  fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E>
+  {
+    self.check(bytes.to_vec())
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(
+    self,
+    mut seq: A,
+  ) -> Result<Self::Value, A::Error>
+  {
+    let mut bytes = Vec::with_capacity(self.0);
+    while let Some(byte) = seq.next_element()? {
+      if bytes.len() == self.0 {
+        return Err(de::Error::invalid_length(bytes.len() + 1, &self));
+      }
+      bytes.push(byte);
+    }
+    self.check(bytes)
+  }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String
+{
+  const DIGITS: &[u8; 16] = b"0123456789abcdef";
+  let mut out = String::with_capacity(2 * bytes.len());
+  for b in bytes {
+    out.push(DIGITS[(b >> 4) as usize] as char);
+    out.push(DIGITS[(b & 15) as usize] as char);
+  }
+  out
+}
+
+pub(crate) fn unhex(s: &str) -> Option<Vec<u8>>
+{
+  let digit = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+  s.as_bytes()
+    .chunks(2)
+    .map(|pair| match pair {
+      [hi, lo] => Some(digit(*hi)? << 4 | digit(*lo)?),
+      _ => None,
+    })
+    .collect()
+}
+
+#[cfg(all(test, feature = "offchain"))]
+mod tests
+{
+  use ::serde::{Deserialize, Serialize};
+
+  use crate::{Keypair, PUBLICKEYBYTES, SEEDBYTES, SIGNBYTES};
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct Release
+  {
+    #[serde(with = "crate::serde::public_key")]
+    signer: [u8; PUBLICKEYBYTES],
+    #[serde(with = "crate::serde::signature")]
+    signature: [u8; SIGNBYTES],
+  }
+
+  fn release() -> Release
+  {
+    let keypair = Keypair::from_seed(&[3; SEEDBYTES]);
+    Release {
+      signer: keypair.public().try_into().unwrap(),
+      signature: keypair.sign(b"release"),
+    }
+  }
+
+  fn to_cbor<T: Serialize>(value: &T) -> Vec<u8>
+  {
+    let mut out = vec![];
+    ciborium::ser::into_writer(value, &mut out).unwrap();
+    out
+  }
+
+  #[test]
+  fn json_uses_hex()
+  {
+    let release = release();
+    let json = serde_json::to_value(&release).unwrap();
+    let signer = json["signer"].as_str().unwrap().to_string();
+    assert_eq!(signer.len(), 2 * PUBLICKEYBYTES);
+    assert!(signer.bytes().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(
+      &signer[..4],
+      format!("{:02x}{:02x}", release.signer[0], release.signer[1])
+    );
+    assert_eq!(serde_json::from_value::<Release>(json).unwrap(), release);
+
+    let upper = serde_json::json!({
+      "signer": signer.to_uppercase(),
+      "signature": hex::encode(release.signature),
+    });
+    assert_eq!(serde_json::from_value::<Release>(upper).unwrap(), release);
+  }
+
+  #[test]
+  fn cbor_uses_bytes()
+  {
+    let release = release();
+    let cbor = to_cbor(&release);
+    // map of two entries, the first a byte string with a 2-byte length
+    assert_eq!(cbor[0], 0xA2);
+    let at = 1 + 1 + "signer".len();
+    assert_eq!(cbor[at], 0x59);
+    assert_eq!(&cbor[at + 3..at + 3 + PUBLICKEYBYTES], release.signer);
+    let decoded: Release = ciborium::de::from_reader(&cbor[..]).unwrap();
+    assert_eq!(decoded, release);
+  }
+
+  #[test]
+  fn rejects_wrong_lengths()
+  {
+    let release = release();
+    let mut json = serde_json::to_value(&release).unwrap();
+    json["signature"] = hex::encode(&release.signature[1..]).into();
+    let err = serde_json::from_value::<Release>(json.clone()).unwrap_err();
+    assert!(err.to_string().contains("invalid length"), "{}", err);
+    json["signature"] =
+      format!("{}zz", hex::encode(&release.signature[1..])).into();
+    assert!(serde_json::from_value::<Release>(json).is_err());
+
+    use ciborium::value::Value;
+    let short = Value::Map(vec![
+      ("signer".into(), Value::Bytes(release.signer[1..].to_vec())),
+      ("signature".into(), Value::Bytes(release.signature.to_vec())),
+    ]);
+    let cbor = to_cbor(&short);
+    assert!(ciborium::de::from_reader::<Release, _>(&cbor[..]).is_err());
+  }
+
+  #[test]
+  fn keypair()
+  {
+    let keypair = Keypair::from_seed(&[4; SEEDBYTES]);
+    let json = serde_json::to_string(&keypair).unwrap();
+    assert!(json.starts_with("{\"public\":\""));
+    assert_eq!(serde_json::from_str::<Keypair>(&json).unwrap(), keypair);
+    let decoded: Keypair =
+      ciborium::de::from_reader(&to_cbor(&keypair)[..]).unwrap();
+    assert_eq!(decoded, keypair);
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["secret"] = "00".into();
+    assert!(serde_json::from_value::<Keypair>(value.clone()).is_err());
+    value["secret"] = serde_json::to_value(&keypair).unwrap()["secret"].clone();
+    value["extra"] = true.into();
+    assert!(serde_json::from_value::<Keypair>(value).is_err());
+  }
+
+  #[test]
+  fn keypair_must_match()
+  {
+    let keypair = Keypair::from_seed(&[4; SEEDBYTES]);
+    let other = Keypair::from_seed(&[5; SEEDBYTES]);
+    let mut value = serde_json::to_value(&keypair).unwrap();
+    value["public"] = serde_json::to_value(&other).unwrap()["public"].clone();
+    let err = serde_json::from_value::<Keypair>(value).unwrap_err();
+    assert!(err.to_string().contains("does not match"), "{}", err);
+
+    // an expanded key whose stored tr does not belong to its t0
+    let mut value = serde_json::to_value(&keypair).unwrap();
+    let mut secret = keypair.expose_secret().to_vec();
+    secret[2 * SEEDBYTES] ^= 1;
+    value["secret"] = hex::encode(secret).into();
+    let err = serde_json::from_value::<Keypair>(value).unwrap_err();
+    assert!(err.to_string().contains("inconsistent"), "{}", err);
+  }
+}
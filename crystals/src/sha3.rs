@@ -0,0 +1,458 @@
+//! Incremental SHA-3 (FIPS 202), Keccak-256, cSHAKE and KMAC
+//! (SP 800-185) built on the Keccak-f[1600] permutation in `fips202`.
+//!
+//! Fixed-output hashers are used as `update` then `finalize`, XOFs as
+//! `update` then `finalize_xof` followed by any number of `read` calls.
+
+use crate::fips202::*;
+
+const SHA3_PAD: u8 = 0x06;
+const SHAKE_PAD: u8 = 0x1f;
+const CSHAKE_PAD: u8 = 0x04;
+const KECCAK_PAD: u8 = 0x01;
+
+/// Sponge in the absorbing phase
+#[derive(Clone)]
+struct Sponge
+{
+  state: KeccakState,
+  rate: usize,
+  pad: u8,
+}
+
+impl Sponge
+{
+  fn new(rate: usize, pad: u8) -> Sponge
+  {
+    Sponge {
+      state: KeccakState::default(),
+      rate,
+      pad,
+    }
+  }
+
+  fn absorb(&mut self, data: &[u8])
+  {
+    keccak_absorb(&mut self.state, self.rate, data, data.len());
+  }
+
+  fn finalize(mut self) -> XofReader
+  {
+    keccak_finalize(&mut self.state.s, self.state.pos, self.rate, self.pad);
+    self.state.pos = self.rate;
+    XofReader {
+      state: self.state,
+      rate: self.rate,
+    }
+  }
+}
+
+/// Squeezing phase of an extendable-output function
+#[derive(Clone)]
+pub struct XofReader
+{
+  state: KeccakState,
+  rate: usize,
+}
+
+impl XofReader
+{
+  /// Fills `out` with the next bytes of output
+  pub fn read(&mut self, out: &mut [u8])
+  {
+    self.state.pos = keccak_squeeze(
+      out,
+      out.len(),
+      &mut self.state.s,
+      self.state.pos,
+      self.rate,
+    );
+  }
+}
+
+macro_rules! fixed_hasher {
+  ($name:ident, $doc:literal, $rate:expr, $pad:expr, $len:literal) => {
+    #[doc = $doc]
+    #[derive(Clone)]
+    pub struct $name
+    {
+      sponge: Sponge,
+    }
+
+    impl Default for $name
+    {
+      fn default() -> Self
+      {
+        Self::new()
+      }
+    }
+
+    impl $name
+    {
+      pub const OUTPUT_BYTES: usize = $len;
+
+      pub fn new() -> Self
+      {
+        $name {
+          sponge: Sponge::new($rate, $pad),
+        }
+      }
+
+      pub fn update(&mut self, data: &[u8])
+      {
+        self.sponge.absorb(data);
+      }
+
+      pub fn finalize(self) -> [u8; $len]
+      {
+        let mut out = [0u8; $len];
+        self.sponge.finalize().read(&mut out);
+        out
+      }
+
+      /// One-shot hash of `data`
+      pub fn digest(data: &[u8]) -> [u8; $len]
+      {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+      }
+    }
+  };
+}
+
+fixed_hasher!(Sha3_256, "SHA3-256", SHA3_256_RATE, SHA3_PAD, 32);
+fixed_hasher!(Sha3_512, "SHA3-512", SHA3_512_RATE, SHA3_PAD, 64);
+fixed_hasher!(
+  Keccak256,
+  "Keccak-256 with the original padding, as used by Ethereum",
+  SHAKE256_RATE,
+  KECCAK_PAD,
+  32
+);
+
+macro_rules! xof {
+  ($name:ident, $doc:literal, $rate:expr) => {
+    #[doc = $doc]
+    #[derive(Clone)]
+    pub struct $name
+    {
+      sponge: Sponge,
+    }
+
+    impl Default for $name
+    {
+      fn default() -> Self
+      {
+        Self::new()
+      }
+    }
+
+    impl $name
+    {
+      pub fn new() -> Self
+      {
+        $name {
+          sponge: Sponge::new($rate, SHAKE_PAD),
+        }
+      }
+
+      pub fn update(&mut self, data: &[u8])
+      {
+        self.sponge.absorb(data);
+      }
+
+      pub fn finalize_xof(self) -> XofReader
+      {
+        self.sponge.finalize()
+      }
+    }
+  };
+}
+
+xof!(Shake128, "SHAKE128", SHAKE128_RATE);
+xof!(Shake256, "SHAKE256", SHAKE256_RATE);
+
+/// left_encode(x), SP 800-185 section 2.3.1. Returns the buffer and the
+/// offset of the encoding in it.
+fn left_encode(x: u64) -> ([u8; 9], usize)
+{
+  let mut buf = [0u8; 9];
+  buf[1..].copy_from_slice(&x.to_be_bytes());
+  let start = (buf[1..8].iter().take_while(|b| **b == 0).count() + 1).min(8);
+  buf[start - 1] = (9 - start) as u8;
+  (buf, start - 1)
+}
+
+/// right_encode(x), SP 800-185 section 2.3.1
+fn right_encode(x: u64) -> ([u8; 9], usize)
+{
+  let mut buf = [0u8; 9];
+  buf[..8].copy_from_slice(&x.to_be_bytes());
+  let start = buf[..7].iter().take_while(|b| **b == 0).count();
+  buf[8] = (8 - start) as u8;
+  (buf, start)
+}
+
+/// Absorbs encode_string(s) and returns the number of bytes absorbed
+fn absorb_encoded(sponge: &mut Sponge, s: &[u8]) -> usize
+{
+  let (buf, start) = left_encode(8 * s.len() as u64);
+  sponge.absorb(&buf[start..]);
+  sponge.absorb(s);
+  9 - start + s.len()
+}
+
+/// Absorbs bytepad(encode_string(x_1) || ... || encode_string(x_k), rate)
+fn absorb_bytepad(sponge: &mut Sponge, strings: &[&[u8]])
+{
+  let (buf, start) = left_encode(sponge.rate as u64);
+  sponge.absorb(&buf[start..]);
+  let mut len = 9 - start;
+  for s in strings {
+    len += absorb_encoded(sponge, s);
+  }
+  let zeros = [0u8; SHAKE128_RATE];
+  let rem = len % sponge.rate;
+  if rem != 0 {
+    sponge.absorb(&zeros[..sponge.rate - rem]);
+  }
+}
+
+macro_rules! cshake {
+  ($name:ident, $doc:literal, $rate:expr) => {
+    #[doc = $doc]
+    #[derive(Clone)]
+    pub struct $name
+    {
+      sponge: Sponge,
+    }
+
+    impl $name
+    {
+      /// New instance with function name `n` and customization string
+      /// `s`. With both empty this is plain SHAKE.
+      pub fn new(n: &[u8], s: &[u8]) -> Self
+      {
+        if n.is_empty() && s.is_empty() {
+          return $name {
+            sponge: Sponge::new($rate, SHAKE_PAD),
+          };
+        }
+        let mut sponge = Sponge::new($rate, CSHAKE_PAD);
+        absorb_bytepad(&mut sponge, &[n, s]);
+        $name { sponge }
+      }
+
+      pub fn update(&mut self, data: &[u8])
+      {
+        self.sponge.absorb(data);
+      }
+
+      pub fn finalize_xof(self) -> XofReader
+      {
+        self.sponge.finalize()
+      }
+    }
+  };
+}
+
+cshake!(CShake128, "cSHAKE128, SP 800-185 section 3", SHAKE128_RATE);
+cshake!(CShake256, "cSHAKE256, SP 800-185 section 3", SHAKE256_RATE);
+
+macro_rules! kmac {
+  ($name:ident, $doc:literal, $rate:expr) => {
+    #[doc = $doc]
+    #[derive(Clone)]
+    pub struct $name
+    {
+      sponge: Sponge,
+    }
+
+    impl $name
+    {
+      /// New instance with key `key` and customization string `s`
+      pub fn new(key: &[u8], s: &[u8]) -> Self
+      {
+        let mut sponge = Sponge::new($rate, CSHAKE_PAD);
+        absorb_bytepad(&mut sponge, &[b"KMAC", s]);
+        absorb_bytepad(&mut sponge, &[key]);
+        $name { sponge }
+      }
+
+      pub fn update(&mut self, data: &[u8])
+      {
+        self.sponge.absorb(data);
+      }
+
+      /// Fills `out` with a tag of `out.len()` bytes. The tag length is
+      /// part of the input, so shorter tags are not prefixes of longer
+      /// ones.
+      pub fn finalize(mut self, out: &mut [u8])
+      {
+        let (buf, start) = right_encode(8 * out.len() as u64);
+        self.sponge.absorb(&buf[start..]);
+        self.sponge.finalize().read(out);
+      }
+
+      /// KMACXOF, the variable length output form
+      pub fn finalize_xof(mut self) -> XofReader
+      {
+        let (buf, start) = right_encode(0);
+        self.sponge.absorb(&buf[start..]);
+        self.sponge.finalize()
+      }
+    }
+  };
+}
+
+kmac!(Kmac128, "KMAC128, SP 800-185 section 4", SHAKE128_RATE);
+kmac!(Kmac256, "KMAC256, SP 800-185 section 4", SHAKE256_RATE);
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  fn cshake128(s: &[u8], data: &[u8]) -> String
+  {
+    let mut cshake = CShake128::new(b"", s);
+    cshake.update(data);
+    xof(cshake.finalize_xof(), 32)
+  }
+
+  fn cshake256(s: &[u8], data: &[u8]) -> String
+  {
+    let mut cshake = CShake256::new(b"", s);
+    cshake.update(data);
+    xof(cshake.finalize_xof(), 64)
+  }
+
+  fn kmac128(s: &[u8]) -> String
+  {
+    let mut kmac = Kmac128::new(&kmac_key(), s);
+    kmac.update(&DATA);
+    let mut tag = [0u8; 32];
+    kmac.finalize(&mut tag);
+    hex::encode(tag)
+  }
+
+  fn kmac256(s: &[u8]) -> String
+  {
+    let mut kmac = Kmac256::new(&kmac_key(), s);
+    kmac.update(&DATA);
+    let mut tag = [0u8; 64];
+    kmac.finalize(&mut tag);
+    hex::encode(tag)
+  }
+
+  fn xof(mut reader: XofReader, len: usize) -> String
+  {
+    let mut out = vec![0u8; len];
+    reader.read(&mut out);
+    hex::encode(out)
+  }
+
+  // SP 800-185 sample key and data
+  fn kmac_key() -> Vec<u8>
+  {
+    (0x40u8..0x60).collect()
+  }
+
+  const DATA: [u8; 4] = [0, 1, 2, 3];
+
+  #[test]
+  fn sha3_vectors()
+  {
+    assert_eq!(
+      hex::encode(Sha3_256::digest(b"")),
+      "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+    );
+    assert_eq!(
+      hex::encode(Sha3_256::digest(b"abc")),
+      "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+    );
+    assert_eq!(
+      hex::encode(Sha3_512::digest(b"abc")),
+      "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+       10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+    );
+    assert_eq!(
+      hex::encode(Keccak256::digest(b"")),
+      "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+  }
+
+  #[test]
+  fn incremental_matches_one_shot()
+  {
+    let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+    let mut hasher = Sha3_256::new();
+    for chunk in data.chunks(7) {
+      hasher.update(chunk);
+    }
+    assert_eq!(hasher.finalize(), Sha3_256::digest(&data));
+
+    let mut shake = Shake128::new();
+    shake.update(&data);
+    let mut one = [0u8; 300];
+    let mut split = [0u8; 300];
+    shake.clone().finalize_xof().read(&mut one);
+    let mut reader = shake.finalize_xof();
+    for chunk in split.chunks_mut(13) {
+      reader.read(chunk);
+    }
+    assert_eq!(one, split);
+  }
+
+  #[test]
+  fn shake_vectors()
+  {
+    assert_eq!(
+      xof(Shake128::new().finalize_xof(), 32),
+      "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+    );
+    assert_eq!(
+      xof(Shake256::new().finalize_xof(), 64),
+      "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f\
+       d75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be"
+    );
+  }
+
+  #[test]
+  fn cshake_vectors()
+  {
+    let data: Vec<u8> = (0..200u8).collect();
+    assert_eq!(
+      cshake128(b"Email Signature", &DATA),
+      "c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5"
+    );
+    assert_eq!(
+      cshake128(b"Email Signature", &data),
+      "c5221d50e4f822d96a2e8881a961420f294b7b24fe3d2094baed2c6524cc166b"
+    );
+    assert_eq!(
+      cshake256(b"Email Signature", &DATA),
+      "d008828e2b80ac9d2218ffee1d070c48b8e4c87bff32c9699d5b6896eee0edd1\
+       64020e2be0560858d9c00c037e34a96937c561a74c412bb4c746469527281c8c"
+    );
+  }
+
+  #[test]
+  fn kmac_vectors()
+  {
+    assert_eq!(
+      kmac128(b""),
+      "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e"
+    );
+    assert_eq!(
+      kmac128(b"My Tagged Application"),
+      "3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5"
+    );
+    assert_eq!(
+      kmac256(b"My Tagged Application"),
+      "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7\
+       f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"
+    );
+  }
+}
@@ -4,7 +4,7 @@ use crate::{
 };
 
 #[cfg(feature = "offchain")]
- use randombytes::*;
+use crate::randombytes::*;
 
 #[cfg(feature = "offchain")]
 
@@ -23,14 +23,7 @@ pub fn crypto_sign_keypair(
   let mut tr = [0u8; SEEDBYTES];
   let (mut rho, mut rhoprime, mut key) =
     ([0u8; SEEDBYTES], [0u8; CRHBYTES], [0u8; SEEDBYTES]);
-  let mut mat = [
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-  ];
+  let mut mat: [Polyvecl; K] = core::array::from_fn(|_| Polyvecl::default());
   let mut s1 = Polyvecl::default();
   let (mut s2, mut t1, mut t0) = (
     Polyveck::default(),
@@ -77,20 +70,59 @@ pub fn crypto_sign_keypair(
   return 0;
 }
 
+#[cfg(feature = "offchain")]
+/// Recomputes the public key of a packed secret key as in key
+/// generation, and checks it against the t0 and tr stored in sk.
+pub fn crypto_sign_public_from_secret(
+  pk: &mut [u8],
+  sk: &[u8],
+) -> Result<(), SignError>
+{
+  if pk.len() != PUBLICKEYBYTES || sk.len() != SECRETKEYBYTES {
+    return Err(SignError::Input);
+  }
+  let (mut rho, mut tr, mut key) =
+    ([0u8; SEEDBYTES], [0u8; SEEDBYTES], [0u8; SEEDBYTES]);
+  let mut mat: [Polyvecl; K] = core::array::from_fn(|_| Polyvecl::default());
+  let mut s1 = Polyvecl::default();
+  let (mut s2, mut t0, mut t1, mut t0_check) = (
+    Polyveck::default(),
+    Polyveck::default(),
+    Polyveck::default(),
+    Polyveck::default(),
+  );
+  unpack_sk(&mut rho, &mut tr, &mut key, &mut t0, &mut s1, &mut s2, sk);
+
+  polyvec_matrix_expand(&mut mat, &rho);
+  polyvecl_ntt(&mut s1);
+  polyvec_matrix_pointwise_montgomery(&mut t1, &mat, &s1);
+  polyveck_reduce(&mut t1);
+  polyveck_invntt_tomont(&mut t1);
+  polyveck_add(&mut t1, &s2);
+  polyveck_caddq(&mut t1);
+  polyveck_power2round(&mut t1, &mut t0_check);
+  pack_pk(pk, &rho, &t1);
+
+  let mut tr_check = [0u8; SEEDBYTES];
+  shake256(&mut tr_check, SEEDBYTES, pk, PUBLICKEYBYTES);
+  let t0_matches = t0
+    .vec
+    .iter()
+    .zip(t0_check.vec.iter())
+    .all(|(a, b)| a.coeffs == b.coeffs);
+  if tr_check != tr || !t0_matches {
+    return Err(SignError::Input);
+  }
+  Ok(())
+}
+
 pub fn crypto_sign_signature(sig: &mut [u8], m: &[u8], sk: &[u8])
 {
   // `key` and `mu` are concatenated
   let mut keymu = [0u8; SEEDBYTES + CRHBYTES];
 
   let mut nonce = 0u16;
-  let mut mat = [
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-  ];
+  let mut mat: [Polyvecl; K] = core::array::from_fn(|_| Polyvecl::default());
   let (mut s1, mut y) = (Polyvecl::default(), Polyvecl::default());
   let (mut s2, mut t0) = (Polyveck::default(), Polyveck::default());
   let (mut w1, mut w0) = (Polyveck::default(), Polyveck::default());
@@ -189,12 +221,39 @@ pub fn crypto_sign_signature(sig: &mut [u8], m: &[u8], sk: &[u8])
   }
 }
 
+/// crypto_sign_signature followed by verification of the result against
+/// pk, as a countermeasure against fault injection: a single faulty
+/// signature can leak the secret key. The public key is taken from the
+/// caller rather than recomputed from sk. On mismatch sig is zeroed and
+/// SignError::Fault is returned; buffers of the wrong length give
+/// SignError::Input.
+pub fn crypto_sign_signature_checked(
+  sig: &mut [u8],
+  m: &[u8],
+  sk: &[u8],
+  pk: &[u8],
+) -> Result<(), SignError>
+{
+  if sig.len() < SIGNBYTES
+    || sk.len() != SECRETKEYBYTES
+    || pk.len() != PUBLICKEYBYTES
+  {
+    return Err(SignError::Input);
+  }
+  crypto_sign_signature(sig, m, sk);
+  if crypto_sign_verify(&sig[..SIGNBYTES], m, pk).is_err() {
+    sig[..SIGNBYTES].fill(0);
+    return Err(SignError::Fault);
+  }
+  Ok(())
+}
+
 pub fn crypto_sign_verify_stage1(
   sig: &[u8],
   pk: &[u8],
 ) -> Result<(Box<[u8; SEEDBYTES]>, Box<Polyveck>), SignError>
 {
-  if sig.len() != SIGNBYTES {
+  if sig.len() != SIGNBYTES || pk.len() != PUBLICKEYBYTES {
     return Err(SignError::Input);
   }
   let mut rho = Box::new([0u8; SEEDBYTES]);
@@ -209,6 +268,9 @@ pub fn crypto_sign_verify_stage2(
   sig: &[u8],
 ) -> Result<(Box<[u8; SEEDBYTES]>, Box<Polyvecl>, Box<Polyveck>), SignError>
 {
+  if sig.len() != SIGNBYTES {
+    return Err(SignError::Input);
+  }
   let mut c = Box::new([0u8; SEEDBYTES]);
   let mut z = Box::new(Polyvecl::default());
   let mut h = Box::new(Polyveck::default());
@@ -246,44 +308,39 @@ pub fn crypto_sign_verify_stage4(c: &mut [u8; SEEDBYTES]) -> Box<Poly>
 
 pub fn crypto_sign_verify_stage5(
   rho: &mut [u8; SEEDBYTES],
-) -> Box<[Polyvecl; 6]>
+) -> Box<[Polyvecl; K]>
 {
-  let mut mat: Box<[Polyvecl; 6]> = Box::new([
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-    Polyvecl::default(),
-  ]);
+  let mut mat: Box<[Polyvecl; K]> =
+    Box::new(core::array::from_fn(|_| Polyvecl::default()));
   polyvec_matrix_expand(&mut *mat, rho);
   mat
 }
 
+/// Poly, Polyvecl and Polyveck keep their coefficients on the heap, so
+/// the inputs are taken by value.
 pub fn crypto_sign_verify_stage6(
-  mut cp: Box<Poly>,
-  mut mat: Box<[Polyvecl; 6]>,
-  mut z: Box<Polyvecl>,
-  mut t1: Box<Polyveck>,
+  mut cp: Poly,
+  mat: [Polyvecl; K],
+  mut z: Polyvecl,
+  mut t1: Polyveck,
 ) -> Result<Box<Polyveck>, SignError>
 {
   let mut w1 = Box::new(Polyveck::default());
-  polyvecl_ntt(&mut *z);
-  polyvec_matrix_pointwise_montgomery(&mut *w1, &mut *mat, &*z);
-  poly_ntt(&mut *cp);
-  polyveck_shiftl(&mut *t1);
-  polyveck_ntt(&mut *t1);
-  let t1_2 = Box::new((*t1).clone()); // Allocate t1_2 on the heap
-  polyveck_pointwise_poly_montgomery(&mut *t1, &*cp, &*t1_2);
+  polyvecl_ntt(&mut z);
+  polyvec_matrix_pointwise_montgomery(&mut w1, &mat, &z);
+  poly_ntt(&mut cp);
+  polyveck_shiftl(&mut t1);
+  polyveck_ntt(&mut t1);
+  let t1_2 = t1.clone();
+  polyveck_pointwise_poly_montgomery(&mut t1, &cp, &t1_2);
+  polyveck_sub(&mut w1, &t1);
   Ok(w1)
 }
 
 pub fn crypto_sign_verify_stage7(
   mut w1: Box<Polyveck>,
-  t1: Polyveck,
 ) -> Result<Box<Polyveck>, SignError>
 {
-  polyveck_sub(&mut *w1, &t1);
   polyveck_reduce(&mut *w1);
   polyveck_invntt_tomont(&mut *w1);
 
@@ -326,19 +383,18 @@ fn solvematrix(
 {
   let cp = crypto_sign_verify_stage4(&mut **c); // Correct dereferencing
   let mat = crypto_sign_verify_stage5(&mut *rho); // Correct dereferencing
-  let w1_part1 = crypto_sign_verify_stage6(cp, mat, z, t1.clone())?; // Correct dereferencing for `t1`
+  let w1_part1 = crypto_sign_verify_stage6(*cp, *mat, *z, (**t1).clone())?;
   Ok(w1_part1)
 }
 
 pub fn finalverify(
   w1_part1: Box<Polyveck>,
-  t1: Box<Polyveck>,
   h: Box<Polyveck>,
   mu: Box<[u8; 64]>,
   c: Box<[u8; 32]>,
 ) -> Result<(), SignError>
 {
-  let w1 = crypto_sign_verify_stage7(w1_part1, *t1)?; // Correct dereferencing for `t1`
+  let w1 = crypto_sign_verify_stage7(w1_part1)?;
 
   let buf = Box::new([0u8; K * POLYW1_PACKEDBYTES]);
   let mut c2 = Box::new([0u8; SEEDBYTES]);
@@ -365,7 +421,7 @@ pub fn crypto_sign_verify(
   let w1_part1 = solvematrix(rho, &mut c, z, &t1)?;
 
   // Stage E: Final Verification
-  finalverify(w1_part1, t1, h, mu, c)
+  finalverify(w1_part1, h, mu, c)
 }
 
 pub fn crypto_sign_open(msg: &[u8], mlen: &mut usize, sig: &[u8], pk: &[u8]) -> Result<(), SignError> {
@@ -392,3 +448,190 @@ fn goto_badsig(msg_copy: &mut [u8], mlen: &mut usize) -> Result<(), SignError> {
     msg_copy.fill(0);
     Err(SignError::Verify)
 }
+
+/// Differential tests against the independent crystals-dilithium crate,
+/// which implements the same round 3.1 specification.
+#[cfg(all(test, feature = "offchain"))]
+mod tests
+{
+  use super::*;
+  #[cfg(feature = "mode2")]
+  use crystals_dilithium::dilithium2 as reference;
+  #[cfg(not(any(feature = "mode2", feature = "mode5")))]
+  use crystals_dilithium::dilithium3 as reference;
+  #[cfg(feature = "mode5")]
+  use crystals_dilithium::dilithium5 as reference;
+
+  const CASES: u8 = 16;
+
+  /// Deterministic test vector bytes: SHAKE256(label || i).
+  fn bytes(label: &[u8], i: u8, len: usize) -> Vec<u8>
+  {
+    let mut input = label.to_vec();
+    input.push(i);
+    let mut out = vec![0u8; len];
+    shake256(&mut out, len, &input, input.len());
+    out
+  }
+
+  fn keypair(seed: &[u8]) -> (Vec<u8>, Vec<u8>)
+  {
+    let mut pk = vec![0u8; PUBLICKEYBYTES];
+    let mut sk = vec![0u8; SECRETKEYBYTES];
+    crypto_sign_keypair(&mut pk, &mut sk, Some(seed));
+    (pk, sk)
+  }
+
+  fn sign(msg: &[u8], sk: &[u8]) -> Vec<u8>
+  {
+    let mut sig = vec![0u8; SIGNBYTES];
+    crypto_sign_signature(&mut sig, msg, sk);
+    sig
+  }
+
+  #[test]
+  fn sizes_match_reference()
+  {
+    assert_eq!(PUBLICKEYBYTES, reference::PUBLICKEYBYTES);
+    assert_eq!(SECRETKEYBYTES, reference::SECRETKEYBYTES);
+    assert_eq!(SIGNBYTES, reference::SIGNBYTES);
+  }
+
+  #[test]
+  fn keygen_matches_reference()
+  {
+    for i in 0..CASES {
+      let seed = bytes(b"seed", i, SEEDBYTES);
+      let (pk, sk) = keypair(&seed);
+      let expected = reference::Keypair::generate(Some(&seed)).unwrap();
+      assert_eq!(pk, expected.public.to_bytes(), "public key, case {}", i);
+      assert_eq!(sk, expected.secret.to_bytes(), "secret key, case {}", i);
+    }
+  }
+
+  #[test]
+  fn signatures_match_reference()
+  {
+    for i in 0..CASES {
+      let seed = bytes(b"seed", i, SEEDBYTES);
+      let msg = bytes(b"msg", i, 33 * i as usize);
+      let (_, sk) = keypair(&seed);
+      let expected = reference::Keypair::generate(Some(&seed)).unwrap();
+      assert_eq!(sign(&msg, &sk), expected.sign(&msg), "case {}", i);
+    }
+  }
+
+  #[test]
+  fn verification_matches_reference()
+  {
+    for i in 0..CASES {
+      let seed = bytes(b"seed", i, SEEDBYTES);
+      let msg = bytes(b"msg", i, 33 * i as usize);
+      let (pk, sk) = keypair(&seed);
+      let reference_pk = reference::PublicKey::from_bytes(&pk).unwrap();
+
+      // each side accepts the other's signature
+      let ours = sign(&msg, &sk);
+      let theirs = reference::Keypair::generate(Some(&seed))
+        .unwrap()
+        .sign(&msg);
+      assert!(reference_pk.verify(&msg, &ours), "case {}", i);
+      assert!(crypto_sign_verify(&theirs, &msg, &pk).is_ok(), "case {}", i);
+
+      // and both reject the same tampered signatures and messages
+      let mut tampered = ours.clone();
+      let pos = bytes(b"pos", i, 2);
+      let pos = u16::from_le_bytes([pos[0], pos[1]]) as usize % SIGNBYTES;
+      tampered[pos] ^= 1 << (i % 8);
+      assert!(!reference_pk.verify(&msg, &tampered), "case {}", i);
+      assert!(crypto_sign_verify(&tampered, &msg, &pk).is_err(), "case {}", i);
+
+      let mut other = msg.clone();
+      other.push(i);
+      assert!(!reference_pk.verify(&other, &ours), "case {}", i);
+      assert!(crypto_sign_verify(&ours, &other, &pk).is_err(), "case {}", i);
+    }
+  }
+
+  /// Runs the stages one at a time, the way a program split across
+  /// instructions calls them. Stage 6 once dropped c*t1*2^d, so every
+  /// valid signature was rejected.
+  fn verify_staged(sig: &[u8], msg: &[u8], pk: &[u8]) -> Result<(), SignError>
+  {
+    let (mut rho, t1) = crypto_sign_verify_stage1(sig, pk)?;
+    let (mut c, z, h) = crypto_sign_verify_stage2(sig)?;
+    let mu = crypto_sign_verify_stage3(pk, msg);
+    let cp = crypto_sign_verify_stage4(&mut c);
+    let mat = crypto_sign_verify_stage5(&mut rho);
+    let w1 = crypto_sign_verify_stage6(*cp, *mat, *z, *t1)?;
+    let w1 = crypto_sign_verify_stage7(w1)?;
+    let buf = Box::new([0u8; K * POLYW1_PACKEDBYTES]);
+    let mut c2 = [0u8; SEEDBYTES];
+    crypto_sign_verify_stage8(buf, w1, &h, mu, &c, &mut c2)
+  }
+
+  /// Keygen, signing and stage 5 once expanded a six-row matrix
+  /// whatever the mode, which broke mode5 (K = 8). Run with
+  /// `--features mode5` to cover that case.
+  #[test]
+  fn matrix_has_k_rows()
+  {
+    let mat = crypto_sign_verify_stage5(&mut [7u8; SEEDBYTES]);
+    for row in mat.iter() {
+      assert_eq!(row.vec.len(), L);
+      assert!(row.vec.iter().all(|p| p.coeffs.iter().any(|&x| x != 0)));
+    }
+
+    let (pk, sk) = keypair(&[7u8; SEEDBYTES]);
+    let sig = sign(b"rows", &sk);
+    assert_eq!(crypto_sign_verify(&sig, b"rows", &pk), Ok(()));
+  }
+
+  #[test]
+  fn checked_signing_lengths()
+  {
+    let (pk, sk) = keypair(&[3u8; SEEDBYTES]);
+    let mut sig = vec![0u8; SIGNBYTES];
+    let input = Err(SignError::Input);
+    let short = &mut [0u8; SIGNBYTES - 1];
+    assert_eq!(crypto_sign_signature_checked(short, b"", &sk, &pk), input);
+    assert_eq!(
+      crypto_sign_signature_checked(&mut sig, b"", &sk[1..], &pk),
+      input
+    );
+    assert_eq!(
+      crypto_sign_signature_checked(&mut sig, b"", &sk, &pk[1..]),
+      input
+    );
+    assert_eq!(crypto_sign_signature_checked(&mut sig, b"", &sk, &pk), Ok(()));
+  }
+
+  #[test]
+  fn staged_verification()
+  {
+    for i in 0..CASES {
+      let seed = bytes(b"seed", i, SEEDBYTES);
+      let msg = bytes(b"msg", i, 33 * i as usize);
+      let (pk, sk) = keypair(&seed);
+      let mut sig = sign(&msg, &sk);
+      assert_eq!(verify_staged(&sig, &msg, &pk), Ok(()), "case {}", i);
+      sig[SIGNBYTES - 1] ^= 1;
+      assert!(verify_staged(&sig, &msg, &pk).is_err(), "case {}", i);
+    }
+  }
+
+  /// Stage 7 once subtracted t1 itself instead of c*t1*2^d, which stage
+  /// 6 computed and dropped, so crypto_sign_verify rejected every valid
+  /// signature.
+  #[test]
+  fn verify_accepts_own_signatures()
+  {
+    for i in 0..CASES {
+      let seed = bytes(b"seed", i, SEEDBYTES);
+      let msg = bytes(b"msg", i, 33 * i as usize);
+      let (pk, sk) = keypair(&seed);
+      let sig = sign(&msg, &sk);
+      assert!(crypto_sign_verify(&sig, &msg, &pk).is_ok(), "case {}", i);
+    }
+  }
+}
@@ -0,0 +1,680 @@
+//! SLH-DSA (FIPS 205) stateless hash-based signatures, SHAKE parameter
+//! sets only, built on the Keccak core in `fips202`.
+//!
+//! Verification is split into stages like `crypto_sign_verify`, so an
+//! on-chain program can spread it over several instructions:
+//!
+//! 1. `slh_verify_stage1`: input checks and the message digest
+//! 2. `slh_verify_stage2`: FORS public key from the FORS signature
+//! 3. `slh_verify_stage3`: one XMSS layer of the hypertree, run for
+//!    `layer` in `0..params.d`
+//! 4. `slh_verify_stage4`: comparison with the public root
+
+mod address;
+mod fors;
+mod hash;
+mod hypertree;
+mod params;
+mod wots;
+
+pub use self::params::*;
+
+use self::{address::*, fors::*, hash::*, hypertree::*};
+use crate::{KeypairError, SignError};
+
+#[cfg(feature = "offchain")]
+use crate::randombytes::*;
+
+/// Maximum length of the application context string
+pub const MAX_CONTEXT_BYTES: usize = 255;
+
+/// Message digest and the signing key it selects
+pub struct MessageDigest
+{
+  pub md: Box<[u8]>,
+  pub idx_tree: u64,
+  pub idx_leaf: u32,
+}
+
+/// Splits H_msg output into the FORS message and the hypertree indices,
+/// FIPS 205 algorithm 19 lines 7-12.
+fn split_digest(p: &ParameterSet, digest: &[u8]) -> MessageDigest
+{
+  let md_bytes = (p.k * p.a).div_ceil(8);
+  let tree_bits = p.h - p.hp;
+  let tree_bytes = tree_bits.div_ceil(8);
+  let leaf_bytes = p.hp.div_ceil(8);
+
+  let mut idx_tree = 0u64;
+  for b in &digest[md_bytes..md_bytes + tree_bytes] {
+    idx_tree = (idx_tree << 8) | *b as u64;
+  }
+  if tree_bits < 64 {
+    idx_tree &= (1u64 << tree_bits) - 1;
+  }
+
+  let mut idx_leaf = 0u32;
+  for b in &digest[md_bytes + tree_bytes..md_bytes + tree_bytes + leaf_bytes] {
+    idx_leaf = (idx_leaf << 8) | *b as u32;
+  }
+  idx_leaf &= (1u32 << p.hp) - 1;
+
+  MessageDigest {
+    md: digest[..md_bytes].to_vec().into_boxed_slice(),
+    idx_tree,
+    idx_leaf,
+  }
+}
+
+/// Domain separator of the pure signing interface, 0 || len(ctx) || ctx
+fn pure_prefix(ctx: &[u8]) -> Result<Vec<u8>, SignError>
+{
+  if ctx.len() > MAX_CONTEXT_BYTES {
+    return Err(SignError::Input);
+  }
+  let mut prefix = vec![0u8, ctx.len() as u8];
+  prefix.extend_from_slice(ctx);
+  Ok(prefix)
+}
+
+/// Generates a keypair from the three n-byte seeds, FIPS 205 algorithm 18.
+/// The secret key is SK.seed || SK.prf || PK.seed || PK.root and the
+/// public key PK.seed || PK.root.
+pub fn slh_keygen_internal(
+  p: &ParameterSet,
+  pk: &mut [u8],
+  sk: &mut [u8],
+  sk_seed: &[u8],
+  sk_prf: &[u8],
+  pk_seed: &[u8],
+) -> Result<(), KeypairError>
+{
+  let n = p.n;
+  if pk.len() != p.public_key_bytes() {
+    return Err(KeypairError::InvalidPublicKey);
+  }
+  if sk.len() != p.secret_key_bytes()
+    || sk_seed.len() != n
+    || sk_prf.len() != n
+    || pk_seed.len() != n
+  {
+    return Err(KeypairError::InvalidSecretKey);
+  }
+  let mut adrs = Adrs::default();
+  adrs.set_layer_address(p.d as u32 - 1);
+  let mut root = [0u8; MAX_N];
+  xmss_node(&mut root, p, sk_seed, 0, p.hp as u32, pk_seed, &mut adrs);
+
+  sk[..n].copy_from_slice(&sk_seed[..n]);
+  sk[n..2 * n].copy_from_slice(&sk_prf[..n]);
+  sk[2 * n..3 * n].copy_from_slice(&pk_seed[..n]);
+  sk[3 * n..4 * n].copy_from_slice(&root[..n]);
+  pk[..2 * n].copy_from_slice(&sk[2 * n..4 * n]);
+  Ok(())
+}
+
+/// Generates a signature over `prefix || msg`, FIPS 205 algorithm 19.
+/// Without `addrnd` the signature is deterministic.
+pub fn slh_sign_internal(
+  p: &ParameterSet,
+  sig: &mut [u8],
+  prefix: &[u8],
+  msg: &[u8],
+  sk: &[u8],
+  addrnd: Option<&[u8]>,
+) -> Result<(), SignError>
+{
+  let n = p.n;
+  if sig.len() != p.signature_bytes()
+    || sk.len() != p.secret_key_bytes()
+    || addrnd.is_some_and(|x| x.len() != n)
+  {
+    return Err(SignError::Input);
+  }
+  let (sk_seed, sk_prf) = (&sk[..n], &sk[n..2 * n]);
+  let (pk_seed, pk_root) = (&sk[2 * n..3 * n], &sk[3 * n..4 * n]);
+
+  let opt_rand = match addrnd {
+    Some(x) => x,
+    None => pk_seed,
+  };
+  prf_msg(&mut sig[..n], sk_prf, opt_rand, prefix, msg);
+
+  let mut digest = [0u8; MAX_M];
+  h_msg(&mut digest[..p.m], &sig[..n], pk_seed, pk_root, prefix, msg);
+  let d = split_digest(p, &digest[..p.m]);
+
+  let mut adrs = Adrs::default();
+  adrs.set_tree_address(d.idx_tree);
+  adrs.set_type_and_clear(FORS_TREE);
+  adrs.set_key_pair_address(d.idx_leaf);
+
+  let (sig_fors, sig_ht) = sig[n..].split_at_mut(p.fors_bytes());
+  fors_sign(sig_fors, &d.md, p, sk_seed, pk_seed, &mut adrs);
+  let mut pk_fors = [0u8; MAX_N];
+  fors_pk_from_sig(&mut pk_fors, sig_fors, &d.md, p, pk_seed, &mut adrs);
+
+  ht_sign(
+    sig_ht,
+    &pk_fors[..n],
+    p,
+    sk_seed,
+    pk_seed,
+    d.idx_tree,
+    d.idx_leaf,
+  );
+  Ok(())
+}
+
+fn check_lengths(
+  p: &ParameterSet,
+  sig: &[u8],
+  pk: &[u8],
+) -> Result<(), SignError>
+{
+  if sig.len() != p.signature_bytes() || pk.len() != p.public_key_bytes() {
+    return Err(SignError::Input);
+  }
+  Ok(())
+}
+
+/// Rejects a digest that `split_digest` cannot produce for `p`
+fn check_digest(
+  p: &ParameterSet,
+  digest: &MessageDigest,
+) -> Result<(), SignError>
+{
+  let tree_bits = p.h - p.hp;
+  if digest.md.len() != (p.k * p.a).div_ceil(8)
+    || digest.idx_leaf >> p.hp != 0
+    || (tree_bits < 64 && digest.idx_tree >> tree_bits != 0)
+  {
+    return Err(SignError::Input);
+  }
+  Ok(())
+}
+
+fn verify_digest(
+  p: &ParameterSet,
+  sig: &[u8],
+  prefix: &[u8],
+  msg: &[u8],
+  pk: &[u8],
+) -> Result<Box<MessageDigest>, SignError>
+{
+  check_lengths(p, sig, pk)?;
+  let n = p.n;
+  let mut digest = [0u8; MAX_M];
+  h_msg(
+    &mut digest[..p.m],
+    &sig[..n],
+    &pk[..n],
+    &pk[n..],
+    prefix,
+    msg,
+  );
+  Ok(Box::new(split_digest(p, &digest[..p.m])))
+}
+
+/// Stage 1: checks input lengths and computes the message digest.
+pub fn slh_verify_stage1(
+  p: &ParameterSet,
+  sig: &[u8],
+  msg: &[u8],
+  ctx: &[u8],
+  pk: &[u8],
+) -> Result<Box<MessageDigest>, SignError>
+{
+  let prefix = pure_prefix(ctx)?;
+  verify_digest(p, sig, &prefix, msg, pk)
+}
+
+/// Stage 2: recomputes the FORS public key from the FORS signature.
+pub fn slh_verify_stage2(
+  p: &ParameterSet,
+  sig: &[u8],
+  pk: &[u8],
+  digest: &MessageDigest,
+) -> Result<Box<[u8]>, SignError>
+{
+  check_lengths(p, sig, pk)?;
+  check_digest(p, digest)?;
+  let n = p.n;
+  let mut adrs = Adrs::default();
+  adrs.set_tree_address(digest.idx_tree);
+  adrs.set_type_and_clear(FORS_TREE);
+  adrs.set_key_pair_address(digest.idx_leaf);
+
+  let mut pk_fors = vec![0u8; n].into_boxed_slice();
+  let sig_fors = &sig[n..n + p.fors_bytes()];
+  fors_pk_from_sig(&mut pk_fors, sig_fors, &digest.md, p, &pk[..n], &mut adrs);
+  Ok(pk_fors)
+}
+
+/// Stage 3: computes the root of the XMSS tree on `layer` from the
+/// node returned by the previous layer (the FORS key for layer 0).
+pub fn slh_verify_stage3(
+  p: &ParameterSet,
+  sig: &[u8],
+  pk: &[u8],
+  digest: &MessageDigest,
+  layer: usize,
+  node: &[u8],
+) -> Result<Box<[u8]>, SignError>
+{
+  check_lengths(p, sig, pk)?;
+  check_digest(p, digest)?;
+  let n = p.n;
+  if layer >= p.d || node.len() != n {
+    return Err(SignError::Input);
+  }
+  let mut root = vec![0u8; n].into_boxed_slice();
+  let sig_ht = &sig[n + p.fors_bytes()..];
+  ht_verify_layer(
+    &mut root,
+    node,
+    sig_ht,
+    p,
+    &pk[..n],
+    digest.idx_tree,
+    digest.idx_leaf,
+    layer,
+  );
+  Ok(root)
+}
+
+/// Stage 4: compares the top-layer root with PK.root.
+pub fn slh_verify_stage4(
+  p: &ParameterSet,
+  pk: &[u8],
+  root: &[u8],
+) -> Result<(), SignError>
+{
+  if pk.len() != p.public_key_bytes() || root.len() != p.n {
+    return Err(SignError::Input);
+  }
+  if root[..] != pk[p.n..2 * p.n] {
+    return Err(SignError::Verify);
+  }
+  Ok(())
+}
+
+fn verify_stages(
+  p: &ParameterSet,
+  sig: &[u8],
+  pk: &[u8],
+  digest: &MessageDigest,
+) -> Result<(), SignError>
+{
+  let mut node = slh_verify_stage2(p, sig, pk, digest)?;
+  for layer in 0..p.d {
+    node = slh_verify_stage3(p, sig, pk, digest, layer, &node)?;
+  }
+  slh_verify_stage4(p, pk, &node)
+}
+
+/// Verifies a signature over `msg` without context processing,
+/// FIPS 205 algorithm 20.
+pub fn slh_verify_internal(
+  p: &ParameterSet,
+  sig: &[u8],
+  msg: &[u8],
+  pk: &[u8],
+) -> Result<(), SignError>
+{
+  let digest = verify_digest(p, sig, &[], msg, pk)?;
+  verify_stages(p, sig, pk, &digest)
+}
+
+/// Verifies a pure SLH-DSA signature under the context `ctx`,
+/// FIPS 205 algorithm 24.
+pub fn slh_verify(
+  p: &ParameterSet,
+  sig: &[u8],
+  msg: &[u8],
+  ctx: &[u8],
+  pk: &[u8],
+) -> Result<(), SignError>
+{
+  let digest = slh_verify_stage1(p, sig, msg, ctx, pk)?;
+  verify_stages(p, sig, pk, &digest)
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SlhDsaKeypair
+{
+  params: ParameterSet,
+  public: Box<[u8]>,
+  secret: Box<[u8]>,
+}
+
+/// Secret key elided
+impl std::fmt::Debug for SlhDsaKeypair
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(
+      f,
+      "{}\npublic: {:?}\nsecret: <elided>",
+      self.params.name, self.public
+    )
+  }
+}
+
+impl SlhDsaKeypair
+{
+  /// Generates a keypair from system randomness
+  #[cfg(feature = "offchain")]
+  pub fn generate(params: ParameterSet) -> SlhDsaKeypair
+  {
+    let n = params.n;
+    let mut seeds = [0u8; 3 * MAX_N];
+    randombytes(&mut seeds, 3 * n);
+    Self::derive(params, &seeds[..n], &seeds[n..2 * n], &seeds[2 * n..3 * n])
+  }
+
+  /// Derives a keypair from SK.seed, SK.prf and PK.seed
+  pub fn from_seeds(
+    params: ParameterSet,
+    sk_seed: &[u8],
+    sk_prf: &[u8],
+    pk_seed: &[u8],
+  ) -> Result<SlhDsaKeypair, KeypairError>
+  {
+    let n = params.n;
+    if sk_seed.len() != n || sk_prf.len() != n || pk_seed.len() != n {
+      return Err(KeypairError::InvalidSecretKey);
+    }
+    Ok(Self::derive(params, sk_seed, sk_prf, pk_seed))
+  }
+
+  fn derive(
+    params: ParameterSet,
+    sk_seed: &[u8],
+    sk_prf: &[u8],
+    pk_seed: &[u8],
+  ) -> SlhDsaKeypair
+  {
+    let mut public = vec![0u8; params.public_key_bytes()].into_boxed_slice();
+    let mut secret = vec![0u8; params.secret_key_bytes()].into_boxed_slice();
+    slh_keygen_internal(
+      &params,
+      &mut public,
+      &mut secret,
+      sk_seed,
+      sk_prf,
+      pk_seed,
+    )
+    .expect("seed lengths checked by the caller");
+    SlhDsaKeypair {
+      params,
+      public,
+      secret,
+    }
+  }
+
+  pub fn params(&self) -> &ParameterSet
+  {
+    &self.params
+  }
+
+  /// Public key, PK.seed || PK.root
+  pub fn public(&self) -> &[u8]
+  {
+    &self.public
+  }
+
+  /// Explicitly expose secret key
+  pub fn expose_secret(&self) -> &[u8]
+  {
+    &self.secret
+  }
+
+  /// Hedged signature over `msg` under the context `ctx`,
+  /// FIPS 205 algorithm 22
+  #[cfg(feature = "offchain")]
+  pub fn sign(&self, msg: &[u8], ctx: &[u8]) -> Result<Vec<u8>, SignError>
+  {
+    let mut addrnd = [0u8; MAX_N];
+    randombytes(&mut addrnd, self.params.n);
+    self.sign_with(msg, ctx, Some(&addrnd[..self.params.n]))
+  }
+
+  /// Deterministic variant of `sign`
+  pub fn sign_deterministic(
+    &self,
+    msg: &[u8],
+    ctx: &[u8],
+  ) -> Result<Vec<u8>, SignError>
+  {
+    self.sign_with(msg, ctx, None)
+  }
+
+  fn sign_with(
+    &self,
+    msg: &[u8],
+    ctx: &[u8],
+    addrnd: Option<&[u8]>,
+  ) -> Result<Vec<u8>, SignError>
+  {
+    let prefix = pure_prefix(ctx)?;
+    let mut sig = vec![0u8; self.params.signature_bytes()];
+    slh_sign_internal(
+      &self.params,
+      &mut sig,
+      &prefix,
+      msg,
+      &self.secret,
+      addrnd,
+    )?;
+    Ok(sig)
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::fips202::sha3_256;
+
+  // Keys derived from the seeds 00..0f, 10..1f, 20..2f and deterministic
+  // signatures over "crystals" under the context "slh", which exercise the
+  // pure external interface that the ACVP vectors in tests/slhdsa_acvp.rs
+  // do not reach, and SHAKE-128s, which those vectors lack. The values
+  // were produced by the fips205 crate 0.4.1 (KG::keygen_with_seeds, then
+  // try_sign with hedged = false). Signatures are compared by their
+  // SHA3-256 digests.
+  const PK_128S: &str = "202122232425262728292a2b2c2d2e2f\
+                         89fd81fdbb5b94129b14761bdc6bf682";
+  const SIG_128S_DIGEST: &str =
+    "53866e140862cb4177613a33298e299b55b0e288e54b4ce32183626b2d1b5a97";
+  const PK_128F: &str = "202122232425262728292a2b2c2d2e2f\
+                         a90e4715b9a925c332801767fd786371";
+  const SIG_128F_DIGEST: &str =
+    "0c0f14eade474685025c84440bf482112958ff96c89a226eb9d8d272bcfd7596";
+
+  fn digest(x: &[u8]) -> String
+  {
+    let mut h = [0u8; 32];
+    sha3_256(&mut h, x, x.len());
+    hex::encode(h)
+  }
+
+  fn keypair(params: ParameterSet) -> SlhDsaKeypair
+  {
+    let seeds: Vec<u8> = (0u8..48).collect();
+    SlhDsaKeypair::from_seeds(
+      params,
+      &seeds[..16],
+      &seeds[16..32],
+      &seeds[32..48],
+    )
+    .unwrap()
+  }
+
+  fn kat(params: ParameterSet, pk: &str, sig_digest: &str)
+  {
+    let keypair = keypair(params);
+    assert_eq!(hex::encode(keypair.public()), pk.replace(' ', ""));
+
+    let sig = keypair.sign_deterministic(b"crystals", b"slh").unwrap();
+    assert_eq!(sig.len(), params.signature_bytes());
+    assert_eq!(digest(&sig), sig_digest);
+    assert!(
+      slh_verify(&params, &sig, b"crystals", b"slh", keypair.public()).is_ok()
+    );
+  }
+
+  #[test]
+  fn shake_128s_kat()
+  {
+    kat(SLH_DSA_SHAKE_128S, PK_128S, SIG_128S_DIGEST);
+  }
+
+  #[test]
+  fn shake_128f_kat()
+  {
+    kat(SLH_DSA_SHAKE_128F, PK_128F, SIG_128F_DIGEST);
+  }
+
+  #[test]
+  fn staged_verify()
+  {
+    let p = SLH_DSA_SHAKE_128F;
+    let keypair = keypair(p);
+    let pk = keypair.public();
+    let mut sig = keypair.sign_deterministic(b"crystals", b"").unwrap();
+
+    let digest = slh_verify_stage1(&p, &sig, b"crystals", b"", pk).unwrap();
+    let mut node = slh_verify_stage2(&p, &sig, pk, &digest).unwrap();
+    for layer in 0..p.d {
+      node = slh_verify_stage3(&p, &sig, pk, &digest, layer, &node).unwrap();
+    }
+    assert!(slh_verify_stage4(&p, pk, &node).is_ok());
+
+    assert_eq!(
+      slh_verify(&p, &sig, b"crystals", b"ctx", pk),
+      Err(SignError::Verify)
+    );
+    assert_eq!(
+      slh_verify(&p, &sig, b"crystalz", b"", pk),
+      Err(SignError::Verify)
+    );
+    assert_eq!(
+      slh_verify(&p, &sig[1..], b"crystals", b"", pk),
+      Err(SignError::Input)
+    );
+    let last = sig.len() - 1;
+    sig[last] ^= 1;
+    assert_eq!(
+      slh_verify(&p, &sig, b"crystals", b"", pk),
+      Err(SignError::Verify)
+    );
+  }
+
+  #[test]
+  fn staged_input_checks()
+  {
+    let p = SLH_DSA_SHAKE_128F;
+    let keypair = keypair(p);
+    let pk = keypair.public();
+    let sig = keypair.sign_deterministic(b"crystals", b"").unwrap();
+    let digest = slh_verify_stage1(&p, &sig, b"crystals", b"", pk).unwrap();
+    let node = slh_verify_stage2(&p, &sig, pk, &digest).unwrap();
+    let input = Err(SignError::Input);
+
+    assert_eq!(slh_verify_stage2(&p, &sig[1..], pk, &digest), input);
+    assert_eq!(slh_verify_stage2(&p, &sig, &pk[1..], &digest), input);
+    let bad = [
+      MessageDigest {
+        md: digest.md[1..].into(),
+        ..*digest
+      },
+      MessageDigest {
+        md: digest.md.clone(),
+        idx_leaf: 1 << p.hp,
+        ..*digest
+      },
+      MessageDigest {
+        md: digest.md.clone(),
+        idx_tree: 1 << (p.h - p.hp),
+        ..*digest
+      },
+    ];
+    for d in &bad {
+      assert_eq!(slh_verify_stage2(&p, &sig, pk, d), input);
+      assert_eq!(slh_verify_stage3(&p, &sig, pk, d, 0, &node), input);
+    }
+    assert_eq!(slh_verify_stage3(&p, &sig, pk, &digest, p.d, &node), input);
+    assert_eq!(
+      slh_verify_stage3(&p, &sig, pk, &digest, 0, &node[1..]),
+      input
+    );
+    assert_eq!(
+      slh_verify_stage3(&p, &sig[..sig.len() - 1], pk, &digest, 0, &node),
+      input
+    );
+    assert_eq!(
+      slh_verify_stage4(&p, &pk[1..], &node),
+      Err(SignError::Input)
+    );
+    assert_eq!(slh_verify_stage4(&p, pk, &node[1..]), Err(SignError::Input));
+  }
+
+  #[test]
+  fn internal_input_checks()
+  {
+    let p = SLH_DSA_SHAKE_128F;
+    let (mut pk, mut sk) = (vec![0u8; 32], vec![0u8; 64]);
+    let seed = [0u8; 16];
+    assert_eq!(
+      slh_keygen_internal(&p, &mut pk[1..], &mut sk, &seed, &seed, &seed),
+      Err(KeypairError::InvalidPublicKey)
+    );
+    assert_eq!(
+      slh_keygen_internal(&p, &mut pk, &mut sk, &seed[1..], &seed, &seed),
+      Err(KeypairError::InvalidSecretKey)
+    );
+    assert_eq!(
+      slh_keygen_internal(&p, &mut pk, &mut sk[1..], &seed, &seed, &seed),
+      Err(KeypairError::InvalidSecretKey)
+    );
+
+    let keypair = keypair(p);
+    let sk = keypair.expose_secret();
+    let mut sig = vec![0u8; p.signature_bytes()];
+    assert_eq!(
+      slh_sign_internal(&p, &mut sig[1..], b"", b"", sk, None),
+      Err(SignError::Input)
+    );
+    assert_eq!(
+      slh_sign_internal(&p, &mut sig, b"", b"", &sk[1..], None),
+      Err(SignError::Input)
+    );
+    assert_eq!(
+      slh_sign_internal(&p, &mut sig, b"", b"", sk, Some(&seed[1..])),
+      Err(SignError::Input)
+    );
+  }
+
+  #[test]
+  fn context_too_long()
+  {
+    let keypair = keypair(SLH_DSA_SHAKE_128F);
+    let ctx = [0u8; MAX_CONTEXT_BYTES + 1];
+    assert_eq!(
+      keypair.sign_deterministic(b"crystals", &ctx),
+      Err(SignError::Input)
+    );
+  }
+
+  #[cfg(feature = "offchain")]
+  #[test]
+  fn hedged_sign_verify()
+  {
+    let p = SLH_DSA_SHAKE_128F;
+    let keypair = SlhDsaKeypair::generate(p);
+    let sig = keypair.sign(b"crystals", b"").unwrap();
+    assert!(slh_verify(&p, &sig, b"crystals", b"", keypair.public()).is_ok());
+  }
+}
@@ -0,0 +1,87 @@
+//! Hash function addresses (ADRS), FIPS 205 section 4.2.
+
+pub const WOTS_HASH: u32 = 0;
+pub const WOTS_PK: u32 = 1;
+pub const TREE: u32 = 2;
+pub const FORS_TREE: u32 = 3;
+pub const FORS_ROOTS: u32 = 4;
+pub const WOTS_PRF: u32 = 5;
+pub const FORS_PRF: u32 = 6;
+
+pub const ADRSBYTES: usize = 32;
+
+/// 32-byte address: layer (4) || tree (12) || type (4) || 3 words (12)
+#[derive(Clone, Copy, Default)]
+pub struct Adrs(pub [u8; ADRSBYTES]);
+
+impl Adrs
+{
+  fn set_word(&mut self, offset: usize, x: u32)
+  {
+    self.0[offset..offset + 4].copy_from_slice(&x.to_be_bytes());
+  }
+
+  fn word(&self, offset: usize) -> u32
+  {
+    u32::from_be_bytes([
+      self.0[offset],
+      self.0[offset + 1],
+      self.0[offset + 2],
+      self.0[offset + 3],
+    ])
+  }
+
+  pub fn set_layer_address(&mut self, layer: u32)
+  {
+    self.set_word(0, layer);
+  }
+
+  /// The upper 4 bytes of the 12-byte tree address are always zero
+  pub fn set_tree_address(&mut self, tree: u64)
+  {
+    self.0[4..8].fill(0);
+    self.0[8..16].copy_from_slice(&tree.to_be_bytes());
+  }
+
+  /// Sets the type and zeroes the three trailing words
+  pub fn set_type_and_clear(&mut self, t: u32)
+  {
+    self.set_word(16, t);
+    self.0[20..32].fill(0);
+  }
+
+  pub fn set_key_pair_address(&mut self, i: u32)
+  {
+    self.set_word(20, i);
+  }
+
+  pub fn key_pair_address(&self) -> u32
+  {
+    self.word(20)
+  }
+
+  pub fn set_chain_address(&mut self, i: u32)
+  {
+    self.set_word(24, i);
+  }
+
+  pub fn set_tree_height(&mut self, z: u32)
+  {
+    self.set_word(24, z);
+  }
+
+  pub fn set_hash_address(&mut self, i: u32)
+  {
+    self.set_word(28, i);
+  }
+
+  pub fn set_tree_index(&mut self, i: u32)
+  {
+    self.set_word(28, i);
+  }
+
+  pub fn tree_index(&self) -> u32
+  {
+    self.word(28)
+  }
+}
@@ -0,0 +1,122 @@
+#![allow(clippy::needless_range_loop)]
+//! FORS few-time signatures, FIPS 205 section 8.
+
+use super::{address::*, hash::*, params::*, wots::base_2b};
+
+/// Generates a FORS private-key value, FIPS 205 algorithm 14.
+fn fors_skgen(
+  out: &mut [u8],
+  sk_seed: &[u8],
+  pk_seed: &[u8],
+  adrs: &Adrs,
+  idx: u32,
+)
+{
+  let mut sk_adrs = *adrs;
+  sk_adrs.set_type_and_clear(FORS_PRF);
+  sk_adrs.set_key_pair_address(adrs.key_pair_address());
+  sk_adrs.set_tree_index(idx);
+  prf(out, pk_seed, sk_seed, &sk_adrs);
+}
+
+/// Computes the root of a FORS subtree, FIPS 205 algorithm 15.
+fn fors_node(
+  out: &mut [u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  i: u32,
+  z: u32,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  if z == 0 {
+    let mut sk = [0u8; MAX_N];
+    fors_skgen(&mut sk[..n], sk_seed, pk_seed, adrs, i);
+    adrs.set_tree_height(0);
+    adrs.set_tree_index(i);
+    thash(&mut out[..n], pk_seed, adrs, &sk[..n]);
+  } else {
+    let mut nodes = [0u8; 2 * MAX_N];
+    fors_node(&mut nodes[..n], p, sk_seed, 2 * i, z - 1, pk_seed, adrs);
+    fors_node(&mut nodes[n..], p, sk_seed, 2 * i + 1, z - 1, pk_seed, adrs);
+    adrs.set_tree_height(z);
+    adrs.set_tree_index(i);
+    thash(&mut out[..n], pk_seed, adrs, &nodes[..2 * n]);
+  }
+}
+
+/// Generates a FORS signature, FIPS 205 algorithm 16.
+pub fn fors_sign(
+  sig: &mut [u8],
+  md: &[u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let (n, a) = (p.n, p.a);
+  let mut indices = [0u32; MAX_FORS_K];
+  base_2b(&mut indices, md, a, p.k);
+
+  for i in 0..p.k {
+    let offset = i * (a + 1) * n;
+    let leaf = ((i as u32) << a) + indices[i];
+    fors_skgen(&mut sig[offset..offset + n], sk_seed, pk_seed, adrs, leaf);
+    for j in 0..a {
+      let s = (indices[i] >> j) ^ 1;
+      let node = &mut sig[offset + (j + 1) * n..offset + (j + 2) * n];
+      let idx = ((i as u32) << (a - j)) + s;
+      fors_node(node, p, sk_seed, idx, j as u32, pk_seed, adrs);
+    }
+  }
+}
+
+/// Computes a FORS public key from a signature, FIPS 205 algorithm 17.
+pub fn fors_pk_from_sig(
+  out: &mut [u8],
+  sig: &[u8],
+  md: &[u8],
+  p: &ParameterSet,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let (n, a) = (p.n, p.a);
+  let mut indices = [0u32; MAX_FORS_K];
+  base_2b(&mut indices, md, a, p.k);
+  let mut roots = [0u8; MAX_FORS_K * MAX_N];
+  let mut node = [0u8; MAX_N];
+  let mut pair = [0u8; 2 * MAX_N];
+
+  for i in 0..p.k {
+    let offset = i * (a + 1) * n;
+    adrs.set_tree_height(0);
+    adrs.set_tree_index(((i as u32) << a) + indices[i]);
+    thash(&mut node[..n], pk_seed, adrs, &sig[offset..offset + n]);
+
+    let auth = &sig[offset + n..offset + (a + 1) * n];
+    for j in 0..a {
+      adrs.set_tree_height(j as u32 + 1);
+      let sibling = &auth[j * n..(j + 1) * n];
+      if (indices[i] >> j) & 1 == 0 {
+        adrs.set_tree_index(adrs.tree_index() / 2);
+        pair[..n].copy_from_slice(&node[..n]);
+        pair[n..2 * n].copy_from_slice(sibling);
+      } else {
+        adrs.set_tree_index((adrs.tree_index() - 1) / 2);
+        pair[..n].copy_from_slice(sibling);
+        pair[n..2 * n].copy_from_slice(&node[..n]);
+      }
+      thash(&mut node[..n], pk_seed, adrs, &pair[..2 * n]);
+    }
+    roots[i * n..(i + 1) * n].copy_from_slice(&node[..n]);
+  }
+
+  let mut pk_adrs = *adrs;
+  pk_adrs.set_type_and_clear(FORS_ROOTS);
+  pk_adrs.set_key_pair_address(adrs.key_pair_address());
+  thash(&mut out[..n], pk_seed, &pk_adrs, &roots[..p.k * n]);
+}
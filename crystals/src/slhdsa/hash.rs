@@ -0,0 +1,52 @@
+//! SHAKE256 instantiation of the SLH-DSA hash functions,
+//! FIPS 205 section 11.1.
+
+use super::address::*;
+use crate::fips202::*;
+
+fn shake256_parts(out: &mut [u8], parts: &[&[u8]])
+{
+  let mut state = KeccakState::default();
+  for part in parts {
+    shake256_absorb(&mut state, part, part.len());
+  }
+  shake256_finalize(&mut state);
+  shake256_squeeze(out, out.len(), &mut state);
+}
+
+/// H_msg(R, PK.seed, PK.root, M') with M' = prefix || M
+pub fn h_msg(
+  out: &mut [u8],
+  r: &[u8],
+  pk_seed: &[u8],
+  pk_root: &[u8],
+  prefix: &[u8],
+  msg: &[u8],
+)
+{
+  shake256_parts(out, &[r, pk_seed, pk_root, prefix, msg]);
+}
+
+/// PRF(PK.seed, SK.seed, ADRS)
+pub fn prf(out: &mut [u8], pk_seed: &[u8], sk_seed: &[u8], adrs: &Adrs)
+{
+  shake256_parts(out, &[pk_seed, &adrs.0, sk_seed]);
+}
+
+/// PRF_msg(SK.prf, opt_rand, M') with M' = prefix || M
+pub fn prf_msg(
+  out: &mut [u8],
+  sk_prf: &[u8],
+  opt_rand: &[u8],
+  prefix: &[u8],
+  msg: &[u8],
+)
+{
+  shake256_parts(out, &[sk_prf, opt_rand, prefix, msg]);
+}
+
+/// Tweakable hash F, H and T_l; they only differ in input length.
+pub fn thash(out: &mut [u8], pk_seed: &[u8], adrs: &Adrs, input: &[u8])
+{
+  shake256_parts(out, &[pk_seed, &adrs.0, input]);
+}
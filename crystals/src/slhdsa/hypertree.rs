@@ -0,0 +1,173 @@
+//! XMSS trees and the hypertree, FIPS 205 sections 6 and 7.
+
+use super::{address::*, hash::*, params::*, wots::*};
+
+/// Computes the root of an XMSS subtree, FIPS 205 algorithm 9.
+pub fn xmss_node(
+  out: &mut [u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  i: u32,
+  z: u32,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  if z == 0 {
+    adrs.set_type_and_clear(WOTS_HASH);
+    adrs.set_key_pair_address(i);
+    wots_pkgen(out, p, sk_seed, pk_seed, adrs);
+  } else {
+    let mut nodes = [0u8; 2 * MAX_N];
+    xmss_node(&mut nodes[..n], p, sk_seed, 2 * i, z - 1, pk_seed, adrs);
+    xmss_node(&mut nodes[n..], p, sk_seed, 2 * i + 1, z - 1, pk_seed, adrs);
+    adrs.set_type_and_clear(TREE);
+    adrs.set_tree_height(z);
+    adrs.set_tree_index(i);
+    thash(&mut out[..n], pk_seed, adrs, &nodes[..2 * n]);
+  }
+}
+
+/// Generates an XMSS signature (WOTS+ signature || AUTH),
+/// FIPS 205 algorithm 10.
+pub fn xmss_sign(
+  sig: &mut [u8],
+  m: &[u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  idx: u32,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  let auth_offset = p.wots_len() * n;
+  for j in 0..p.hp {
+    let k = (idx >> j) ^ 1;
+    let node = &mut sig[auth_offset + j * n..auth_offset + (j + 1) * n];
+    xmss_node(node, p, sk_seed, k, j as u32, pk_seed, adrs);
+  }
+  adrs.set_type_and_clear(WOTS_HASH);
+  adrs.set_key_pair_address(idx);
+  wots_sign(sig, m, p, sk_seed, pk_seed, adrs);
+}
+
+/// Computes an XMSS root from a signature, FIPS 205 algorithm 11.
+pub fn xmss_pk_from_sig(
+  out: &mut [u8],
+  idx: u32,
+  sig: &[u8],
+  m: &[u8],
+  p: &ParameterSet,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  let auth = &sig[p.wots_len() * n..];
+  let mut node = [0u8; MAX_N];
+  let mut pair = [0u8; 2 * MAX_N];
+
+  adrs.set_type_and_clear(WOTS_HASH);
+  adrs.set_key_pair_address(idx);
+  wots_pk_from_sig(&mut node, sig, m, p, pk_seed, adrs);
+
+  adrs.set_type_and_clear(TREE);
+  adrs.set_tree_index(idx);
+  for k in 0..p.hp {
+    adrs.set_tree_height(k as u32 + 1);
+    let sibling = &auth[k * n..(k + 1) * n];
+    if (idx >> k) & 1 == 0 {
+      adrs.set_tree_index(adrs.tree_index() / 2);
+      pair[..n].copy_from_slice(&node[..n]);
+      pair[n..2 * n].copy_from_slice(sibling);
+    } else {
+      adrs.set_tree_index((adrs.tree_index() - 1) / 2);
+      pair[..n].copy_from_slice(sibling);
+      pair[n..2 * n].copy_from_slice(&node[..n]);
+    }
+    thash(&mut node[..n], pk_seed, adrs, &pair[..2 * n]);
+  }
+  out[..n].copy_from_slice(&node[..n]);
+}
+
+/// Tree and leaf index of the XMSS tree used on `layer`.
+pub fn layer_indices(
+  p: &ParameterSet,
+  idx_tree: u64,
+  idx_leaf: u32,
+  layer: usize,
+) -> (u64, u32)
+{
+  if layer == 0 {
+    return (idx_tree, idx_leaf);
+  }
+  // idx_tree holds up to 64 bits, so the top layer of SHAKE-256f would
+  // shift by the full width
+  let tree = idx_tree.checked_shr((layer * p.hp) as u32).unwrap_or(0);
+  let leaf = (idx_tree >> ((layer - 1) * p.hp)) & ((1u64 << p.hp) - 1);
+  (tree, leaf as u32)
+}
+
+/// Generates a hypertree signature, FIPS 205 algorithm 12.
+pub fn ht_sign(
+  sig: &mut [u8],
+  m: &[u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  pk_seed: &[u8],
+  idx_tree: u64,
+  idx_leaf: u32,
+)
+{
+  let n = p.n;
+  let xmss_bytes = p.xmss_bytes();
+  let mut root = [0u8; MAX_N];
+  root[..n].copy_from_slice(&m[..n]);
+
+  for j in 0..p.d {
+    let (tree, leaf) = layer_indices(p, idx_tree, idx_leaf, j);
+    let mut adrs = Adrs::default();
+    adrs.set_layer_address(j as u32);
+    adrs.set_tree_address(tree);
+    let layer_sig = &mut sig[j * xmss_bytes..(j + 1) * xmss_bytes];
+    let msg = root;
+    xmss_sign(layer_sig, &msg[..n], p, sk_seed, leaf, pk_seed, &mut adrs);
+    if j < p.d - 1 {
+      xmss_pk_from_sig(
+        &mut root,
+        leaf,
+        layer_sig,
+        &msg[..n],
+        p,
+        pk_seed,
+        &mut adrs,
+      );
+    }
+  }
+}
+
+/// Computes the root of the XMSS tree on `layer` from the layer's
+/// signature and the root of the layer below (or the FORS key for
+/// layer 0). One step of FIPS 205 algorithm 13.
+#[allow(clippy::too_many_arguments)]
+pub fn ht_verify_layer(
+  out: &mut [u8],
+  node: &[u8],
+  sig_ht: &[u8],
+  p: &ParameterSet,
+  pk_seed: &[u8],
+  idx_tree: u64,
+  idx_leaf: u32,
+  layer: usize,
+)
+{
+  let xmss_bytes = p.xmss_bytes();
+  let (tree, leaf) = layer_indices(p, idx_tree, idx_leaf, layer);
+  let mut adrs = Adrs::default();
+  adrs.set_layer_address(layer as u32);
+  adrs.set_tree_address(tree);
+  let layer_sig = &sig_ht[layer * xmss_bytes..(layer + 1) * xmss_bytes];
+  xmss_pk_from_sig(out, leaf, layer_sig, node, p, pk_seed, &mut adrs);
+}
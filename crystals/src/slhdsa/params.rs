@@ -0,0 +1,134 @@
+/// Largest security parameter n across the parameter sets
+pub const MAX_N: usize = 32;
+/// Largest WOTS+ chain count, len = 2n + 3
+pub const MAX_WOTS_LEN: usize = 2 * MAX_N + 3;
+/// Largest number of FORS trees
+pub const MAX_FORS_K: usize = 35;
+/// Largest H_msg output length m
+pub const MAX_M: usize = 49;
+
+/// SLH-DSA parameter set, FIPS 205 table 2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParameterSet
+{
+  pub name: &'static str,
+  /// Security parameter, bytes per hash value
+  pub n: usize,
+  /// Total hypertree height
+  pub h: usize,
+  /// Number of hypertree layers
+  pub d: usize,
+  /// Height of each XMSS tree, h' = h / d
+  pub hp: usize,
+  /// Height of each FORS tree
+  pub a: usize,
+  /// Number of FORS trees
+  pub k: usize,
+  /// Length of the H_msg digest
+  pub m: usize,
+}
+
+impl ParameterSet
+{
+  pub const fn wots_len1(&self) -> usize
+  {
+    2 * self.n
+  }
+
+  pub const fn wots_len(&self) -> usize
+  {
+    2 * self.n + 3
+  }
+
+  /// Bytes of one XMSS signature (WOTS+ signature || AUTH)
+  pub const fn xmss_bytes(&self) -> usize
+  {
+    (self.wots_len() + self.hp) * self.n
+  }
+
+  pub const fn fors_bytes(&self) -> usize
+  {
+    self.k * (self.a + 1) * self.n
+  }
+
+  pub const fn public_key_bytes(&self) -> usize
+  {
+    2 * self.n
+  }
+
+  pub const fn secret_key_bytes(&self) -> usize
+  {
+    4 * self.n
+  }
+
+  pub const fn signature_bytes(&self) -> usize
+  {
+    self.n + self.fors_bytes() + self.d * self.xmss_bytes()
+  }
+}
+
+pub const SLH_DSA_SHAKE_128S: ParameterSet = ParameterSet {
+  name: "SLH-DSA-SHAKE-128s",
+  n: 16,
+  h: 63,
+  d: 7,
+  hp: 9,
+  a: 12,
+  k: 14,
+  m: 30,
+};
+
+pub const SLH_DSA_SHAKE_128F: ParameterSet = ParameterSet {
+  name: "SLH-DSA-SHAKE-128f",
+  n: 16,
+  h: 66,
+  d: 22,
+  hp: 3,
+  a: 6,
+  k: 33,
+  m: 34,
+};
+
+pub const SLH_DSA_SHAKE_192S: ParameterSet = ParameterSet {
+  name: "SLH-DSA-SHAKE-192s",
+  n: 24,
+  h: 63,
+  d: 7,
+  hp: 9,
+  a: 14,
+  k: 17,
+  m: 39,
+};
+
+pub const SLH_DSA_SHAKE_192F: ParameterSet = ParameterSet {
+  name: "SLH-DSA-SHAKE-192f",
+  n: 24,
+  h: 66,
+  d: 22,
+  hp: 3,
+  a: 8,
+  k: 33,
+  m: 42,
+};
+
+pub const SLH_DSA_SHAKE_256S: ParameterSet = ParameterSet {
+  name: "SLH-DSA-SHAKE-256s",
+  n: 32,
+  h: 64,
+  d: 8,
+  hp: 8,
+  a: 14,
+  k: 22,
+  m: 47,
+};
+
+pub const SLH_DSA_SHAKE_256F: ParameterSet = ParameterSet {
+  name: "SLH-DSA-SHAKE-256f",
+  n: 32,
+  h: 68,
+  d: 17,
+  hp: 4,
+  a: 9,
+  k: 35,
+  m: 49,
+};
@@ -0,0 +1,148 @@
+//! WOTS+ one-time signatures, FIPS 205 section 5.
+
+use super::{address::*, hash::*, params::*};
+
+pub const LG_W: usize = 4;
+pub const W: u32 = 1 << LG_W;
+pub const LEN2: usize = 3;
+
+/// Computes the base 2^b representation of x, FIPS 205 algorithm 4.
+pub fn base_2b(out: &mut [u32], x: &[u8], b: usize, out_len: usize)
+{
+  let (mut idx, mut bits, mut total) = (0usize, 0usize, 0u64);
+  for o in out.iter_mut().take(out_len) {
+    while bits < b {
+      total = (total << 8) | x[idx] as u64;
+      idx += 1;
+      bits += 8;
+    }
+    bits -= b;
+    *o = ((total >> bits) & ((1 << b) - 1)) as u32;
+  }
+}
+
+/// Message and checksum digits signed by the WOTS+ chains.
+fn chain_lengths(out: &mut [u32], p: &ParameterSet, m: &[u8])
+{
+  let len1 = p.wots_len1();
+  base_2b(out, m, LG_W, len1);
+  let mut csum = 0u32;
+  for digit in out.iter().take(len1) {
+    csum += W - 1 - digit;
+  }
+  csum <<= (8 - ((LEN2 * LG_W) % 8)) % 8;
+  let csum_bytes = csum.to_be_bytes();
+  base_2b(&mut out[len1..], &csum_bytes[2..], LG_W, LEN2);
+}
+
+/// Iterates F `s` times starting at step `i`, FIPS 205 algorithm 5.
+fn chain(
+  out: &mut [u8],
+  x: &[u8],
+  i: u32,
+  s: u32,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = x.len();
+  let mut tmp = [0u8; MAX_N];
+  tmp[..n].copy_from_slice(x);
+  for j in i..i + s {
+    adrs.set_hash_address(j);
+    let input = tmp;
+    thash(&mut tmp[..n], pk_seed, adrs, &input[..n]);
+  }
+  out[..n].copy_from_slice(&tmp[..n]);
+}
+
+fn secret_adrs(adrs: &Adrs) -> Adrs
+{
+  let mut sk_adrs = *adrs;
+  sk_adrs.set_type_and_clear(WOTS_PRF);
+  sk_adrs.set_key_pair_address(adrs.key_pair_address());
+  sk_adrs
+}
+
+/// Compresses the chain ends into the WOTS+ public key.
+fn compress(out: &mut [u8], ends: &[u8], pk_seed: &[u8], adrs: &Adrs)
+{
+  let mut pk_adrs = *adrs;
+  pk_adrs.set_type_and_clear(WOTS_PK);
+  pk_adrs.set_key_pair_address(adrs.key_pair_address());
+  thash(out, pk_seed, &pk_adrs, ends);
+}
+
+/// Generates a WOTS+ public key, FIPS 205 algorithm 6.
+pub fn wots_pkgen(
+  out: &mut [u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  let mut ends = [0u8; MAX_WOTS_LEN * MAX_N];
+  let mut sk = [0u8; MAX_N];
+  let mut sk_adrs = secret_adrs(adrs);
+  for i in 0..p.wots_len() {
+    sk_adrs.set_chain_address(i as u32);
+    prf(&mut sk[..n], pk_seed, sk_seed, &sk_adrs);
+    adrs.set_chain_address(i as u32);
+    chain(&mut ends[i * n..], &sk[..n], 0, W - 1, pk_seed, adrs);
+  }
+  compress(&mut out[..n], &ends[..p.wots_len() * n], pk_seed, adrs);
+}
+
+/// Generates a WOTS+ signature on an n-byte message, FIPS 205
+/// algorithm 7.
+pub fn wots_sign(
+  sig: &mut [u8],
+  m: &[u8],
+  p: &ParameterSet,
+  sk_seed: &[u8],
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  let mut lengths = [0u32; MAX_WOTS_LEN];
+  chain_lengths(&mut lengths, p, m);
+  let mut sk = [0u8; MAX_N];
+  let mut sk_adrs = secret_adrs(adrs);
+  for i in 0..p.wots_len() {
+    sk_adrs.set_chain_address(i as u32);
+    prf(&mut sk[..n], pk_seed, sk_seed, &sk_adrs);
+    adrs.set_chain_address(i as u32);
+    chain(&mut sig[i * n..], &sk[..n], 0, lengths[i], pk_seed, adrs);
+  }
+}
+
+/// Computes a WOTS+ public key from a signature, FIPS 205 algorithm 8.
+pub fn wots_pk_from_sig(
+  out: &mut [u8],
+  sig: &[u8],
+  m: &[u8],
+  p: &ParameterSet,
+  pk_seed: &[u8],
+  adrs: &mut Adrs,
+)
+{
+  let n = p.n;
+  let mut lengths = [0u32; MAX_WOTS_LEN];
+  chain_lengths(&mut lengths, p, m);
+  let mut ends = [0u8; MAX_WOTS_LEN * MAX_N];
+  for i in 0..p.wots_len() {
+    adrs.set_chain_address(i as u32);
+    chain(
+      &mut ends[i * n..],
+      &sig[i * n..(i + 1) * n],
+      lengths[i],
+      W - 1 - lengths[i],
+      pk_seed,
+      adrs,
+    );
+  }
+  compress(&mut out[..n], &ends[..p.wots_len() * n], pk_seed, adrs);
+}
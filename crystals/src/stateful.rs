@@ -0,0 +1,33 @@
+//! Stateful hash-based signatures from NIST SP 800-208: LMS/HSS and XMSS
+//! with SHAKE256, built on the Keccak core in `fips202`.
+//!
+//! Verification is always available. Signing is off-chain only: every
+//! signature consumes a one-time key index, which is retired in a
+//! `StateStore` before the signature is computed, so a crash can lose an
+//! index but never hand the same index out twice.
+
+pub mod lms;
+#[cfg(feature = "offchain")]
+mod state;
+pub mod xmss;
+
+#[cfg(feature = "offchain")]
+pub use self::state::*;
+
+use crate::fips202::*;
+
+/// SHAKE256 over the concatenation of `parts`
+fn shake256_parts(out: &mut [u8], parts: &[&[u8]])
+{
+  let mut state = KeccakState::default();
+  for part in parts {
+    shake256_absorb(&mut state, part, part.len());
+  }
+  shake256_finalize(&mut state);
+  shake256_squeeze(out, out.len(), &mut state);
+}
+
+fn read_u32(x: &[u8]) -> u32
+{
+  u32::from_be_bytes([x[0], x[1], x[2], x[3]])
+}
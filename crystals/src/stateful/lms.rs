@@ -0,0 +1,821 @@
+//! LMS and HSS (RFC 8554) with the SHAKE256 parameter sets of
+//! SP 800-208 section 4.
+
+use super::{read_u32, shake256_parts};
+use crate::fips202::*;
+use crate::SignError;
+
+#[cfg(feature = "offchain")]
+use super::state::*;
+#[cfg(feature = "offchain")]
+use crate::KeypairError;
+
+const D_PBLC: [u8; 2] = [0x80, 0x80];
+const D_MESG: [u8; 2] = [0x81, 0x81];
+const D_LEAF: [u8; 2] = [0x82, 0x82];
+const D_INTR: [u8; 2] = [0x83, 0x83];
+
+const MAX_N: usize = 32;
+pub const IDENTIFIER_BYTES: usize = 16;
+/// Maximum number of levels of an HSS key
+pub const HSS_MAX_LEVELS: usize = 8;
+
+/// LM-OTS parameter set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LmotsParams
+{
+  pub type_id: u32,
+  pub n: usize,
+  pub w: usize,
+  pub p: usize,
+  pub ls: u32,
+}
+
+impl LmotsParams
+{
+  pub const fn signature_bytes(&self) -> usize
+  {
+    4 + (self.p + 1) * self.n
+  }
+}
+
+pub const LMOTS_SHAKE_N32_W1: LmotsParams = LmotsParams {
+  type_id: 0x09,
+  n: 32,
+  w: 1,
+  p: 265,
+  ls: 7,
+};
+pub const LMOTS_SHAKE_N32_W2: LmotsParams = LmotsParams {
+  type_id: 0x0a,
+  n: 32,
+  w: 2,
+  p: 133,
+  ls: 6,
+};
+pub const LMOTS_SHAKE_N32_W4: LmotsParams = LmotsParams {
+  type_id: 0x0b,
+  n: 32,
+  w: 4,
+  p: 67,
+  ls: 4,
+};
+pub const LMOTS_SHAKE_N32_W8: LmotsParams = LmotsParams {
+  type_id: 0x0c,
+  n: 32,
+  w: 8,
+  p: 34,
+  ls: 0,
+};
+pub const LMOTS_SHAKE_N24_W1: LmotsParams = LmotsParams {
+  type_id: 0x0d,
+  n: 24,
+  w: 1,
+  p: 200,
+  ls: 8,
+};
+pub const LMOTS_SHAKE_N24_W2: LmotsParams = LmotsParams {
+  type_id: 0x0e,
+  n: 24,
+  w: 2,
+  p: 101,
+  ls: 6,
+};
+pub const LMOTS_SHAKE_N24_W4: LmotsParams = LmotsParams {
+  type_id: 0x0f,
+  n: 24,
+  w: 4,
+  p: 51,
+  ls: 4,
+};
+pub const LMOTS_SHAKE_N24_W8: LmotsParams = LmotsParams {
+  type_id: 0x10,
+  n: 24,
+  w: 8,
+  p: 26,
+  ls: 0,
+};
+
+const LMOTS_PARAMS: [LmotsParams; 8] = [
+  LMOTS_SHAKE_N32_W1,
+  LMOTS_SHAKE_N32_W2,
+  LMOTS_SHAKE_N32_W4,
+  LMOTS_SHAKE_N32_W8,
+  LMOTS_SHAKE_N24_W1,
+  LMOTS_SHAKE_N24_W2,
+  LMOTS_SHAKE_N24_W4,
+  LMOTS_SHAKE_N24_W8,
+];
+
+/// LMS parameter set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LmsParams
+{
+  pub type_id: u32,
+  pub m: usize,
+  pub h: usize,
+}
+
+impl LmsParams
+{
+  pub const fn public_key_bytes(&self) -> usize
+  {
+    8 + IDENTIFIER_BYTES + self.m
+  }
+
+  pub const fn signature_bytes(&self, ots: &LmotsParams) -> usize
+  {
+    8 + ots.signature_bytes() + self.h * self.m
+  }
+}
+
+pub const LMS_SHAKE_M32_H5: LmsParams = LmsParams {
+  type_id: 0x0f,
+  m: 32,
+  h: 5,
+};
+pub const LMS_SHAKE_M32_H10: LmsParams = LmsParams {
+  type_id: 0x10,
+  m: 32,
+  h: 10,
+};
+pub const LMS_SHAKE_M32_H15: LmsParams = LmsParams {
+  type_id: 0x11,
+  m: 32,
+  h: 15,
+};
+pub const LMS_SHAKE_M32_H20: LmsParams = LmsParams {
+  type_id: 0x12,
+  m: 32,
+  h: 20,
+};
+pub const LMS_SHAKE_M32_H25: LmsParams = LmsParams {
+  type_id: 0x13,
+  m: 32,
+  h: 25,
+};
+pub const LMS_SHAKE_M24_H5: LmsParams = LmsParams {
+  type_id: 0x14,
+  m: 24,
+  h: 5,
+};
+pub const LMS_SHAKE_M24_H10: LmsParams = LmsParams {
+  type_id: 0x15,
+  m: 24,
+  h: 10,
+};
+pub const LMS_SHAKE_M24_H15: LmsParams = LmsParams {
+  type_id: 0x16,
+  m: 24,
+  h: 15,
+};
+pub const LMS_SHAKE_M24_H20: LmsParams = LmsParams {
+  type_id: 0x17,
+  m: 24,
+  h: 20,
+};
+pub const LMS_SHAKE_M24_H25: LmsParams = LmsParams {
+  type_id: 0x18,
+  m: 24,
+  h: 25,
+};
+
+const LMS_PARAMS: [LmsParams; 10] = [
+  LMS_SHAKE_M32_H5,
+  LMS_SHAKE_M32_H10,
+  LMS_SHAKE_M32_H15,
+  LMS_SHAKE_M32_H20,
+  LMS_SHAKE_M32_H25,
+  LMS_SHAKE_M24_H5,
+  LMS_SHAKE_M24_H10,
+  LMS_SHAKE_M24_H15,
+  LMS_SHAKE_M24_H20,
+  LMS_SHAKE_M24_H25,
+];
+
+pub fn lmots_params(type_id: u32) -> Option<LmotsParams>
+{
+  LMOTS_PARAMS.iter().copied().find(|p| p.type_id == type_id)
+}
+
+pub fn lms_params(type_id: u32) -> Option<LmsParams>
+{
+  LMS_PARAMS.iter().copied().find(|p| p.type_id == type_id)
+}
+
+/// Digit i of S in base 2^w, RFC 8554 section 3.1.3
+fn coef(s: &[u8], i: usize, w: usize) -> u32
+{
+  let shift = 8 - (w * (i % (8 / w)) + w);
+  ((s[i * w / 8] >> shift) as u32) & ((1 << w) - 1)
+}
+
+/// Message digest Q || Cksm(Q), RFC 8554 section 4.4
+fn digest_with_checksum(out: &mut [u8], ots: &LmotsParams)
+{
+  let max = (1u32 << ots.w) - 1;
+  let mut sum = 0u32;
+  for i in 0..ots.n * 8 / ots.w {
+    sum += max - coef(out, i, ots.w);
+  }
+  let cksm = ((sum << ots.ls) as u16).to_be_bytes();
+  out[ots.n..ots.n + 2].copy_from_slice(&cksm);
+}
+
+/// Runs the hash chain of digit `i` from step `start` to `end`
+fn chain(tmp: &mut [u8], id: &[u8], q: u32, i: usize, start: u32, end: u32)
+{
+  let n = tmp.len();
+  let mut next = [0u8; MAX_N];
+  for j in start..end {
+    shake256_parts(
+      &mut next[..n],
+      &[
+        id,
+        &q.to_be_bytes(),
+        &(i as u16).to_be_bytes(),
+        &[j as u8],
+        tmp,
+      ],
+    );
+    tmp.copy_from_slice(&next[..n]);
+  }
+}
+
+/// Candidate LM-OTS public key from a signature, RFC 8554 algorithm 4b.
+/// `sig` starts after the LM-OTS type.
+fn lmots_candidate(
+  kc: &mut [u8],
+  ots: &LmotsParams,
+  id: &[u8],
+  q: u32,
+  sig: &[u8],
+  msg: &[u8],
+)
+{
+  let n = ots.n;
+  let max = (1u32 << ots.w) - 1;
+  let mut qc = [0u8; MAX_N + 2];
+  shake256_parts(
+    &mut qc[..n],
+    &[id, &q.to_be_bytes(), &D_MESG, &sig[..n], msg],
+  );
+  digest_with_checksum(&mut qc, ots);
+
+  let mut state = KeccakState::default();
+  shake256_absorb(&mut state, id, id.len());
+  shake256_absorb(&mut state, &q.to_be_bytes(), 4);
+  shake256_absorb(&mut state, &D_PBLC, 2);
+  let mut tmp = [0u8; MAX_N];
+  for i in 0..ots.p {
+    let y = &sig[(i + 1) * n..(i + 2) * n];
+    tmp[..n].copy_from_slice(y);
+    chain(&mut tmp[..n], id, q, i, coef(&qc, i, ots.w), max);
+    shake256_absorb(&mut state, &tmp, n);
+  }
+  shake256_finalize(&mut state);
+  shake256_squeeze(kc, n, &mut state);
+}
+
+/// Root candidate from a leaf public key and authentication path,
+/// RFC 8554 algorithm 6a
+fn lms_root_from_leaf(
+  out: &mut [u8],
+  lms: &LmsParams,
+  id: &[u8],
+  q: u32,
+  kc: &[u8],
+  path: &[u8],
+)
+{
+  let m = lms.m;
+  let mut node_num = (1u32 << lms.h) + q;
+  let mut tmp = [0u8; MAX_N];
+  shake256_parts(&mut tmp[..m], &[id, &node_num.to_be_bytes(), &D_LEAF, kc]);
+  let mut next = [0u8; MAX_N];
+  for sibling in path.chunks(m) {
+    let parent = (node_num / 2).to_be_bytes();
+    if node_num & 1 == 1 {
+      shake256_parts(
+        &mut next[..m],
+        &[id, &parent, &D_INTR, sibling, &tmp[..m]],
+      );
+    } else {
+      shake256_parts(
+        &mut next[..m],
+        &[id, &parent, &D_INTR, &tmp[..m], sibling],
+      );
+    }
+    tmp[..m].copy_from_slice(&next[..m]);
+    node_num /= 2;
+  }
+  out.copy_from_slice(&tmp[..m]);
+}
+
+/// Length of the LMS signature at the start of `sig`
+fn lms_signature_len(sig: &[u8]) -> Result<usize, SignError>
+{
+  if sig.len() < 8 {
+    return Err(SignError::Input);
+  }
+  let ots = lmots_params(read_u32(&sig[4..])).ok_or(SignError::Input)?;
+  let off = 4 + ots.signature_bytes();
+  if sig.len() < off + 4 {
+    return Err(SignError::Input);
+  }
+  let lms = lms_params(read_u32(&sig[off..])).ok_or(SignError::Input)?;
+  Ok(lms.signature_bytes(&ots))
+}
+
+/// Verifies an LMS signature, RFC 8554 algorithm 6a. The public key is
+/// lms_type || lmots_type || I || T[1].
+pub fn lms_verify(sig: &[u8], msg: &[u8], pk: &[u8]) -> Result<(), SignError>
+{
+  if pk.len() < 8 {
+    return Err(SignError::Input);
+  }
+  let lms = lms_params(read_u32(pk)).ok_or(SignError::Input)?;
+  let ots = lmots_params(read_u32(&pk[4..])).ok_or(SignError::Input)?;
+  if pk.len() != lms.public_key_bytes() || ots.n != lms.m {
+    return Err(SignError::Input);
+  }
+  if sig.len() != lms_signature_len(sig)? {
+    return Err(SignError::Input);
+  }
+  let ots_off = 4;
+  let lms_off = ots_off + ots.signature_bytes();
+  if read_u32(&sig[ots_off..]) != ots.type_id
+    || read_u32(&sig[lms_off..]) != lms.type_id
+  {
+    return Err(SignError::Verify);
+  }
+
+  let q = read_u32(sig);
+  if q >= 1u32 << lms.h {
+    return Err(SignError::Verify);
+  }
+  let id = &pk[8..8 + IDENTIFIER_BYTES];
+  let mut kc = [0u8; MAX_N];
+  lmots_candidate(
+    &mut kc[..ots.n],
+    &ots,
+    id,
+    q,
+    &sig[ots_off + 4..lms_off],
+    msg,
+  );
+  let mut root = [0u8; MAX_N];
+  lms_root_from_leaf(
+    &mut root[..lms.m],
+    &lms,
+    id,
+    q,
+    &kc[..ots.n],
+    &sig[lms_off + 4..],
+  );
+
+  if root[..lms.m] != pk[8 + IDENTIFIER_BYTES..] {
+    return Err(SignError::Verify);
+  }
+  Ok(())
+}
+
+/// Verifies an HSS signature, RFC 8554 algorithm 6. The public key is
+/// u32(L) || LMS public key of the top level tree.
+pub fn hss_verify(sig: &[u8], msg: &[u8], pk: &[u8]) -> Result<(), SignError>
+{
+  if pk.len() < 4 || sig.len() < 4 {
+    return Err(SignError::Input);
+  }
+  let levels = read_u32(pk) as usize;
+  if levels == 0 || levels > HSS_MAX_LEVELS {
+    return Err(SignError::Input);
+  }
+  if read_u32(sig) as usize + 1 != levels {
+    return Err(SignError::Verify);
+  }
+
+  let mut key = &pk[4..];
+  let mut rest = &sig[4..];
+  for _ in 0..levels - 1 {
+    let sig_len = lms_signature_len(rest)?;
+    if rest.len() < sig_len + 8 {
+      return Err(SignError::Input);
+    }
+    let lms = lms_params(read_u32(&rest[sig_len..])).ok_or(SignError::Input)?;
+    let pub_len = lms.public_key_bytes();
+    if rest.len() < sig_len + pub_len {
+      return Err(SignError::Input);
+    }
+    let child = &rest[sig_len..sig_len + pub_len];
+    lms_verify(&rest[..sig_len], child, key)?;
+    key = child;
+    rest = &rest[sig_len + pub_len..];
+  }
+  lms_verify(rest, msg, key)
+}
+
+/// One LMS tree of an HSS private key
+#[cfg(feature = "offchain")]
+struct Tree<'a>
+{
+  lms: &'a LmsParams,
+  ots: &'a LmotsParams,
+  id: [u8; IDENTIFIER_BYTES],
+  seed: [u8; 32],
+}
+
+#[cfg(feature = "offchain")]
+impl Tree<'_>
+{
+  /// Pseudorandom LM-OTS private key element, RFC 8554 appendix A
+  fn ots_secret(&self, out: &mut [u8], q: u32, i: u16)
+  {
+    shake256_parts(
+      out,
+      &[
+        &self.id,
+        &q.to_be_bytes(),
+        &i.to_be_bytes(),
+        &[0xff],
+        &self.seed,
+      ],
+    );
+  }
+
+  /// LM-OTS public key K of leaf q, RFC 8554 algorithm 1
+  fn ots_public(&self, out: &mut [u8], q: u32)
+  {
+    let n = self.ots.n;
+    let max = (1u32 << self.ots.w) - 1;
+    let mut state = KeccakState::default();
+    shake256_absorb(&mut state, &self.id, IDENTIFIER_BYTES);
+    shake256_absorb(&mut state, &q.to_be_bytes(), 4);
+    shake256_absorb(&mut state, &D_PBLC, 2);
+    let mut tmp = [0u8; MAX_N];
+    for i in 0..self.ots.p {
+      self.ots_secret(&mut tmp[..n], q, i as u16);
+      chain(&mut tmp[..n], &self.id, q, i, 0, max);
+      shake256_absorb(&mut state, &tmp, n);
+    }
+    shake256_finalize(&mut state);
+    shake256_squeeze(out, n, &mut state);
+  }
+
+  /// Node r of the Merkle tree, RFC 8554 section 5.3
+  fn node(&self, out: &mut [u8], r: u32)
+  {
+    let m = self.lms.m;
+    if r >= 1 << self.lms.h {
+      let mut k = [0u8; MAX_N];
+      self.ots_public(&mut k[..m], r - (1 << self.lms.h));
+      shake256_parts(out, &[&self.id, &r.to_be_bytes(), &D_LEAF, &k[..m]]);
+    } else {
+      let mut children = [0u8; 2 * MAX_N];
+      self.node(&mut children[..m], 2 * r);
+      self.node(&mut children[m..2 * m], 2 * r + 1);
+      shake256_parts(
+        out,
+        &[&self.id, &r.to_be_bytes(), &D_INTR, &children[..2 * m]],
+      );
+    }
+  }
+
+  fn public(&self) -> Vec<u8>
+  {
+    let m = self.lms.m;
+    let mut pk = vec![0u8; self.lms.public_key_bytes()];
+    pk[..4].copy_from_slice(&self.lms.type_id.to_be_bytes());
+    pk[4..8].copy_from_slice(&self.ots.type_id.to_be_bytes());
+    pk[8..8 + IDENTIFIER_BYTES].copy_from_slice(&self.id);
+    self.node(&mut pk[8 + IDENTIFIER_BYTES..8 + IDENTIFIER_BYTES + m], 1);
+    pk
+  }
+
+  /// LMS signature with leaf q, RFC 8554 algorithms 3 and 5
+  fn sign(&self, sig: &mut Vec<u8>, q: u32, msg: &[u8])
+  {
+    let (n, m) = (self.ots.n, self.lms.m);
+    sig.extend_from_slice(&q.to_be_bytes());
+    sig.extend_from_slice(&self.ots.type_id.to_be_bytes());
+
+    // The randomizer only has to be unpredictable before signing; it is
+    // derived like the private key elements from an unused chain index
+    let mut c = [0u8; MAX_N];
+    self.ots_secret(&mut c[..n], q, 0xfffd);
+    sig.extend_from_slice(&c[..n]);
+
+    let mut qc = [0u8; MAX_N + 2];
+    shake256_parts(
+      &mut qc[..n],
+      &[&self.id, &q.to_be_bytes(), &D_MESG, &c[..n], msg],
+    );
+    digest_with_checksum(&mut qc, self.ots);
+    let mut tmp = [0u8; MAX_N];
+    for i in 0..self.ots.p {
+      self.ots_secret(&mut tmp[..n], q, i as u16);
+      chain(&mut tmp[..n], &self.id, q, i, 0, coef(&qc, i, self.ots.w));
+      sig.extend_from_slice(&tmp[..n]);
+    }
+
+    sig.extend_from_slice(&self.lms.type_id.to_be_bytes());
+    let mut r = (1u32 << self.lms.h) + q;
+    while r > 1 {
+      self.node(&mut tmp[..m], r ^ 1);
+      sig.extend_from_slice(&tmp[..m]);
+      r /= 2;
+    }
+  }
+
+  /// Tree signed by leaf q of this tree, RFC 8554 appendix A
+  fn child<'a>(
+    &self,
+    lms: &'a LmsParams,
+    ots: &'a LmotsParams,
+    q: u32,
+  ) -> Tree<'a>
+  {
+    let mut seed = [0u8; 32];
+    let mut id = [0u8; 32];
+    self.ots_secret(&mut seed, q, 0xfffe);
+    self.ots_secret(&mut id, q, 0xffff);
+    Tree {
+      lms,
+      ots,
+      id: id[..IDENTIFIER_BYTES].try_into().unwrap(),
+      seed,
+    }
+  }
+}
+
+/// HSS private key. The key itself is immutable; the index of the next
+/// one-time key lives in a `StateStore`.
+///
+/// Signing recomputes the LMS trees along the signing path, so it costs
+/// about 2^h one-time key generations per level.
+#[cfg(feature = "offchain")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct HssPrivateKey
+{
+  levels: Box<[(LmsParams, LmotsParams)]>,
+  id: [u8; IDENTIFIER_BYTES],
+  seed: [u8; 32],
+}
+
+/// Secret key elided
+#[cfg(feature = "offchain")]
+impl std::fmt::Debug for HssPrivateKey
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(f, "levels: {:?}\nsecret: <elided>", self.levels)
+  }
+}
+
+#[cfg(feature = "offchain")]
+impl HssPrivateKey
+{
+  /// Generates a private key from system randomness
+  pub fn generate(
+    levels: &[(LmsParams, LmotsParams)],
+  ) -> Result<HssPrivateKey, KeypairError>
+  {
+    let mut bytes = [0u8; IDENTIFIER_BYTES + 32];
+    crate::randombytes::randombytes(&mut bytes, IDENTIFIER_BYTES + 32);
+    Self::from_seed(
+      levels,
+      &bytes[..IDENTIFIER_BYTES],
+      &bytes[IDENTIFIER_BYTES..],
+    )
+  }
+
+  /// Derives a private key from the top level identifier I and a 32 byte
+  /// seed. The trees of the lower levels are derived from their parents.
+  pub fn from_seed(
+    levels: &[(LmsParams, LmotsParams)],
+    id: &[u8],
+    seed: &[u8],
+  ) -> Result<HssPrivateKey, KeypairError>
+  {
+    let height: usize = levels.iter().map(|(lms, _)| lms.h).sum();
+    if levels.is_empty()
+      || levels.len() > HSS_MAX_LEVELS
+      || height > 63
+      || levels.iter().any(|(lms, ots)| lms.m != ots.n)
+    {
+      return Err(KeypairError::InvalidSecretKey);
+    }
+    Ok(HssPrivateKey {
+      levels: levels.into(),
+      id: id.try_into().map_err(|_| KeypairError::InvalidSecretKey)?,
+      seed: seed
+        .try_into()
+        .map_err(|_| KeypairError::InvalidSecretKey)?,
+    })
+  }
+
+  /// Serialized key: u32(L) || (lms_type || lmots_type) * L || I || seed
+  pub fn to_bytes(&self) -> Vec<u8>
+  {
+    let mut out = (self.levels.len() as u32).to_be_bytes().to_vec();
+    for (lms, ots) in self.levels.iter() {
+      out.extend_from_slice(&lms.type_id.to_be_bytes());
+      out.extend_from_slice(&ots.type_id.to_be_bytes());
+    }
+    out.extend_from_slice(&self.id);
+    out.extend_from_slice(&self.seed);
+    out
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<HssPrivateKey, KeypairError>
+  {
+    let invalid = KeypairError::InvalidSecretKey;
+    if bytes.len() < 4 {
+      return Err(invalid);
+    }
+    let count = read_u32(bytes) as usize;
+    if count > HSS_MAX_LEVELS
+      || bytes.len() != 4 + 8 * count + IDENTIFIER_BYTES + 32
+    {
+      return Err(invalid);
+    }
+    let mut levels = Vec::with_capacity(count);
+    for chunk in bytes[4..4 + 8 * count].chunks(8) {
+      let lms =
+        lms_params(read_u32(chunk)).ok_or(KeypairError::InvalidSecretKey)?;
+      let ots = lmots_params(read_u32(&chunk[4..]))
+        .ok_or(KeypairError::InvalidSecretKey)?;
+      levels.push((lms, ots));
+    }
+    let off = 4 + 8 * count;
+    Self::from_seed(
+      &levels,
+      &bytes[off..off + IDENTIFIER_BYTES],
+      &bytes[off + IDENTIFIER_BYTES..],
+    )
+  }
+
+  fn top(&self) -> Tree<'_>
+  {
+    let (lms, ots) = &self.levels[0];
+    Tree {
+      lms,
+      ots,
+      id: self.id,
+      seed: self.seed,
+    }
+  }
+
+  /// Public key, u32(L) || top level LMS public key
+  pub fn public(&self) -> Vec<u8>
+  {
+    let mut pk = (self.levels.len() as u32).to_be_bytes().to_vec();
+    pk.extend_from_slice(&self.top().public());
+    pk
+  }
+
+  /// Number of signatures the key can produce
+  pub fn max_signatures(&self) -> u64
+  {
+    1 << self.levels.iter().map(|(lms, _)| lms.h).sum::<usize>()
+  }
+
+  /// Signs `msg` with the next unused one-time key. The index is retired
+  /// in `state` before the signature is computed.
+  pub fn sign<S: StateStore>(
+    &self,
+    msg: &[u8],
+    state: &mut S,
+  ) -> Result<Vec<u8>, StateError>
+  {
+    let index = reserve_index(state, self.max_signatures())?;
+    Ok(self.sign_index(index, msg))
+  }
+
+  fn sign_index(&self, index: u64, msg: &[u8]) -> Vec<u8>
+  {
+    let count = self.levels.len();
+    let mut sig = ((count - 1) as u32).to_be_bytes().to_vec();
+    let mut shift: usize = self.levels.iter().map(|(lms, _)| lms.h).sum();
+    let mut tree = self.top();
+    for level in 0..count {
+      shift -= tree.lms.h;
+      let q = ((index >> shift) & ((1 << tree.lms.h) - 1)) as u32;
+      if level + 1 == count {
+        tree.sign(&mut sig, q, msg);
+      } else {
+        let (lms, ots) = &self.levels[level + 1];
+        let child = tree.child(lms, ots, q);
+        let child_pk = child.public();
+        tree.sign(&mut sig, q, &child_pk);
+        sig.extend_from_slice(&child_pk);
+        tree = child;
+      }
+    }
+    sig
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  // Single level HSS key with LMS_SHAKE_M32_H5 / LMOTS_SHAKE_N32_W8 and
+  // its third signature over "crystals", produced by an independent
+  // implementation.
+  const HSS_PK: &str = "\
+    000000010000000f0000000cdac5f211588122bfb06986295864e1df39c46816\
+    919c92941a7660d3c8e04a8d9b8b41c55da406281fefb92f30aeeb3d";
+  const HSS_SIG: &str = "\
+    00000000000000020000000ca3aa11ba2347283dde42b13a738037aa6d64a640\
+    d3cd4beec48884917763a9d57363ab0559f846f516367bcb71a52876dbbdd731\
+    f22d10ccef1dc4dc7f47cdc31e54fedf3a9b8660abd815617fd2aade0f3ce180\
+    6027e070a91a525f39aefde364722fb56bb0830c87db797c12b52a503f161f51\
+    878bcaa4ee97a97a93ac472a536d1ab125af73333c4d60ae50d80f2574c49ff4\
+    259b01d9883f6b70ef4d7c69b7fbab84fbfaadf0cb983c21a27d33f18499d2c2\
+    dd7860e84fd9f6f452af4c07974207538887e674a14f73dbaa614e1289099730\
+    70a160d0a031f6fc65cfd5d0ac5c447f58b92c93d3adb5701f80250d3d67bef7\
+    0a248ae19ec5dd4a2a553a437ec07c84c4a55571eee79e03b9aca5ff65ae3f61\
+    46057f00604cb129975429bd94a5d1db74ab3c872a547058d6b2682456a3c526\
+    4c361a806dbf9c7d77b406a1d3507a6577b17256a8eaca5e0760dc541452dfa0\
+    ebc3e7da3ec654e4d7cc40b60b0668bcc5cf8e8384e8e1037b8e66f9b172eb1b\
+    f6e81b46c44acc763c375b719a1acd35a5c394d1f638a83fb8df677e09babe3b\
+    4c74a363e6bf9180ea0fc8c05ef885db782ae79f983c2e25cc60942b6723e6d4\
+    c84a2fb1bcdec6207335ba0955e164f189e1b17415a44cb8ae626fa94bd2ce3b\
+    ed56b0dea5f7af0433e87b4ecc0cb7a13f011914da16b6be68ddce7af3af11cc\
+    c6ed1b8f7520e46075feb6ffab6d6ea6a033480c4496d0ed6f58ba363faabee3\
+    3a876a59a7b47526ae17627ad43945458d094d2a29d4302edea5826fafd504a3\
+    487ca709f66487966cf4245945cae21a9db589119968f98ecf8d1beb50a65e99\
+    a04b5d9d885c96164bb5448b6748492e34fcdc1059406d2238bdc86c92af020e\
+    3acc98d605410e7a07eeca081f666318a2030ed7582fb222bb13cfa902978899\
+    0518ca01a2d1c701b6e00ffe4d7c2751638b9c75d236995b50bdfb456df2d56a\
+    dd6edf1ce876ff0647f43446a4c4758ce7b55fafdc443a362921aa0069a7d397\
+    979a1acce40306731dd6bb2930300a657c6b6f7d7686ec25527f5e631c2a3bc8\
+    bf0821c9729ad05186c85615335735b49dcba36ded824f0398c59b42811fa6fc\
+    6acb00b580ba5b012aa481557157d044fbbd7fb86215c7bae812f5f99d4fdd9f\
+    045c46c319d0e8facc81f8e4e4b1e3d267b845684d83a7e43d5a6dc993c8dff7\
+    b667dfc60ae8e371feb892ec4033f437680a9139aceb2269f806f1339b2b61ff\
+    0a0efd15ae9c3efa97fd26666d699d8c606ab99233f5e302b8c12dd3585b8adc\
+    2d8876b0ba1850eb9842c64df184d152873caa7020f08d62ef7b893530908879\
+    1d5b6ba1169acf5dfffedb12fd0932dd3b8f1b15f9e7388864b86b9d86687e26\
+    2aa4d9bacb55cfec9ec45c743f4f0c9ab4945a695d59655a5b68b9bd17a92315\
+    fca2e7136c983e13dfdcb1fb24a1abd61a7bb2aba5a0de4a33bd5002ceb6afbc\
+    dd222563abe8f10f853231a49b747b9dd65345a3c4798d7d1bb85df58ff24315\
+    53e9b803a2de66961934ea66031d717408ec3e5acd2ceb8a91b3eeb41461c9ff\
+    e941962ada20f82bb74967a50000000fa6b102c9e4fda2e72e3fecc3020c0863\
+    466424f084c3abb36119905bbed8acb2ab789925f25c8c9c787e518924ef9777\
+    5fabf10dae185d0cd2f546603ed89d885519e016fda02571863c93f1d50cd4fc\
+    d3eb06a5005a35c08bd53dc53a6d4705e44e8ea39875bc0cb2365a2647ca8b6a\
+    29b20a62cdd79353be363d6055967acd5a89fd2a1402973d083df891cd81391a\
+    500b37a0eb51b58ec083e00fda1e546c";
+
+  #[test]
+  fn hss_verify_kat()
+  {
+    let pk = hex::decode(HSS_PK).unwrap();
+    let mut sig = hex::decode(HSS_SIG).unwrap();
+    assert_eq!(hss_verify(&sig, b"crystals", &pk), Ok(()));
+    assert_eq!(lms_verify(&sig[4..], b"crystals", &pk[4..]), Ok(()));
+    assert_eq!(hss_verify(&sig, b"crystalz", &pk), Err(SignError::Verify));
+    assert_eq!(
+      hss_verify(&sig[1..], b"crystals", &pk),
+      Err(SignError::Input)
+    );
+
+    let last = sig.len() - 1;
+    sig[last] ^= 1;
+    assert_eq!(hss_verify(&sig, b"crystals", &pk), Err(SignError::Verify));
+  }
+
+  #[cfg(feature = "offchain")]
+  #[test]
+  fn hss_sign_verify()
+  {
+    let levels = [
+      (LMS_SHAKE_M32_H5, LMOTS_SHAKE_N32_W4),
+      (LMS_SHAKE_M24_H5, LMOTS_SHAKE_N24_W4),
+    ];
+    let key =
+      HssPrivateKey::from_seed(&levels, &[1u8; 16], &[2u8; 32]).unwrap();
+    assert_eq!(key.max_signatures(), 1024);
+    assert_eq!(HssPrivateKey::from_bytes(&key.to_bytes()).unwrap(), key);
+
+    let path =
+      std::env::temp_dir().join(format!("crystals-hss-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let pk = key.public();
+    let mut state = FileState::create(&path, &pk).unwrap();
+    let first = key.sign(b"crystals", &mut state).unwrap();
+    let second = key.sign(b"crystals", &mut state).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(hss_verify(&first, b"crystals", &pk), Ok(()));
+    assert_eq!(hss_verify(&second, b"crystals", &pk), Ok(()));
+    assert_eq!(
+      hss_verify(&second, b"crystalz", &pk),
+      Err(SignError::Verify)
+    );
+  }
+}
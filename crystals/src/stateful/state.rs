@@ -0,0 +1,340 @@
+//! Persistent tracking of one-time key indices.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::fips202::sha3_256;
+
+/// Length of the key id at the start of a state file
+const KEY_ID_BYTES: usize = 32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError
+{
+  /// Every one-time key of the private key has been used
+  Exhausted,
+  /// The store was asked to move back to an index already handed out
+  Reused,
+  /// The state could not be read or written
+  Storage,
+  /// The state file belongs to another key
+  WrongKey,
+}
+
+/// Storage for the next unused one-time key index of a private key.
+///
+/// `store` must only return once the new value is durable, and must refuse
+/// to move the index backwards.
+pub trait StateStore
+{
+  /// Returns the next unused index
+  fn load(&mut self) -> Result<u64, StateError>;
+  /// Records `next` as the next unused index
+  fn store(&mut self, next: u64) -> Result<(), StateError>;
+
+  /// Runs `f` while no other writer can load or store the index, so two
+  /// signers can't both read the same index before either records the
+  /// next one. The default only calls `f`, which is enough for a store
+  /// that a single handle owns.
+  fn exclusive<T, F>(&mut self, f: F) -> Result<T, StateError>
+  where
+    Self: Sized,
+    F: FnOnce(&mut Self) -> Result<T, StateError>,
+  {
+    f(self)
+  }
+}
+
+/// Index kept in a file as the SHA3-256 of the public key it belongs to,
+/// followed by the index as 8 big-endian bytes. Updates are written to a
+/// fresh temporary file, synced and renamed over the old state, and the
+/// directory is synced after the rename.
+///
+/// On unix, [`StateStore::exclusive`] holds an exclusive `flock` on a
+/// `.lock` file next to the state, so any number of processes and handles
+/// can sign with the same state. The lock file is left in place; removing
+/// it while a signer runs defeats the lock. Other platforms take no lock,
+/// and only one `FileState` may use a state file at a time.
+pub struct FileState
+{
+  path: PathBuf,
+  key_id: [u8; KEY_ID_BYTES],
+}
+
+/// Distinguishes temporary files of concurrent updates within a process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn key_id(public_key: &[u8]) -> [u8; KEY_ID_BYTES]
+{
+  let mut id = [0u8; KEY_ID_BYTES];
+  sha3_256(&mut id, public_key, public_key.len());
+  id
+}
+
+/// Makes a file created or renamed in the directory of `path` durable
+fn sync_parent(path: &Path) -> io::Result<()>
+{
+  #[cfg(unix)]
+  {
+    let parent = match path.parent() {
+      Some(dir) if !dir.as_os_str().is_empty() => dir,
+      _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()?;
+  }
+  #[cfg(not(unix))]
+  let _ = path;
+  Ok(())
+}
+
+/// Blocks until this process holds an exclusive lock on `file`. The lock
+/// is released when `file` is closed.
+#[cfg(unix)]
+fn lock_exclusive(file: &File) -> io::Result<()>
+{
+  use std::os::unix::io::AsRawFd;
+
+  loop {
+    // SAFETY: the descriptor belongs to `file`, which outlives the call
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+      return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    if err.kind() != io::ErrorKind::Interrupted {
+      return Err(err);
+    }
+  }
+}
+
+/// Writes the state to a new file at `path`, failing if it exists
+fn write_new(path: &Path, contents: &[u8]) -> io::Result<()>
+{
+  let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+  file.write_all(contents)?;
+  file.sync_all()
+}
+
+impl FileState
+{
+  /// Creates a state file for the key with `public_key`, starting at
+  /// index 0. Fails if the file already exists, so an existing state can't
+  /// be reset by accident.
+  pub fn create<P: AsRef<Path>>(
+    path: P,
+    public_key: &[u8],
+  ) -> Result<FileState, StateError>
+  {
+    let state = FileState {
+      path: path.as_ref().to_path_buf(),
+      key_id: key_id(public_key),
+    };
+    write_new(&state.path, &state.contents(0))
+      .and_then(|_| sync_parent(&state.path))
+      .map_err(|_| StateError::Storage)?;
+    Ok(state)
+  }
+
+  /// Opens an existing state file, which must have been created for the
+  /// key with `public_key`
+  pub fn open<P: AsRef<Path>>(
+    path: P,
+    public_key: &[u8],
+  ) -> Result<FileState, StateError>
+  {
+    let mut state = FileState {
+      path: path.as_ref().to_path_buf(),
+      key_id: key_id(public_key),
+    };
+    state.load()?;
+    Ok(state)
+  }
+
+  #[cfg(unix)]
+  fn lock_path(&self) -> PathBuf
+  {
+    let mut path = self.path.clone().into_os_string();
+    path.push(".lock");
+    PathBuf::from(path)
+  }
+
+  fn contents(&self, next: u64) -> [u8; KEY_ID_BYTES + 8]
+  {
+    let mut bytes = [0u8; KEY_ID_BYTES + 8];
+    bytes[..KEY_ID_BYTES].copy_from_slice(&self.key_id);
+    bytes[KEY_ID_BYTES..].copy_from_slice(&next.to_be_bytes());
+    bytes
+  }
+}
+
+impl StateStore for FileState
+{
+  fn load(&mut self) -> Result<u64, StateError>
+  {
+    let mut bytes = [0u8; KEY_ID_BYTES + 8];
+    File::open(&self.path)
+      .and_then(|mut file| file.read_exact(&mut bytes))
+      .map_err(|_| StateError::Storage)?;
+    if bytes[..KEY_ID_BYTES] != self.key_id {
+      return Err(StateError::WrongKey);
+    }
+    let mut index = [0u8; 8];
+    index.copy_from_slice(&bytes[KEY_ID_BYTES..]);
+    Ok(u64::from_be_bytes(index))
+  }
+
+  fn store(&mut self, next: u64) -> Result<(), StateError>
+  {
+    if next <= self.load()? {
+      return Err(StateError::Reused);
+    }
+    let mut tmp = self.path.clone().into_os_string();
+    tmp.push(format!(
+      ".{}.{}.tmp",
+      std::process::id(),
+      TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = PathBuf::from(tmp);
+    write_new(&tmp, &self.contents(next))
+      .and_then(|_| fs::rename(&tmp, &self.path))
+      .and_then(|_| sync_parent(&self.path))
+      .map_err(|_| {
+        let _ = fs::remove_file(&tmp);
+        StateError::Storage
+      })
+  }
+
+  fn exclusive<T, F>(&mut self, f: F) -> Result<T, StateError>
+  where
+    F: FnOnce(&mut Self) -> Result<T, StateError>,
+  {
+    #[cfg(unix)]
+    let _lock = OpenOptions::new()
+      .write(true)
+      .create(true)
+      .truncate(false)
+      .open(self.lock_path())
+      .and_then(|file| lock_exclusive(&file).map(|_| file))
+      .map_err(|_| StateError::Storage)?;
+    f(self)
+  }
+}
+
+/// Retires and returns the next unused index out of `max`
+pub(super) fn reserve_index<S: StateStore>(
+  state: &mut S,
+  max: u64,
+) -> Result<u64, StateError>
+{
+  state.exclusive(|state| {
+    let index = state.load()?;
+    if index >= max {
+      return Err(StateError::Exhausted);
+    }
+    state.store(index + 1)?;
+    Ok(index)
+  })
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  struct Fixed(u64);
+
+  impl StateStore for Fixed
+  {
+    fn load(&mut self) -> Result<u64, StateError>
+    {
+      Ok(self.0)
+    }
+
+    fn store(&mut self, next: u64) -> Result<(), StateError>
+    {
+      self.0 = next;
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn reserve_until_exhausted()
+  {
+    let mut state = Fixed(2);
+    assert_eq!(reserve_index(&mut state, 4), Ok(2));
+    assert_eq!(reserve_index(&mut state, 4), Ok(3));
+    assert_eq!(reserve_index(&mut state, 4), Err(StateError::Exhausted));
+  }
+
+  #[test]
+  fn file_state_never_moves_back()
+  {
+    let path = std::env::temp_dir()
+      .join(format!("crystals-state-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut state = FileState::create(&path, b"key").unwrap();
+    assert!(FileState::create(&path, b"key").is_err());
+
+    assert_eq!(reserve_index(&mut state, 10), Ok(0));
+    assert_eq!(reserve_index(&mut state, 10), Ok(1));
+    assert_eq!(state.store(1), Err(StateError::Reused));
+
+    let mut reopened = FileState::open(&path, b"key").unwrap();
+    assert_eq!(reserve_index(&mut reopened, 10), Ok(2));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn file_state_belongs_to_one_key()
+  {
+    let dir = std::env::temp_dir()
+      .join(format!("crystals-state-key-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("state");
+    let mut state = FileState::create(&path, b"key").unwrap();
+    assert_eq!(reserve_index(&mut state, 10), Ok(0));
+
+    assert!(matches!(
+      FileState::open(&path, b"other key"),
+      Err(StateError::WrongKey)
+    ));
+    // no temporary files are left behind
+    let left = fs::read_dir(&dir).unwrap().count();
+    assert_eq!(left, if cfg!(unix) { 2 } else { 1 });
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn file_state_shared_between_handles()
+  {
+    let dir = std::env::temp_dir()
+      .join(format!("crystals-state-shared-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("state");
+    FileState::create(&path, b"key").unwrap();
+
+    // each handle has its own lock file descriptor, as separate
+    // processes would
+    let threads: Vec<_> = (0..4)
+      .map(|_| {
+        let mut state = FileState::open(&path, b"key").unwrap();
+        std::thread::spawn(move || {
+          (0..25)
+            .map(|_| reserve_index(&mut state, 1000).unwrap())
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect();
+    let mut indices: Vec<u64> = threads
+      .into_iter()
+      .flat_map(|t| t.join().unwrap())
+      .collect();
+    indices.sort_unstable();
+    assert_eq!(indices, (0..100).collect::<Vec<_>>());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
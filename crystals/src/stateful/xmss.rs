@@ -0,0 +1,757 @@
+//! XMSS (RFC 8391) with the SHAKE256 parameter sets of SP 800-208
+//! section 5. Keys and signatures use the layout of the XMSS reference
+//! implementation, with the OID in front of the public key.
+
+use super::{read_u32, shake256_parts};
+use crate::SignError;
+
+#[cfg(feature = "offchain")]
+use super::state::*;
+#[cfg(feature = "offchain")]
+use crate::KeypairError;
+
+const MAX_N: usize = 32;
+const WOTS_W: u32 = 16;
+const WOTS_LOG_W: usize = 4;
+const WOTS_LEN2: usize = 3;
+const MAX_WOTS_LEN: usize = 2 * MAX_N + WOTS_LEN2;
+
+const PADDING_F: u8 = 0;
+const PADDING_H: u8 = 1;
+const PADDING_HASH: u8 = 2;
+const PADDING_PRF: u8 = 3;
+#[cfg(feature = "offchain")]
+const PADDING_PRF_KEYGEN: u8 = 4;
+
+const ADDR_TYPE_OTS: u32 = 0;
+const ADDR_TYPE_LTREE: u32 = 1;
+const ADDR_TYPE_HASHTREE: u32 = 2;
+
+/// XMSS parameter set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XmssParams
+{
+  pub oid: u32,
+  pub n: usize,
+  pub h: usize,
+  /// Length of the domain separating prefix of every hash call
+  pub padding: usize,
+}
+
+impl XmssParams
+{
+  pub const fn wots_len(&self) -> usize
+  {
+    2 * self.n + WOTS_LEN2
+  }
+
+  pub const fn public_key_bytes(&self) -> usize
+  {
+    4 + 2 * self.n
+  }
+
+  pub const fn signature_bytes(&self) -> usize
+  {
+    4 + self.n + (self.wots_len() + self.h) * self.n
+  }
+}
+
+pub const XMSS_SHAKE256_10_256: XmssParams = XmssParams {
+  oid: 0x10,
+  n: 32,
+  h: 10,
+  padding: 32,
+};
+pub const XMSS_SHAKE256_16_256: XmssParams = XmssParams {
+  oid: 0x11,
+  n: 32,
+  h: 16,
+  padding: 32,
+};
+pub const XMSS_SHAKE256_20_256: XmssParams = XmssParams {
+  oid: 0x12,
+  n: 32,
+  h: 20,
+  padding: 32,
+};
+pub const XMSS_SHAKE256_10_192: XmssParams = XmssParams {
+  oid: 0x13,
+  n: 24,
+  h: 10,
+  padding: 4,
+};
+pub const XMSS_SHAKE256_16_192: XmssParams = XmssParams {
+  oid: 0x14,
+  n: 24,
+  h: 16,
+  padding: 4,
+};
+pub const XMSS_SHAKE256_20_192: XmssParams = XmssParams {
+  oid: 0x15,
+  n: 24,
+  h: 20,
+  padding: 4,
+};
+
+const XMSS_PARAMS: [XmssParams; 6] = [
+  XMSS_SHAKE256_10_256,
+  XMSS_SHAKE256_16_256,
+  XMSS_SHAKE256_20_256,
+  XMSS_SHAKE256_10_192,
+  XMSS_SHAKE256_16_192,
+  XMSS_SHAKE256_20_192,
+];
+
+pub fn xmss_params(oid: u32) -> Option<XmssParams>
+{
+  XMSS_PARAMS.iter().copied().find(|p| p.oid == oid)
+}
+
+/// Hash address, RFC 8391 section 2.5. Changing the type clears the
+/// words that follow it.
+#[derive(Clone, Copy, Default)]
+struct Addr([u32; 8]);
+
+impl Addr
+{
+  fn set_type(&mut self, t: u32)
+  {
+    self.0[3] = t;
+    self.0[4..].fill(0);
+  }
+
+  /// OTS address, L-tree address or padding
+  fn set_word4(&mut self, x: u32)
+  {
+    self.0[4] = x;
+  }
+
+  /// Chain address or tree height
+  fn set_word5(&mut self, x: u32)
+  {
+    self.0[5] = x;
+  }
+
+  /// Hash address or tree index
+  fn set_word6(&mut self, x: u32)
+  {
+    self.0[6] = x;
+  }
+
+  fn word6(&self) -> u32
+  {
+    self.0[6]
+  }
+
+  fn set_key_and_mask(&mut self, x: u32)
+  {
+    self.0[7] = x;
+  }
+
+  fn to_bytes(self) -> [u8; 32]
+  {
+    let mut out = [0u8; 32];
+    for (o, w) in out.chunks_mut(4).zip(self.0.iter()) {
+      o.copy_from_slice(&w.to_be_bytes());
+    }
+    out
+  }
+}
+
+fn hash(p: &XmssParams, out: &mut [u8], padding: u8, parts: &[&[u8]])
+{
+  let mut prefixed = [&[][..]; 5];
+  let mut pad = [0u8; MAX_N];
+  pad[p.padding - 1] = padding;
+  prefixed[0] = &pad[..p.padding];
+  prefixed[1..=parts.len()].copy_from_slice(parts);
+  shake256_parts(&mut out[..p.n], &prefixed[..=parts.len()]);
+}
+
+fn prf(p: &XmssParams, out: &mut [u8], key: &[u8], addr: &Addr)
+{
+  hash(p, out, PADDING_PRF, &[key, &addr.to_bytes()]);
+}
+
+/// F with key and bitmask derived from the public seed
+fn thash_f(
+  p: &XmssParams,
+  out: &mut [u8],
+  input: &[u8],
+  seed: &[u8],
+  addr: &mut Addr,
+)
+{
+  let n = p.n;
+  let mut key = [0u8; MAX_N];
+  let mut masked = [0u8; MAX_N];
+  addr.set_key_and_mask(0);
+  prf(p, &mut key, seed, addr);
+  addr.set_key_and_mask(1);
+  prf(p, &mut masked, seed, addr);
+  for (m, x) in masked.iter_mut().zip(input.iter()).take(n) {
+    *m ^= x;
+  }
+  hash(p, out, PADDING_F, &[&key[..n], &masked[..n]]);
+}
+
+/// RAND_HASH(left, right), RFC 8391 algorithm 7
+fn thash_h(
+  p: &XmssParams,
+  out: &mut [u8],
+  left: &[u8],
+  right: &[u8],
+  seed: &[u8],
+  addr: &mut Addr,
+)
+{
+  let n = p.n;
+  let mut key = [0u8; MAX_N];
+  let mut masked = [0u8; 2 * MAX_N];
+  addr.set_key_and_mask(0);
+  prf(p, &mut key, seed, addr);
+  addr.set_key_and_mask(1);
+  prf(p, &mut masked[..n], seed, addr);
+  addr.set_key_and_mask(2);
+  prf(p, &mut masked[n..2 * n], seed, addr);
+  for i in 0..n {
+    masked[i] ^= left[i];
+    masked[n + i] ^= right[i];
+  }
+  hash(p, out, PADDING_H, &[&key[..n], &masked[..2 * n]]);
+}
+
+/// H_msg(r || root || toByte(idx, n), M)
+fn h_msg(
+  p: &XmssParams,
+  out: &mut [u8],
+  r: &[u8],
+  root: &[u8],
+  idx: u32,
+  msg: &[u8],
+)
+{
+  let mut idx_bytes = [0u8; MAX_N];
+  idx_bytes[p.n - 4..p.n].copy_from_slice(&idx.to_be_bytes());
+  hash(p, out, PADDING_HASH, &[r, root, &idx_bytes[..p.n], msg]);
+}
+
+/// Chaining function, RFC 8391 algorithm 2
+fn chain(
+  p: &XmssParams,
+  x: &mut [u8],
+  start: u32,
+  steps: u32,
+  seed: &[u8],
+  addr: &mut Addr,
+)
+{
+  let mut tmp = [0u8; MAX_N];
+  for i in start..(start + steps).min(WOTS_W) {
+    addr.set_word6(i);
+    tmp[..p.n].copy_from_slice(&x[..p.n]);
+    thash_f(p, x, &tmp, seed, addr);
+  }
+}
+
+/// Message and checksum digits in base w, RFC 8391 algorithm 5
+fn chain_lengths(p: &XmssParams, out: &mut [u32], msg: &[u8])
+{
+  let len1 = 2 * p.n;
+  for (i, b) in msg.iter().take(p.n).enumerate() {
+    out[2 * i] = (*b >> 4) as u32;
+    out[2 * i + 1] = (*b & 15) as u32;
+  }
+  let mut csum: u32 = out[..len1].iter().map(|d| WOTS_W - 1 - d).sum();
+  csum <<= 8 - ((WOTS_LEN2 * WOTS_LOG_W) % 8);
+  for i in 0..WOTS_LEN2 {
+    out[len1 + i] = (csum >> (12 - 4 * i)) & 15;
+  }
+}
+
+/// WOTS+ public key from a signature, RFC 8391 algorithm 6
+fn wots_pk_from_sig(
+  p: &XmssParams,
+  pk: &mut [u8],
+  sig: &[u8],
+  msg: &[u8],
+  seed: &[u8],
+  addr: &mut Addr,
+)
+{
+  let n = p.n;
+  let mut lengths = [0u32; MAX_WOTS_LEN];
+  chain_lengths(p, &mut lengths, msg);
+  for i in 0..p.wots_len() {
+    addr.set_word5(i as u32);
+    pk[i * n..(i + 1) * n].copy_from_slice(&sig[i * n..(i + 1) * n]);
+    chain(
+      p,
+      &mut pk[i * n..(i + 1) * n],
+      lengths[i],
+      WOTS_W - 1 - lengths[i],
+      seed,
+      addr,
+    );
+  }
+}
+
+/// Compresses a WOTS+ public key into a leaf, RFC 8391 algorithm 8
+fn ltree(
+  p: &XmssParams,
+  leaf: &mut [u8],
+  pk: &mut [u8],
+  seed: &[u8],
+  addr: &mut Addr,
+)
+{
+  let n = p.n;
+  let mut len = p.wots_len();
+  let mut height = 0;
+  let mut node = [0u8; MAX_N];
+  addr.set_word5(height);
+  while len > 1 {
+    for i in 0..len / 2 {
+      addr.set_word6(i as u32);
+      thash_h(
+        p,
+        &mut node,
+        &pk[2 * i * n..(2 * i + 1) * n],
+        &pk[(2 * i + 1) * n..(2 * i + 2) * n],
+        seed,
+        addr,
+      );
+      pk[i * n..(i + 1) * n].copy_from_slice(&node[..n]);
+    }
+    if len % 2 == 1 {
+      pk.copy_within((len - 1) * n..len * n, (len / 2) * n);
+    }
+    len = len.div_ceil(2);
+    height += 1;
+    addr.set_word5(height);
+  }
+  leaf[..n].copy_from_slice(&pk[..n]);
+}
+
+/// Root from a leaf and its authentication path, RFC 8391 algorithm 13
+fn root_from_leaf(
+  p: &XmssParams,
+  root: &mut [u8],
+  leaf: &[u8],
+  idx: u32,
+  auth: &[u8],
+  seed: &[u8],
+)
+{
+  let n = p.n;
+  let mut addr = Addr::default();
+  addr.set_type(ADDR_TYPE_HASHTREE);
+  addr.set_word6(idx);
+  let mut node = [0u8; MAX_N];
+  let mut next = [0u8; MAX_N];
+  node[..n].copy_from_slice(&leaf[..n]);
+  for k in 0..p.h {
+    let sibling = &auth[k * n..(k + 1) * n];
+    addr.set_word5(k as u32);
+    let tree_index = addr.word6();
+    if (idx >> k) & 1 == 0 {
+      addr.set_word6(tree_index / 2);
+      thash_h(p, &mut next, &node[..n], sibling, seed, &mut addr);
+    } else {
+      addr.set_word6((tree_index - 1) / 2);
+      thash_h(p, &mut next, sibling, &node[..n], seed, &mut addr);
+    }
+    node[..n].copy_from_slice(&next[..n]);
+  }
+  root[..n].copy_from_slice(&node[..n]);
+}
+
+/// Verifies an XMSS signature, RFC 8391 algorithm 14. The public key is
+/// OID || root || SEED and the signature idx || r || WOTS+ signature ||
+/// authentication path.
+pub fn xmss_verify(sig: &[u8], msg: &[u8], pk: &[u8]) -> Result<(), SignError>
+{
+  if pk.len() < 4 {
+    return Err(SignError::Input);
+  }
+  let p = xmss_params(read_u32(pk)).ok_or(SignError::Input)?;
+  verify_with(&p, sig, msg, pk)
+}
+
+fn verify_with(
+  p: &XmssParams,
+  sig: &[u8],
+  msg: &[u8],
+  pk: &[u8],
+) -> Result<(), SignError>
+{
+  if pk.len() != p.public_key_bytes() || sig.len() != p.signature_bytes() {
+    return Err(SignError::Input);
+  }
+  let n = p.n;
+  let (root, seed) = (&pk[4..4 + n], &pk[4 + n..4 + 2 * n]);
+  let idx = read_u32(sig);
+  if idx as u64 >= 1u64 << p.h {
+    return Err(SignError::Verify);
+  }
+  let r = &sig[4..4 + n];
+  let sig_ots = &sig[4 + n..4 + n + p.wots_len() * n];
+  let auth = &sig[4 + n + p.wots_len() * n..];
+
+  let mut digest = [0u8; MAX_N];
+  h_msg(p, &mut digest, r, root, idx, msg);
+
+  let mut addr = Addr::default();
+  addr.set_type(ADDR_TYPE_OTS);
+  addr.set_word4(idx);
+  let mut wots_pk = [0u8; MAX_WOTS_LEN * MAX_N];
+  wots_pk_from_sig(p, &mut wots_pk, sig_ots, &digest, seed, &mut addr);
+
+  addr.set_type(ADDR_TYPE_LTREE);
+  addr.set_word4(idx);
+  let mut leaf = [0u8; MAX_N];
+  ltree(p, &mut leaf, &mut wots_pk, seed, &mut addr);
+
+  let mut candidate = [0u8; MAX_N];
+  root_from_leaf(p, &mut candidate, &leaf, idx, auth, seed);
+  if candidate[..n] != *root {
+    return Err(SignError::Verify);
+  }
+  Ok(())
+}
+
+/// XMSS private key. The key itself is immutable; the index of the next
+/// one-time key lives in a `StateStore`.
+///
+/// Signing recomputes the tree to build the authentication path, so it
+/// costs 2^h WOTS+ key generations.
+#[cfg(feature = "offchain")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct XmssPrivateKey
+{
+  params: XmssParams,
+  /// SK_SEED || SK_PRF || SEED || root
+  secret: Box<[u8]>,
+}
+
+/// Secret key elided
+#[cfg(feature = "offchain")]
+impl std::fmt::Debug for XmssPrivateKey
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(
+      f,
+      "{:?}\npublic: {:?}\nsecret: <elided>",
+      self.params,
+      self.public()
+    )
+  }
+}
+
+#[cfg(feature = "offchain")]
+impl XmssPrivateKey
+{
+  /// Generates a private key from system randomness
+  pub fn generate(params: XmssParams) -> XmssPrivateKey
+  {
+    let mut seed = [0u8; 3 * MAX_N];
+    crate::randombytes::randombytes(&mut seed, 3 * params.n);
+    Self::derive(params, &seed[..3 * params.n])
+  }
+
+  /// Derives a private key from SK_SEED || SK_PRF || SEED
+  pub fn from_seed(
+    params: XmssParams,
+    seed: &[u8],
+  ) -> Result<XmssPrivateKey, KeypairError>
+  {
+    if seed.len() != 3 * params.n {
+      return Err(KeypairError::InvalidSecretKey);
+    }
+    Ok(Self::derive(params, seed))
+  }
+
+  fn derive(params: XmssParams, seed: &[u8]) -> XmssPrivateKey
+  {
+    let n = params.n;
+    let mut secret = vec![0u8; 4 * n].into_boxed_slice();
+    secret[..3 * n].copy_from_slice(seed);
+    let mut key = XmssPrivateKey { params, secret };
+    let mut root = [0u8; MAX_N];
+    key.treehash(&mut root, &mut [], 0);
+    key.secret[3 * n..].copy_from_slice(&root[..n]);
+    key
+  }
+
+  /// Serialized key: OID || SK_SEED || SK_PRF || SEED || root
+  pub fn to_bytes(&self) -> Vec<u8>
+  {
+    let mut out = self.params.oid.to_be_bytes().to_vec();
+    out.extend_from_slice(&self.secret);
+    out
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<XmssPrivateKey, KeypairError>
+  {
+    if bytes.len() < 4 {
+      return Err(KeypairError::InvalidSecretKey);
+    }
+    let params =
+      xmss_params(read_u32(bytes)).ok_or(KeypairError::InvalidSecretKey)?;
+    if bytes.len() != 4 + 4 * params.n {
+      return Err(KeypairError::InvalidSecretKey);
+    }
+    Ok(XmssPrivateKey {
+      params,
+      secret: bytes[4..].into(),
+    })
+  }
+
+  pub fn params(&self) -> &XmssParams
+  {
+    &self.params
+  }
+
+  /// Public key, OID || root || SEED
+  pub fn public(&self) -> Vec<u8>
+  {
+    let n = self.params.n;
+    let mut pk = self.params.oid.to_be_bytes().to_vec();
+    pk.extend_from_slice(&self.secret[3 * n..4 * n]);
+    pk.extend_from_slice(&self.secret[2 * n..3 * n]);
+    pk
+  }
+
+  /// Number of signatures the key can produce
+  pub fn max_signatures(&self) -> u64
+  {
+    1 << self.params.h
+  }
+
+  /// Signs `msg` with the next unused one-time key. The index is retired
+  /// in `state` before the signature is computed.
+  pub fn sign<S: StateStore>(
+    &self,
+    msg: &[u8],
+    state: &mut S,
+  ) -> Result<Vec<u8>, StateError>
+  {
+    let index = reserve_index(state, self.max_signatures())?;
+    Ok(self.sign_index(index as u32, msg))
+  }
+
+  /// RFC 8391 algorithm 12 with r = PRF(SK_PRF, toByte(idx, 32))
+  fn sign_index(&self, idx: u32, msg: &[u8]) -> Vec<u8>
+  {
+    let p = &self.params;
+    let n = p.n;
+    let (sk_prf, seed) = (&self.secret[n..2 * n], &self.secret[2 * n..3 * n]);
+    let root = &self.secret[3 * n..4 * n];
+    let mut sig = vec![0u8; p.signature_bytes()];
+    sig[..4].copy_from_slice(&idx.to_be_bytes());
+
+    let mut idx_bytes = [0u8; 32];
+    idx_bytes[28..].copy_from_slice(&idx.to_be_bytes());
+    let mut r = [0u8; MAX_N];
+    hash(p, &mut r, PADDING_PRF, &[sk_prf, &idx_bytes]);
+    sig[4..4 + n].copy_from_slice(&r[..n]);
+
+    let mut digest = [0u8; MAX_N];
+    h_msg(p, &mut digest, &r[..n], root, idx, msg);
+
+    let mut lengths = [0u32; MAX_WOTS_LEN];
+    chain_lengths(p, &mut lengths, &digest);
+    let mut addr = Addr::default();
+    addr.set_type(ADDR_TYPE_OTS);
+    addr.set_word4(idx);
+    let sig_ots = &mut sig[4 + n..4 + n + p.wots_len() * n];
+    for i in 0..p.wots_len() {
+      let x = &mut sig_ots[i * n..(i + 1) * n];
+      self.wots_secret(x, i as u32, &mut addr);
+      chain(p, x, 0, lengths[i], seed, &mut addr);
+    }
+
+    let mut root = [0u8; MAX_N];
+    self.treehash(&mut root, &mut sig[4 + n + p.wots_len() * n..], idx);
+    sig
+  }
+
+  /// WOTS+ secret key element i, PRF_keygen(SK_SEED, SEED || ADRS) as in
+  /// SP 800-208 section 5.1
+  fn wots_secret(&self, out: &mut [u8], i: u32, addr: &mut Addr)
+  {
+    let n = self.params.n;
+    let (sk_seed, seed) = (&self.secret[..n], &self.secret[2 * n..3 * n]);
+    addr.set_word5(i);
+    addr.set_word6(0);
+    addr.set_key_and_mask(0);
+    hash(
+      &self.params,
+      out,
+      PADDING_PRF_KEYGEN,
+      &[sk_seed, seed, &addr.to_bytes()],
+    );
+  }
+
+  fn leaf(&self, out: &mut [u8], idx: u32)
+  {
+    let p = &self.params;
+    let n = p.n;
+    let seed = &self.secret[2 * n..3 * n];
+    let mut addr = Addr::default();
+    addr.set_type(ADDR_TYPE_OTS);
+    addr.set_word4(idx);
+    let mut pk = [0u8; MAX_WOTS_LEN * MAX_N];
+    for i in 0..p.wots_len() {
+      let x = &mut pk[i * n..(i + 1) * n];
+      self.wots_secret(x, i as u32, &mut addr);
+      chain(p, x, 0, WOTS_W - 1, seed, &mut addr);
+    }
+    addr.set_type(ADDR_TYPE_LTREE);
+    addr.set_word4(idx);
+    ltree(p, out, &mut pk, seed, &mut addr);
+  }
+
+  /// Root of the tree and, if `auth` is not empty, the authentication path
+  /// of leaf `idx`, RFC 8391 algorithm 9
+  fn treehash(&self, root: &mut [u8], auth: &mut [u8], idx: u32)
+  {
+    let p = &self.params;
+    let n = p.n;
+    let seed = &self.secret[2 * n..3 * n];
+    let mut stack = vec![0u8; (p.h + 1) * n];
+    let mut heights = vec![0u32; p.h + 1];
+    let mut top = 0;
+    let mut addr = Addr::default();
+    addr.set_type(ADDR_TYPE_HASHTREE);
+    let mut node = [0u8; MAX_N];
+    for i in 0..1u32 << p.h {
+      self.leaf(&mut stack[top * n..(top + 1) * n], i);
+      heights[top] = 0;
+      top += 1;
+      if !auth.is_empty() && i == idx ^ 1 {
+        auth[..n].copy_from_slice(&stack[(top - 1) * n..top * n]);
+      }
+      while top >= 2 && heights[top - 1] == heights[top - 2] {
+        let height = heights[top - 1];
+        let tree_index = i >> (height + 1);
+        addr.set_word5(height);
+        addr.set_word6(tree_index);
+        let (left, right) = stack[(top - 2) * n..top * n].split_at(n);
+        thash_h(p, &mut node, left, right, seed, &mut addr);
+        top -= 1;
+        stack[(top - 1) * n..top * n].copy_from_slice(&node[..n]);
+        heights[top - 1] = height + 1;
+        let level = height as usize + 1;
+        if !auth.is_empty() && level < p.h && (idx >> level) ^ 1 == tree_index {
+          auth[level * n..(level + 1) * n].copy_from_slice(&node[..n]);
+        }
+      }
+    }
+    root[..n].copy_from_slice(&stack[..n]);
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  // XMSS-SHAKE256_10_192 key from the seed 00..47 and its first signature
+  // over "crystals", produced by an independent implementation.
+  const PK: &str = "\
+    00000013bbf748c8607840958c52df9cdaa1f8705dd8e4c87d3e54a830313233\
+    3435363738393a3b3c3d3e3f4041424344454647";
+  const SIG: &str = "\
+    00000000f4a0e7d22b1dd3058feb5c4802620a058763a0dfb66d4916464ed766\
+    2112b8a9896249e917d4e28114352f4bff69d85ab381e6637ecb490fd24116cf\
+    6fce0202e6e7b7fea2e4bbee2ef67d892c82116450987c80919e9a64565a4242\
+    4a9a19377c00be51ee85ad256a554506393d5ab8dd8ad4d2c966ec74398595d1\
+    7e4af83b4f1678c61fa8fdfe45459c076ae6739641792bb24dcf4fc372b5df9d\
+    61fc35ed25580e76cc3ca54c4b63e6a057b498e9fde18b34283bb2f046b04ba1\
+    b5ee8db38a2b67d4d0edb47b9ab23c87116202cd0db05498fc6f89ebffd1bd94\
+    5fd6d69f99041968a27382752ab157ff2f465b3ac390815726c3b3253aa09a09\
+    b1806ba20283e9e4b4122cc8b77f9dc70955b6e4382a3de6a91f89738fe84585\
+    d7b201cb03cba05399c286baf826aa5a1804434db857f2ad7ed8ffe82a76182c\
+    aad4fb2f530d26c7c5a3ad693c30f08acd15c0fc06c40244b9ce8c0e0bc7de38\
+    c04a3ede582a7a3cd3269ffeaf558d39ba8f1533ea3f1c7a94b67bd6b24bed74\
+    d5b45272436f26a408ef6c0239761285ab9207b814ac8884492c069af4984817\
+    c3cd0e70546fa73d0d7a078e0b51a3e595b565add52e00805c7ea39cd4c863a8\
+    d858c2834c6207482c230d8150d9175edfbfe7992524fdf603b03a4841de3c78\
+    7130345eb7794aa1506f2f6ad0fe45dee7a33b80f6b82f71f7f0d1dcc1422dfb\
+    37569bd91025bd66c242dbfe646461e8efb83c480b7ed3116415a0be39bb76eb\
+    9ba22e19badab8295f7d0dd8bd9c2c5980d83a4648602abfe4e798ba3a6b5356\
+    e8e8778bce9f4313c47531c7fc3150675da4affa3c3c4537fb806ba4cf11ea76\
+    2ced664f5093410742ed6d6135235be5d0b275dcc4205129dc468f1e77dfef7e\
+    1f0e284537c34f3d8c27e47fdba48be612a37c28ec111c43dc9959df7afd7d79\
+    e94c6afa7e7550673cd24f7680399e9d579a47fdca1fd68f8e1cf54772c33b2b\
+    e4fdd08c3c0c16781c944b008716b69a985baa27851664421011ac973a90dfec\
+    e91c06db2bafafe1f73fb1464d957e96490b2fb2e99bdf0ec8a5f0ef4136131f\
+    9d010b35cb9e07c47c4c5b4d164007a538ceba83c127672e65da65494c46e969\
+    34e15a3470f6cf7f0e80bde35fbacee51e94d61c254ebe961b424925dfb158aa\
+    ef238d7b4365537329b4096382115a167494f164f9c675b16d18a592288d0127\
+    4b594ec3b01bd78dd140d469081f8cebc05799877ae5971721a3d181f5b83455\
+    ec8c5ead9e0e52788815d3eabad3cd418115938d5fc4b25703f3da849403719a\
+    e4098d403196e622270dc470e77743ada7b2349a3bed6e28ce975f61bffc6ceb\
+    a52bf9a8aa868c0eff973c251a9d9798ed3848ac3319c996bf51fd9cb8c2d64e\
+    c21ed9e8247004f16745424c70b64de16c6476618c0ae8545a34d27f69568f12\
+    a885ab59bd27a28f073c87191e91d73478c0a32bd9075eb5597a25e5fa25814c\
+    268d31ce8626f0e7b14741895fa9393af3d54a06d52ed6112c403e5dbc8b49ed\
+    589d368c7c89a0af431101380472e96e68fa77ce9261c18bf6cee7a1939814d1\
+    59168c568ea6fcf30c34606356331d6503516638bb2a28df877d5ab8e94283ef\
+    d93098959742753127a7269404fa3c7e8d17e1e3edee45d9227d5dd57113b565\
+    36710676994f808dd33fca4ece609086c21b8e700c9feb04cd5beaafe1e8a0c8\
+    ce5ee764911f02c712c3dc0fc9388a458b0e63ad9d860e2d855e3fc3d2dee1b4\
+    c16f3714a19dfdf04a1d0ee75f03a3d5721e7ef926de9f151bd6074ad5a47306\
+    a9dbc110dbd53829103d21b8f8c441c7eb87640c16cbe798d08ab61db25d7c8f\
+    69b70e29b2f512bb460abb557dcc9300fc2d391ebed0fb0bbdd2c62b143c2faf\
+    bcc2e3484ca8fb1c36143cb536ab0a941c098ef3d254b10c2575008e7f9fad30\
+    1ccf59a6842d62c7c03a04c1f1cae02219ff7be0d9a0022ad3db75d4c83bed77\
+    603a8fb7f630d60823df418e939a1c20d2eb6dcc0f1b0a21da49efadb43f6f8a\
+    3943b696908c6b69048494526f99af07a430b24dd318558125ea0ebd9291a547\
+    ce1b5a79e7ad5b129232f502dcd794623c972999";
+
+  #[test]
+  fn xmss_verify_kat()
+  {
+    let pk = hex::decode(PK).unwrap();
+    let mut sig = hex::decode(SIG).unwrap();
+    assert_eq!(xmss_verify(&sig, b"crystals", &pk), Ok(()));
+    assert_eq!(xmss_verify(&sig, b"crystalz", &pk), Err(SignError::Verify));
+    assert_eq!(
+      xmss_verify(&sig[1..], b"crystals", &pk),
+      Err(SignError::Input)
+    );
+
+    let last = sig.len() - 1;
+    sig[last] ^= 1;
+    assert_eq!(xmss_verify(&sig, b"crystals", &pk), Err(SignError::Verify));
+  }
+
+  // Signing rebuilds the whole tree, so the round trip uses a height 4
+  // tree instead of one of the standard parameter sets
+  #[cfg(feature = "offchain")]
+  #[test]
+  fn xmss_sign_verify()
+  {
+    let p = XmssParams {
+      h: 4,
+      ..XMSS_SHAKE256_10_256
+    };
+    let seed: Vec<u8> = (0u8..96).collect();
+    let key = XmssPrivateKey::from_seed(p, &seed).unwrap();
+    let pk = key.public();
+
+    let path = std::env::temp_dir()
+      .join(format!("crystals-xmss-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut state = FileState::create(&path, &pk).unwrap();
+    for _ in 0..3 {
+      let sig = key.sign(b"crystals", &mut state).unwrap();
+      assert_eq!(verify_with(&p, &sig, b"crystals", &pk), Ok(()));
+    }
+    assert_eq!(state.load(), Ok(3));
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -1,8 +1,11 @@
 use crate::fips202::*;
+use crate::fips202x4::*;
 use crate::params::{CRHBYTES, SEEDBYTES};
 
 pub type Stream128State = KeccakState;
 pub type Stream256State = KeccakState;
+pub type Stream128Statex4 = KeccakStatex4;
+pub type Stream256Statex4 = KeccakStatex4;
 
 pub const STREAM128_BLOCKBYTES: usize = SHAKE128_RATE;
 pub const STREAM256_BLOCKBYTES: usize = SHAKE256_RATE;
@@ -59,4 +62,52 @@ pub fn dilithium_shake256_stream_init(
   shake256_absorb(state, seed, CRHBYTES);
   shake256_absorb(state, &t, 2);
   shake256_finalize(state);
-}
\ No newline at end of file
+}
+
+/// Absorbs seed|nonce[l] into lane l of four SHAKE128 streams.
+pub fn stream128x4_init(
+  state: &mut Stream128Statex4,
+  seed: &[u8],
+  nonce: [u16; 4],
+) {
+  let mut inputs = [[0u8; SEEDBYTES + 2]; 4];
+  for (input, nonce) in inputs.iter_mut().zip(nonce) {
+    input[..SEEDBYTES].copy_from_slice(&seed[..SEEDBYTES]);
+    input[SEEDBYTES] = nonce as u8;
+    input[SEEDBYTES + 1] = (nonce >> 8) as u8;
+  }
+  let [in0, in1, in2, in3] = &inputs;
+  shake128x4_absorb_once(state, [in0, in1, in2, in3], SEEDBYTES + 2);
+}
+
+pub fn stream128x4_squeezeblocks(
+  out: [&mut [u8]; 4],
+  outblocks: u64,
+  state: &mut Stream128Statex4,
+) {
+  shake128x4_squeezeblocks(out, outblocks as usize, state);
+}
+
+/// Absorbs seed|nonce[l] into lane l of four SHAKE256 streams.
+pub fn stream256x4_init(
+  state: &mut Stream256Statex4,
+  seed: &[u8],
+  nonce: [u16; 4],
+) {
+  let mut inputs = [[0u8; CRHBYTES + 2]; 4];
+  for (input, nonce) in inputs.iter_mut().zip(nonce) {
+    input[..CRHBYTES].copy_from_slice(&seed[..CRHBYTES]);
+    input[CRHBYTES] = nonce as u8;
+    input[CRHBYTES + 1] = (nonce >> 8) as u8;
+  }
+  let [in0, in1, in2, in3] = &inputs;
+  shake256x4_absorb_once(state, [in0, in1, in2, in3], CRHBYTES + 2);
+}
+
+pub fn stream256x4_squeezeblocks(
+  out: [&mut [u8]; 4],
+  outblocks: u64,
+  state: &mut Stream256Statex4,
+) {
+  shake256x4_squeezeblocks(out, outblocks as usize, state);
+}
@@ -0,0 +1,125 @@
+/* Exercises the C API through include/crystals.h; run by tests/capi.rs. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "crystals.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+  do {                                                                \
+    if (!(cond)) {                                                    \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+              #cond);                                                 \
+      failures++;                                                     \
+    }                                                                 \
+  } while (0)
+
+#define CHECK_CODE(call, code)                                         \
+  do {                                                                 \
+    int rc_ = (call);                                                  \
+    if (rc_ != (code)) {                                               \
+      fprintf(stderr, "%s:%d: %s returned %d (%s), expected %d\n",     \
+              __FILE__, __LINE__, #call, rc_, crystals_strerror(rc_),  \
+              (code));                                                 \
+      failures++;                                                      \
+    }                                                                  \
+  } while (0)
+
+int main(void)
+{
+  static uint8_t pk[CRYSTALS_PUBLICKEYBYTES], sk[CRYSTALS_SECRETKEYBYTES];
+  static uint8_t pk2[CRYSTALS_PUBLICKEYBYTES], sk2[CRYSTALS_SECRETKEYBYTES];
+  static uint8_t sig[CRYSTALS_SIGNBYTES];
+  static uint8_t sm[CRYSTALS_SIGNBYTES + 64], opened[64];
+  uint8_t seed[CRYSTALS_SEEDBYTES];
+  const uint8_t msg[] = "matching engine order 42";
+  size_t written = 0;
+
+  /* the header matches the library */
+  CHECK(crystals_mode() == CRYSTALS_MODE);
+  CHECK(crystals_public_key_bytes() == CRYSTALS_PUBLICKEYBYTES);
+  CHECK(crystals_secret_key_bytes() == CRYSTALS_SECRETKEYBYTES);
+  CHECK(crystals_signature_bytes() == CRYSTALS_SIGNBYTES);
+  CHECK(crystals_seed_bytes() == CRYSTALS_SEEDBYTES);
+  CHECK(strcmp(crystals_strerror(CRYSTALS_OK), "success") == 0);
+  CHECK(strcmp(crystals_strerror(12345), "unknown error") == 0);
+
+  /* seeded keys are deterministic */
+  memset(seed, 7, sizeof seed);
+  CHECK_CODE(crystals_keypair_from_seed(seed, sizeof seed, pk, sizeof pk, sk,
+                                        sizeof sk),
+             CRYSTALS_OK);
+  CHECK_CODE(crystals_keypair_from_seed(seed, sizeof seed, pk2, sizeof pk2,
+                                        sk2, sizeof sk2),
+             CRYSTALS_OK);
+  CHECK(memcmp(pk, pk2, sizeof pk) == 0 && memcmp(sk, sk2, sizeof sk) == 0);
+  CHECK_CODE(crystals_keypair(pk2, sizeof pk2, sk2, sizeof sk2), CRYSTALS_OK);
+  CHECK(memcmp(pk, pk2, sizeof pk) != 0);
+
+  /* detached */
+  CHECK_CODE(crystals_sign(sig, sizeof sig, msg, sizeof msg, sk, sizeof sk),
+             CRYSTALS_OK);
+  CHECK_CODE(crystals_verify(sig, sizeof sig, msg, sizeof msg, pk, sizeof pk),
+             CRYSTALS_OK);
+  CHECK_CODE(
+    crystals_verify(sig, sizeof sig, msg, sizeof msg, pk2, sizeof pk2),
+    CRYSTALS_ERROR_VERIFY);
+  sig[0] ^= 1;
+  CHECK_CODE(crystals_verify(sig, sizeof sig, msg, sizeof msg, pk, sizeof pk),
+             CRYSTALS_ERROR_VERIFY);
+
+  /* an empty message may be null */
+  CHECK_CODE(crystals_sign(sig, sizeof sig, NULL, 0, sk, sizeof sk),
+             CRYSTALS_OK);
+  CHECK_CODE(crystals_verify(sig, sizeof sig, NULL, 0, pk, sizeof pk),
+             CRYSTALS_OK);
+
+  /* attached */
+  CHECK_CODE(crystals_sign_attached(sm, sizeof sm, &written, msg, sizeof msg,
+                                    sk, sizeof sk),
+             CRYSTALS_OK);
+  CHECK(written == CRYSTALS_SIGNBYTES + sizeof msg);
+  CHECK_CODE(
+    crystals_open(opened, sizeof opened, &written, sm, written, pk, sizeof pk),
+    CRYSTALS_OK);
+  CHECK(written == sizeof msg && memcmp(opened, msg, sizeof msg) == 0);
+  sm[CRYSTALS_SIGNBYTES] ^= 1;
+  memset(opened, 0, sizeof opened);
+  CHECK_CODE(crystals_open(opened, sizeof opened, &written, sm,
+                           CRYSTALS_SIGNBYTES + sizeof msg, pk, sizeof pk),
+             CRYSTALS_ERROR_VERIFY);
+  CHECK(opened[0] == 0);
+  CHECK_CODE(crystals_open(opened, sizeof opened, &written, sm, 10, pk,
+                           sizeof pk),
+             CRYSTALS_ERROR_VERIFY);
+
+  /* buffers and pointers */
+  CHECK_CODE(crystals_sign(sig, sizeof sig - 1, msg, sizeof msg, sk, sizeof sk),
+             CRYSTALS_ERROR_LENGTH);
+  CHECK_CODE(crystals_sign(sig, sizeof sig, msg, sizeof msg, sk, sizeof sk - 1),
+             CRYSTALS_ERROR_LENGTH);
+  CHECK_CODE(crystals_sign(NULL, sizeof sig, msg, sizeof msg, sk, sizeof sk),
+             CRYSTALS_ERROR_NULL);
+  CHECK_CODE(crystals_sign(sig, sizeof sig, NULL, 1, sk, sizeof sk),
+             CRYSTALS_ERROR_NULL);
+  CHECK_CODE(crystals_verify(sig, sizeof sig, msg, sizeof msg, pk, 1),
+             CRYSTALS_ERROR_LENGTH);
+  CHECK_CODE(crystals_keypair_from_seed(seed, 16, pk, sizeof pk, sk, sizeof sk),
+             CRYSTALS_ERROR_LENGTH);
+  CHECK_CODE(crystals_keypair(pk, sizeof pk, NULL, 0), CRYSTALS_ERROR_NULL);
+  CHECK_CODE(crystals_sign_attached(sm, CRYSTALS_SIGNBYTES, &written, msg,
+                                    sizeof msg, sk, sizeof sk),
+             CRYSTALS_ERROR_LENGTH);
+  CHECK_CODE(crystals_sign_attached(sm, sizeof sm, NULL, msg, sizeof msg, sk,
+                                    sizeof sk),
+             CRYSTALS_ERROR_NULL);
+
+  if (failures) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return EXIT_FAILURE;
+  }
+  printf("ok\n");
+  return EXIT_SUCCESS;
+}
@@ -0,0 +1,122 @@
+//! Generates `include/crystals.h` from `src/capi.rs` with cbindgen and
+//! `cbindgen.toml`, checks that the checked-in header is current, then
+//! builds `tests/c/capi_test.c` against the cdylib and runs it.
+//!
+//! After changing the C API, regenerate the header with
+//! `CRYSTALS_UPDATE_HEADER=1 cargo test --features capi --test capi`.
+
+use std::{env, fs, path::PathBuf, process::Command};
+
+use crystals::{PUBLICKEYBYTES, SECRETKEYBYTES, SEEDBYTES, SIGNBYTES};
+
+/// (mode, public key, secret key, signature) sizes
+const SIZES: [(u32, usize, usize, usize); 3] = [
+  (2, 1312, 2528, 2420),
+  (3, 1952, 4000, 3293),
+  (5, 2592, 4864, 4595),
+];
+
+fn manifest_dir() -> PathBuf
+{
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// The header cbindgen generates from src/capi.rs with cbindgen.toml
+fn header() -> String
+{
+  let dir = manifest_dir();
+  let config = cbindgen::Config::from_file(dir.join("cbindgen.toml")).unwrap();
+  let mut out = vec![];
+  cbindgen::Builder::new()
+    .with_config(config)
+    .with_src(dir.join("src/capi.rs"))
+    .generate()
+    .expect("cbindgen failed on src/capi.rs")
+    .write(&mut out);
+  String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn sizes_match_build()
+{
+  let mode = SIZES
+    .iter()
+    .find(|(_, pk, ..)| *pk == PUBLICKEYBYTES)
+    .unwrap();
+  assert_eq!(
+    (mode.1, mode.2, mode.3),
+    (PUBLICKEYBYTES, SECRETKEYBYTES, SIGNBYTES)
+  );
+  assert_eq!(mode.0 as i32, crystals::capi::crystals_mode());
+
+  // the size block of cbindgen.toml
+  let header = header();
+  for (mode, pk, sk, sig) in SIZES {
+    let define = format!(
+      "#define CRYSTALS_MODE {}\n#define CRYSTALS_PUBLICKEYBYTES {}\n\
+       #define CRYSTALS_SECRETKEYBYTES {}\n#define CRYSTALS_SIGNBYTES {}\n",
+      mode, pk, sk, sig
+    );
+    assert!(header.contains(&define), "mode {} sizes", mode);
+  }
+  let seed = format!("#define CRYSTALS_SEEDBYTES {}\n", SEEDBYTES);
+  assert!(header.contains(&seed));
+}
+
+#[test]
+fn header_is_current()
+{
+  let path = manifest_dir().join("include/crystals.h");
+  let header = header();
+  if env::var_os("CRYSTALS_UPDATE_HEADER").is_some() {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, &header).unwrap();
+  }
+  let current = fs::read_to_string(&path).unwrap_or_default();
+  assert!(
+    current == header,
+    "include/crystals.h is stale; run with CRYSTALS_UPDATE_HEADER=1"
+  );
+}
+
+#[cfg(unix)]
+#[test]
+fn c_program()
+{
+  // the cdylib built for this test run sits next to the test binary in
+  // target/<profile>/deps; target/<profile> may hold an older copy
+  let exe = env::current_exe().unwrap();
+  let lib_dir = exe.parent().unwrap();
+  let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("capi_test");
+  let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+  let mode = format!("-DCRYSTALS_MODE{}", crystals::capi::crystals_mode());
+
+  let status = Command::new(cc)
+    .arg(manifest_dir().join("tests/c/capi_test.c"))
+    .arg("-std=c99")
+    .args(["-Wall", "-Wextra", "-Werror", &mode])
+    .arg("-I")
+    .arg(manifest_dir().join("include"))
+    .arg("-L")
+    .arg(lib_dir)
+    .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+    .arg("-lcrystals")
+    .arg("-o")
+    .arg(&out)
+    .status()
+    .expect("a C compiler is needed, set CC");
+  assert!(status.success(), "compiling tests/c/capi_test.c failed");
+
+  // cargo's own library path would otherwise win over the rpath
+  let output = Command::new(&out)
+    .env("LD_LIBRARY_PATH", lib_dir)
+    .env("DYLD_LIBRARY_PATH", lib_dir)
+    .output()
+    .unwrap();
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+}
@@ -0,0 +1,112 @@
+//! NIST ACVP vectors for the SLH-DSA internal functions, FIPS 205
+//! algorithms 18, 19 and 20.
+//!
+//! `tests/data/slhdsa` holds the SHAKE test groups of the ACVP-Server
+//! sample vector set 53 (`gen-val/json-files/SLH-DSA-*-FIPS205`,
+//! `internalProjection.json`), as vendored in the fips205 0.4.1 crate:
+//! key generation for SHAKE-256f, the first three deterministic and
+//! hedged signatures for SHAKE-128f, and every SHAKE-128f verification
+//! case. Test cases are copied unchanged. The set has no SHAKE-128s
+//! groups.
+
+use crystals::slhdsa::*;
+use serde_json::Value;
+
+fn load(name: &str) -> Value
+{
+  let path = format!("{}/tests/data/slhdsa/{name}", env!("CARGO_MANIFEST_DIR"));
+  serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn bytes(v: &Value) -> Vec<u8>
+{
+  hex::decode(v.as_str().unwrap()).unwrap()
+}
+
+fn params(name: &Value) -> ParameterSet
+{
+  match name.as_str().unwrap() {
+    "SLH-DSA-SHAKE-128f" => SLH_DSA_SHAKE_128F,
+    "SLH-DSA-SHAKE-256f" => SLH_DSA_SHAKE_256F,
+    name => panic!("unexpected parameter set {name}"),
+  }
+}
+
+/// (parameter set, test case) for every case in the file
+fn cases(name: &str) -> Vec<(ParameterSet, Value)>
+{
+  let file = load(name);
+  let groups = file["testGroups"].as_array().unwrap();
+  let cases: Vec<_> = groups
+    .iter()
+    .flat_map(|g| {
+      let p = params(&g["parameterSet"]);
+      g["tests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(move |t| (p, t.clone()))
+    })
+    .collect();
+  assert!(!cases.is_empty());
+  cases
+}
+
+#[test]
+fn key_generation()
+{
+  for (p, t) in cases("SLH-DSA-keyGen-FIPS205.json") {
+    let mut pk = vec![0u8; p.public_key_bytes()];
+    let mut sk = vec![0u8; p.secret_key_bytes()];
+    slh_keygen_internal(
+      &p,
+      &mut pk,
+      &mut sk,
+      &bytes(&t["skSeed"]),
+      &bytes(&t["skPrf"]),
+      &bytes(&t["pkSeed"]),
+    )
+    .unwrap();
+    assert_eq!(pk, bytes(&t["pk"]), "tcId {}", t["tcId"]);
+    assert_eq!(sk, bytes(&t["sk"]), "tcId {}", t["tcId"]);
+  }
+}
+
+#[test]
+fn signature_generation()
+{
+  for (p, t) in cases("SLH-DSA-sigGen-FIPS205.json") {
+    let mut sig = vec![0u8; p.signature_bytes()];
+    let addrnd = t.get("additionalRandomness").map(bytes);
+    slh_sign_internal(
+      &p,
+      &mut sig,
+      &[],
+      &bytes(&t["message"]),
+      &bytes(&t["sk"]),
+      addrnd.as_deref(),
+    )
+    .unwrap();
+    assert!(sig == bytes(&t["signature"]), "tcId {}", t["tcId"]);
+  }
+}
+
+#[test]
+fn signature_verification()
+{
+  for (p, t) in cases("SLH-DSA-sigVer-FIPS205.json") {
+    let result = slh_verify_internal(
+      &p,
+      &bytes(&t["signature"]),
+      &bytes(&t["message"]),
+      &bytes(&t["pk"]),
+    );
+    assert_eq!(
+      result.is_ok(),
+      t["testPassed"].as_bool().unwrap(),
+      "tcId {}: {}",
+      t["tcId"],
+      t["reason"]
+    );
+  }
+}